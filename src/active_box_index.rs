@@ -0,0 +1,321 @@
+use std::collections::{HashMap, HashSet};
+
+use emerald::{Entity, World};
+
+use crate::{hitboxes::Hitbox, hurtboxes::Hurtbox};
+
+/// Active hitboxes and hurtboxes of a world, kept up to date as boxes are loaded and activated,
+/// so finding the active boxes doesn't need to scan every box each frame.
+///
+/// Lives on an entity of its own in the world it indexes, spawned when the first hitbox or
+/// hurtbox set is loaded. Worlds without one fall back to scanning.
+///
+/// Loaded boxes, and boxes activated by the systems and the functions taking the world,
+/// ex. `set_hurtbox_active`, are indexed as they change. Deactivated and despawned boxes are
+/// dropped the next time the index is read. Boxes spawned by hand, or activated directly
+/// with `Hitbox::activate` or by writing `Hurtbox::active`, are only picked up once
+/// `mark_active_boxes_stale` is called.
+#[derive(Clone, Debug)]
+pub struct ActiveBoxIndex {
+    hitboxes: HashSet<Entity>,
+    hurtboxes: HashSet<Entity>,
+    stale: bool,
+}
+impl ActiveBoxIndex {
+    /// Creates a stale index, which is rebuilt the first time it's read.
+    pub fn new() -> Self {
+        Self {
+            hitboxes: HashSet::new(),
+            hurtboxes: HashSet::new(),
+            stale: true,
+        }
+    }
+
+    /// Forces a full rebuild the next time the index is read.
+    pub fn mark_stale(&mut self) {
+        self.stale = true;
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// Rebuilds the index by scanning every hitbox and hurtbox in the world.
+    pub fn rebuild(&mut self, world: &World) {
+        self.hitboxes = scan_active_hitboxes(world).into_iter().collect();
+        self.hurtboxes = scan_active_hurtboxes(world).into_iter().collect();
+        self.stale = false;
+    }
+
+    pub fn insert_hitbox(&mut self, id: Entity) {
+        self.hitboxes.insert(id);
+    }
+
+    pub fn remove_hitbox(&mut self, id: Entity) {
+        self.hitboxes.remove(&id);
+    }
+
+    pub fn insert_hurtbox(&mut self, id: Entity) {
+        self.hurtboxes.insert(id);
+    }
+
+    pub fn remove_hurtbox(&mut self, id: Entity) {
+        self.hurtboxes.remove(&id);
+    }
+
+    /// Returns the active hitboxes, rebuilding the index first if it's stale.
+    pub fn active_hitboxes(&mut self, world: &World) -> Vec<Entity> {
+        if self.stale {
+            self.rebuild(world);
+        }
+
+        self.hitboxes.retain(|id| {
+            world
+                .get::<&Hitbox>(*id)
+                .map(|hitbox| hitbox.is_active())
+                .unwrap_or(false)
+        });
+        sorted(&self.hitboxes)
+    }
+
+    /// Returns the active hurtboxes, rebuilding the index first if it's stale.
+    pub fn active_hurtboxes(&mut self, world: &World) -> Vec<Entity> {
+        if self.stale {
+            self.rebuild(world);
+        }
+
+        self.hurtboxes.retain(|id| {
+            world
+                .get::<&Hurtbox>(*id)
+                .map(|hurtbox| hurtbox.active)
+                .unwrap_or(false)
+        });
+        sorted(&self.hurtboxes)
+    }
+}
+impl Default for ActiveBoxIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Spawns the index of the world, unless it already has one.
+pub(crate) fn ensure_active_box_index(world: &mut World) {
+    if world.query::<&ActiveBoxIndex>().iter().next().is_none() {
+        world.spawn((ActiveBoxIndex::new(),));
+    }
+}
+
+/// Calls the function with the index of the world, if it has one.
+fn with_active_box_index<T>(world: &World, f: impl FnOnce(&mut ActiveBoxIndex) -> T) -> Option<T> {
+    world
+        .query::<&mut ActiveBoxIndex>()
+        .iter()
+        .next()
+        .map(|(_, index)| f(index))
+}
+
+/// Returns the active hitboxes of the world, from its index if it has one.
+pub(crate) fn get_indexed_active_hitboxes(world: &World) -> Vec<Entity> {
+    with_active_box_index(world, |index| index.active_hitboxes(world))
+        .unwrap_or_else(|| scan_active_hitboxes(world))
+}
+
+/// Returns the active hurtboxes of the world, from its index if it has one.
+pub(crate) fn get_indexed_active_hurtboxes(world: &World) -> Vec<Entity> {
+    with_active_box_index(world, |index| index.active_hurtboxes(world))
+        .unwrap_or_else(|| scan_active_hurtboxes(world))
+}
+
+/// Records a hitbox that was just activated or loaded active.
+pub(crate) fn index_hitbox(world: &World, id: Entity) {
+    with_active_box_index(world, |index| index.insert_hitbox(id));
+}
+
+/// Records a hurtbox that was just activated or loaded active.
+pub(crate) fn index_hurtbox(world: &World, id: Entity) {
+    with_active_box_index(world, |index| index.insert_hurtbox(id));
+}
+
+/// Forces the index of the world to rebuild on its next read.
+/// Call after spawning boxes by hand, or activating them directly, see `ActiveBoxIndex`.
+pub fn mark_active_boxes_stale(world: &World) {
+    with_active_box_index(world, |index| index.mark_stale());
+}
+
+/// Keeps one index after a world merge, since the merged world brings its own along.
+/// The kept index is rebuilt, so it covers the merged boxes.
+pub(crate) fn merge_active_box_indexes(
+    new_world: &mut World,
+    entity_map: &HashMap<Entity, Entity>,
+) {
+    let merged = entity_map
+        .values()
+        .filter(|id| new_world.has::<ActiveBoxIndex>(**id))
+        .cloned()
+        .collect::<Vec<Entity>>();
+    let kept = new_world
+        .query::<&ActiveBoxIndex>()
+        .iter()
+        .map(|(id, _)| id)
+        .find(|id| !merged.contains(id));
+
+    if kept.is_some() {
+        merged.into_iter().for_each(|id| {
+            new_world.despawn(id).ok();
+        });
+    }
+
+    mark_active_boxes_stale(new_world);
+}
+
+/// Returns the entities in a stable order, so results don't depend on hashing.
+fn sorted(entities: &HashSet<Entity>) -> Vec<Entity> {
+    let mut entities = entities.iter().cloned().collect::<Vec<Entity>>();
+    entities.sort_by_key(|id| id.to_bits());
+    entities
+}
+
+fn scan_active_hitboxes(world: &World) -> Vec<Entity> {
+    world
+        .query::<&Hitbox>()
+        .iter()
+        .filter_map(|(id, hitbox)| hitbox.is_active().then(|| id))
+        .collect()
+}
+
+fn scan_active_hurtboxes(world: &World) -> Vec<Entity> {
+    world
+        .query::<&Hurtbox>()
+        .iter()
+        .filter_map(|(id, hurtbox)| hurtbox.active.then(|| id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use emerald::World;
+
+    use crate::{
+        active_box_index::{
+            get_indexed_active_hitboxes, get_indexed_active_hurtboxes, mark_active_boxes_stale,
+            merge_active_box_indexes, ActiveBoxIndex,
+        },
+        collision_tests::{spawn_hitbox_set, spawn_hurtbox_set},
+        hitboxes::{set_hitbox_active, Hitbox},
+        hurtboxes::{set_hurtbox_active, Hurtbox},
+    };
+
+    const HITBOX_SET_TOML: &str = r#"
+        [hitboxes.punch]
+        active = true
+        colliders = [{ width = 10.0, height = 10.0 }]
+    "#;
+
+    const INACTIVE_HITBOX_SET_TOML: &str = r#"
+        [hitboxes.punch]
+        colliders = [{ width = 10.0, height = 10.0 }]
+    "#;
+
+    fn index_is_stale(world: &World) -> bool {
+        world
+            .query::<&ActiveBoxIndex>()
+            .iter()
+            .next()
+            .unwrap()
+            .1
+            .is_stale()
+    }
+
+    #[test]
+    fn index_tracks_activation_and_deactivation() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, INACTIVE_HITBOX_SET_TOML);
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+
+        assert!(get_indexed_active_hitboxes(&world).is_empty());
+        assert_eq!(get_indexed_active_hurtboxes(&world), vec![hurtbox]);
+        assert!(!index_is_stale(&world));
+
+        set_hitbox_active(&mut world, hitbox, true);
+        set_hurtbox_active(&mut world, hurtbox, false);
+        assert_eq!(get_indexed_active_hitboxes(&world), vec![hitbox]);
+        assert!(get_indexed_active_hurtboxes(&world).is_empty());
+
+        set_hurtbox_active(&mut world, hurtbox, true);
+        set_hitbox_active(&mut world, hitbox, false);
+        assert!(get_indexed_active_hitboxes(&world).is_empty());
+        assert_eq!(get_indexed_active_hurtboxes(&world), vec![hurtbox]);
+        assert!(!index_is_stale(&world));
+    }
+
+    #[test]
+    fn loaded_and_despawned_boxes_are_indexed_without_a_rebuild() {
+        let mut world = World::new();
+        let (_, first) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        assert_eq!(get_indexed_active_hitboxes(&world), vec![first]);
+
+        let (_, second) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        assert!(!index_is_stale(&world));
+        let active = get_indexed_active_hitboxes(&world)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(active, HashSet::from([first, second]));
+
+        world.despawn(first).unwrap();
+        assert_eq!(get_indexed_active_hitboxes(&world), vec![second]);
+    }
+
+    #[test]
+    fn direct_activations_are_picked_up_once_marked_stale() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, INACTIVE_HITBOX_SET_TOML);
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world.get::<&mut Hurtbox>(hurtbox).unwrap().active = false;
+        assert!(get_indexed_active_hitboxes(&world).is_empty());
+        assert!(get_indexed_active_hurtboxes(&world).is_empty());
+
+        world.get::<&mut Hitbox>(hitbox).unwrap().activate();
+        world.get::<&mut Hurtbox>(hurtbox).unwrap().active = true;
+        mark_active_boxes_stale(&world);
+
+        assert_eq!(get_indexed_active_hitboxes(&world), vec![hitbox]);
+        assert_eq!(get_indexed_active_hurtboxes(&world), vec![hurtbox]);
+    }
+
+    #[test]
+    fn worlds_keep_their_own_index() {
+        let mut world = World::new();
+        let mut other_world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (_, other_hitbox) = spawn_hitbox_set(&mut other_world, HITBOX_SET_TOML);
+
+        assert_eq!(get_indexed_active_hitboxes(&world), vec![hitbox]);
+        assert_eq!(
+            get_indexed_active_hitboxes(&other_world),
+            vec![other_hitbox]
+        );
+    }
+
+    #[test]
+    fn merges_keep_a_single_rebuilt_index() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        assert_eq!(get_indexed_active_hitboxes(&world), vec![hitbox]);
+
+        // The merge brings in a box, along with the index of the world it came from
+        let merged_index = world.spawn((ActiveBoxIndex::new(),));
+        let (_, merged_hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let entity_map = HashMap::from([(merged_index, merged_index)]);
+        merge_active_box_indexes(&mut world, &entity_map);
+
+        assert!(!world.contains(merged_index));
+        assert_eq!(world.query::<&ActiveBoxIndex>().iter().count(), 1);
+        let active = get_indexed_active_hitboxes(&world)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(active, HashSet::from([hitbox, merged_hitbox]));
+    }
+}
@@ -0,0 +1,98 @@
+use emerald::{EmeraldError, Entity, World};
+
+use crate::hitboxes::get_hitbox_owner;
+use crate::hurtboxes::get_hurtbox_owner;
+
+/// Marks an entity whose combat is paused until resumed, ex. a stunned enemy.
+/// The entity and every hitbox and hurtbox it owns receive a delta of 0
+/// from `HitmeConfig::get_delta_for_entity`, including boxes spawned during the pause.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CombatPaused;
+
+/// Pauses the combat of the entity and of its hitboxes and hurtboxes,
+/// freezing its sequences and cooldowns while the rest of the game runs.
+pub fn pause_entity_combat(world: &mut World, id: Entity) -> Result<(), EmeraldError> {
+    world.insert_one(id, CombatPaused)?;
+
+    Ok(())
+}
+
+/// Resumes the combat of the entity and of its hitboxes and hurtboxes.
+pub fn resume_entity_combat(world: &mut World, id: Entity) {
+    world.remove_one::<CombatPaused>(id).ok();
+}
+
+/// Whether the entity, or the owner of the hitbox or hurtbox, has its combat paused.
+pub fn is_combat_paused(world: &World, id: Entity) -> bool {
+    get_hitbox_owner(world, id)
+        .or_else(|| get_hurtbox_owner(world, id))
+        .into_iter()
+        .chain([id])
+        .any(|id| world.has::<CombatPaused>(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use emerald::{toml::Value, Group, Transform, World};
+
+    use crate::{
+        collision_tests::spawn_hitbox_set,
+        combat_pause::{is_combat_paused, pause_entity_combat, resume_entity_combat},
+        hitboxes::{load_hitbox_set, HitboxSet},
+        HitmeConfig,
+    };
+
+    #[test]
+    fn paused_entity_sequence_does_not_advance() {
+        let mut world = World::new();
+        let toml = r#"
+            [hitboxes.hitbox]
+
+            [sequences]
+            attack = [{ duration = 0.25 }, { duration = 0.25 }]
+        "#;
+        let (paused, hitbox) = spawn_hitbox_set(&mut world, toml);
+        let (running, _) = spawn_hitbox_set(&mut world, toml);
+        pause_entity_combat(&mut world, paused).unwrap();
+        assert!(is_combat_paused(&world, hitbox));
+
+        let config = HitmeConfig::default();
+        for owner in [paused, running] {
+            let delta = config.get_delta_for_entity_with(&world, owner, false, || 0.375);
+            let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+            hitbox_set.start_sequence("attack").unwrap();
+            hitbox_set.progress_active_sequence(delta);
+        }
+        let frame = |world: &World, owner| {
+            let hitbox_set = world.get::<&HitboxSet>(owner).unwrap();
+            hitbox_set
+                .active_sequence
+                .as_ref()
+                .map(|sequence| sequence.frame)
+        };
+        assert_eq!(frame(&world, paused), Some(0));
+        assert_ne!(frame(&world, running), Some(0));
+
+        resume_entity_combat(&mut world, paused);
+        assert!(!is_combat_paused(&world, paused) && !is_combat_paused(&world, hitbox));
+    }
+
+    #[test]
+    fn boxes_spawned_during_a_pause_are_paused() {
+        let mut world = World::new();
+        let owner = world.spawn((Transform::default(),));
+        pause_entity_combat(&mut world, owner).unwrap();
+
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                [hitboxes.hitbox]
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        )
+        .unwrap();
+        let (_, hitboxes) =
+            load_hitbox_set(&mut world, &value, owner, Group::GROUP_1, Group::GROUP_2).unwrap();
+
+        assert!(is_combat_paused(&world, hitboxes[0]));
+    }
+}
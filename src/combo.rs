@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use emerald::{Entity, World};
+
+use crate::{host::HitmeHost, HitmeConfig};
+
+/// Tracks how many hits an entity has taken in a row.
+/// The combo resets once the entity goes `HitmeConfig::combo_timeout` without being hit.
+#[derive(Clone, Debug, Default)]
+pub struct ComboTracker {
+    pub hits: u32,
+
+    /// Time elapsed since the last hit landed
+    pub elapsed_time: f32,
+}
+impl ComboTracker {
+    pub fn register_hit(&mut self) -> u32 {
+        self.hits += 1;
+        self.elapsed_time = 0.0;
+        self.hits
+    }
+
+    /// Returns the damage scaling of the latest hit, each hit after the first
+    /// is scaled by `scaling_per_hit` again, down to `min_scaling`.
+    pub fn scaling(&self, scaling_per_hit: f32, min_scaling: f32) -> f32 {
+        let scaled_hits = self.hits.saturating_sub(1) as i32;
+        scaling_per_hit.powi(scaled_hits).max(min_scaling)
+    }
+
+    pub fn reset(&mut self) {
+        self.hits = 0;
+        self.elapsed_time = 0.0;
+    }
+}
+
+/// Tracks the combo of an attacker against each entity it hits, for damage scaling.
+/// Each combo resets once the attacker goes `HitmeConfig::combo_timeout` without hitting
+/// that entity, independently of other attackers hitting it.
+#[derive(Clone, Debug, Default)]
+pub struct AttackerComboTracker {
+    /// Combos by the entity being hit
+    pub combos: HashMap<Entity, ComboTracker>,
+}
+
+/// Registers a hit against the given entity, returning the length of its current combo.
+/// Inserts a `ComboTracker` onto the entity if it doesn't have one yet.
+pub fn register_combo_hit(world: &mut World, id: Entity) -> u32 {
+    if let Ok(mut tracker) = world.get::<&mut ComboTracker>(id) {
+        return tracker.register_hit();
+    }
+
+    let mut tracker = ComboTracker::default();
+    let hits = tracker.register_hit();
+    world.insert_one(id, tracker).ok();
+
+    hits
+}
+
+pub fn get_combo_hits(world: &World, id: Entity) -> u32 {
+    world
+        .get::<&ComboTracker>(id)
+        .ok()
+        .map(|tracker| tracker.hits)
+        .unwrap_or(0)
+}
+
+/// Registers a hit of the attacker against the victim, returning the length of their combo.
+/// Inserts an `AttackerComboTracker` onto the attacker if it doesn't have one yet.
+pub fn register_attacker_combo_hit(world: &mut World, attacker: Entity, victim: Entity) -> u32 {
+    if let Ok(mut tracker) = world.get::<&mut AttackerComboTracker>(attacker) {
+        return tracker.combos.entry(victim).or_default().register_hit();
+    }
+
+    let mut tracker = AttackerComboTracker::default();
+    let hits = tracker.combos.entry(victim).or_default().register_hit();
+    world.insert_one(attacker, tracker).ok();
+
+    hits
+}
+
+/// Returns the damage scaling of the attacker's current combo against the victim,
+/// see `HitmeConfig::combo_scaling`.
+pub fn get_combo_scaling(
+    world: &World,
+    config: &HitmeConfig,
+    attacker: Entity,
+    victim: Entity,
+) -> f32 {
+    world
+        .get::<&AttackerComboTracker>(attacker)
+        .ok()
+        .map(|tracker| {
+            tracker
+                .combos
+                .get(&victim)
+                .map(|combo| combo.scaling(config.combo_scaling, config.combo_min_scaling))
+        })
+        .flatten()
+        .unwrap_or(1.0)
+}
+
+/// Decays combos that haven't been added to within the configured timeout.
+pub(crate) fn combo_system(host: &mut impl HitmeHost, world: &mut World, config: &HitmeConfig) {
+    let timeout = match config.combo_timeout {
+        Some(timeout) => timeout,
+        None => return,
+    };
+
+    for (id, tracker) in world.query::<&mut ComboTracker>().iter() {
+        if tracker.hits == 0 {
+            continue;
+        }
+
+        tracker.elapsed_time += config.get_host_delta_for_entity(host, world, id);
+        if tracker.elapsed_time >= timeout {
+            tracker.reset();
+        }
+    }
+
+    for (id, tracker) in world.query::<&mut AttackerComboTracker>().iter() {
+        if tracker.combos.is_empty() {
+            continue;
+        }
+
+        let delta = config.get_host_delta_for_entity(host, world, id);
+        advance_attacker_combos(tracker, delta, timeout);
+    }
+}
+
+/// Advances the attacker's combos, dropping those that went `timeout` without a hit.
+pub(crate) fn advance_attacker_combos(
+    tracker: &mut AttackerComboTracker,
+    delta: f32,
+    timeout: f32,
+) {
+    for combo in tracker.combos.values_mut() {
+        combo.elapsed_time += delta;
+    }
+    tracker
+        .combos
+        .retain(|_, combo| combo.elapsed_time < timeout);
+}
+
+#[cfg(test)]
+mod tests {
+    use emerald::{Transform, World};
+
+    use crate::combo::{
+        advance_attacker_combos, get_combo_hits, get_combo_scaling, register_attacker_combo_hit,
+        register_combo_hit, AttackerComboTracker, ComboTracker,
+    };
+    use crate::HitmeConfig;
+
+    #[test]
+    fn consecutive_hits_increase_combo() {
+        let mut world = World::new();
+        let id = world.spawn((Transform::default(),));
+
+        assert_eq!(get_combo_hits(&world, id), 0);
+        assert_eq!(register_combo_hit(&mut world, id), 1);
+        assert_eq!(register_combo_hit(&mut world, id), 2);
+        assert_eq!(get_combo_hits(&world, id), 2);
+    }
+
+    #[test]
+    fn later_hits_are_scaled_down() {
+        let mut tracker = ComboTracker::default();
+        tracker.register_hit();
+        assert_eq!(tracker.scaling(0.5, 0.2), 1.0);
+
+        tracker.register_hit();
+        assert_eq!(tracker.scaling(0.5, 0.2), 0.5);
+
+        tracker.register_hit();
+        tracker.register_hit();
+        assert_eq!(tracker.scaling(0.5, 0.2), 0.2);
+    }
+
+    #[test]
+    fn attackers_scale_their_own_combos_against_a_victim() {
+        let mut world = World::new();
+        let victim = world.spawn((Transform::default(),));
+        let first = world.spawn((Transform::default(),));
+        let second = world.spawn((Transform::default(),));
+        let config = HitmeConfig {
+            combo_scaling: 0.5,
+            ..HitmeConfig::default()
+        };
+
+        let scaling = [first, second, first, second]
+            .into_iter()
+            .map(|attacker| {
+                register_attacker_combo_hit(&mut world, attacker, victim);
+                get_combo_scaling(&world, &config, attacker, victim)
+            })
+            .collect::<Vec<f32>>();
+
+        assert_eq!(scaling, vec![1.0, 1.0, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn attacker_combo_lapses_after_the_timeout() {
+        let mut world = World::new();
+        let victim = world.spawn((Transform::default(),));
+        let attacker = world.spawn((Transform::default(),));
+        register_attacker_combo_hit(&mut world, attacker, victim);
+
+        let mut tracker = world.get::<&mut AttackerComboTracker>(attacker).unwrap();
+        advance_attacker_combos(&mut tracker, 0.5, 1.0);
+        assert!(tracker.combos.contains_key(&victim));
+
+        advance_attacker_combos(&mut tracker, 0.5, 1.0);
+        assert!(tracker.combos.is_empty());
+    }
+
+    #[test]
+    fn reset_clears_combo() {
+        let mut tracker = ComboTracker::default();
+        tracker.register_hit();
+        tracker.elapsed_time = 0.5;
+        tracker.reset();
+
+        assert_eq!(tracker.hits, 0);
+        assert_eq!(tracker.elapsed_time, 0.0);
+    }
+}
@@ -1,6 +1,6 @@
 use emerald::{toml, AssetLoader, EmeraldError, Entity, Group, World};
 
-use crate::{hitboxes::HitboxSet, hurtboxes::HurtboxSet};
+use crate::{hitboxes::load_hitbox_set, hurtboxes::HurtboxSet, pushboxes::PushboxSet};
 
 pub fn component_loader(
     _loader: &mut AssetLoader<'_>,
@@ -11,17 +11,54 @@ pub fn component_loader(
     hurtbox_group: Group,
     hitbox_group: Group,
 ) -> Result<(), EmeraldError> {
-    match key {
-        "hitbox_set" => {
-            let hitbox_set =
-                HitboxSet::from_toml(world, value, entity, hurtbox_group, hitbox_group)?;
-            world.insert_one(entity, hitbox_set)?;
+    load_component(world, entity, value, key, hurtbox_group, hitbox_group, None)
+}
+
+/// `component_loader`, that also loads `pushbox_set` keys onto the given pushbox group.
+#[allow(clippy::too_many_arguments)]
+pub fn component_loader_with_pushboxes(
+    _loader: &mut AssetLoader<'_>,
+    entity: Entity,
+    world: &mut World,
+    value: &toml::Value,
+    key: &str,
+    hurtbox_group: Group,
+    hitbox_group: Group,
+    pushbox_group: Group,
+) -> Result<(), EmeraldError> {
+    load_component(
+        world,
+        entity,
+        value,
+        key,
+        hurtbox_group,
+        hitbox_group,
+        Some(pushbox_group),
+    )
+}
+
+fn load_component(
+    world: &mut World,
+    entity: Entity,
+    value: &toml::Value,
+    key: &str,
+    hurtbox_group: Group,
+    hitbox_group: Group,
+    pushbox_group: Option<Group>,
+) -> Result<(), EmeraldError> {
+    match (key, pushbox_group) {
+        ("hitbox_set", _) => {
+            load_hitbox_set(world, value, entity, hurtbox_group, hitbox_group)?;
         }
-        "hurtbox_set" => {
+        ("hurtbox_set", _) => {
             let hurtbox_set =
                 HurtboxSet::from_toml(world, value, entity, hurtbox_group, hitbox_group)?;
             world.insert_one(entity, hurtbox_set)?;
         }
+        ("pushbox_set", Some(pushbox_group)) => {
+            let pushbox_set = PushboxSet::from_toml(world, value, entity, pushbox_group)?;
+            world.insert_one(entity, pushbox_set)?;
+        }
         _ => {}
     }
 
@@ -1,32 +1,273 @@
 use emerald::{Color, ColorRect, Emerald, Transform, Vector2, World};
 
-use crate::{hitboxes::Hitbox, hurtboxes::Hurtbox};
+use crate::{
+    hitboxes::Hitbox,
+    hurtboxes::{CapsuleAxis, ColliderShape, Hurtbox, RectCollider},
+};
 
+/// How debug boxes are drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DrawStyle {
+    /// Solid rects, circles, capsules and polygons are still outlined
+    Fill,
+
+    /// Only the edges of every collider, so the sprite underneath stays visible
+    Outline { thickness: f32 },
+}
+
+/// Settings for debug drawing hitboxes and hurtboxes.
+#[derive(Clone, Debug)]
+pub struct DebugDrawConfig {
+    pub hitbox_color: Color,
+    pub hurtbox_color: Color,
+
+    /// Skips inactive boxes entirely
+    pub only_active: bool,
+
+    /// Draws inactive boxes at half their color's alpha
+    pub dim_inactive: bool,
+
+    /// Scales the alpha of every box, from 0.0 to 1.0.
+    /// Useful for keeping overlapping hitboxes and hurtboxes readable.
+    pub opacity: f32,
+
+    pub style: DrawStyle,
+}
+impl DebugDrawConfig {
+    /// Returns the color to draw a box with, or None if it shouldn't be drawn.
+    fn box_color(&self, color: &Color, visible: bool, active: bool) -> Option<Color> {
+        if !visible || (self.only_active && !active) {
+            return None;
+        }
+
+        let mut color = color.clone();
+        if self.dim_inactive && !active {
+            color.a /= 2;
+        }
+        color.a = (color.a as f32 * self.opacity.clamp(0.0, 1.0)) as u8;
+
+        Some(color)
+    }
+}
+impl Default for DebugDrawConfig {
+    fn default() -> Self {
+        Self {
+            hitbox_color: Color::new(255, 0, 0, 160),
+            hurtbox_color: Color::new(0, 0, 255, 160),
+            only_active: false,
+            dim_inactive: true,
+            opacity: 1.0,
+            style: DrawStyle::Fill,
+        }
+    }
+}
+
+/// Draws every visible hitbox and hurtbox in the same color.
 pub fn draw_debug(emd: &mut Emerald, world: &World, color: &Color) {
-    let mut color_rect = ColorRect::new(color.clone(), 0, 0);
+    let config = DebugDrawConfig {
+        hitbox_color: color.clone(),
+        hurtbox_color: color.clone(),
+        only_active: false,
+        dim_inactive: false,
+        opacity: 1.0,
+        style: DrawStyle::Fill,
+    };
+    draw_debug_with_config(emd, world, &config);
+}
+
+pub fn draw_debug_with_config(emd: &mut Emerald, world: &World, config: &DebugDrawConfig) {
     for (_, (transform, hurtbox)) in world.query::<(&Transform, &Hurtbox)>().iter() {
-        if !hurtbox.visible {
-            continue;
-        }
+        let color = match config.box_color(&config.hurtbox_color, hurtbox.visible, hurtbox.active) {
+            Some(color) => color,
+            None => continue,
+        };
 
         for collider in &hurtbox.colliders {
-            color_rect.width = collider.width as u32;
-            color_rect.height = collider.height as u32;
-            color_rect.offset = Vector2::new(collider.translation.x, collider.translation.y);
-            emd.graphics().draw_color_rect(&color_rect, &transform).ok();
+            draw_collider(emd, collider, transform, &color, config.style);
         }
     }
 
     for (_, (transform, hitbox)) in world.query::<(&Transform, &Hitbox)>().iter() {
-        if !hitbox.visible {
-            continue;
+        let color = match config.box_color(&config.hitbox_color, hitbox.visible, hitbox.is_active())
+        {
+            Some(color) => color,
+            None => continue,
+        };
+
+        for collider in &hitbox.scaled_colliders() {
+            draw_collider(emd, collider, transform, &color, config.style);
         }
+    }
+}
 
-        for collider in &hitbox.raw_collider_data {
-            color_rect.width = collider.width as u32;
-            color_rect.height = collider.height as u32;
-            color_rect.offset = Vector2::new(collider.translation.x, collider.translation.y);
-            emd.graphics().draw_color_rect(&color_rect, &transform).ok();
+/// Number of line segments used to outline circle colliders.
+const CIRCLE_SEGMENTS: usize = 24;
+
+fn point_on_circle(radius: f32, segment: usize) -> Vector2<f32> {
+    let angle = segment as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+    Vector2::new(angle.cos(), angle.sin()) * radius
+}
+
+/// Returns the outline of a capsule, one half circle at each end of its axis.
+fn capsule_outline(half_height: f32, radius: f32, axis: CapsuleAxis) -> Vec<Vector2<f32>> {
+    let half_segments = CIRCLE_SEGMENTS / 2;
+    let mut points = Vec::new();
+    for i in 0..=half_segments {
+        points.push(Vector2::new(0.0, half_height) + point_on_circle(radius, i));
+    }
+    for i in half_segments..=CIRCLE_SEGMENTS {
+        points.push(Vector2::new(0.0, -half_height) + point_on_circle(radius, i));
+    }
+
+    match axis {
+        CapsuleAxis::Y => points,
+        CapsuleAxis::X => points.into_iter().map(|p| Vector2::new(p.y, p.x)).collect(),
+    }
+}
+
+fn draw_collider(
+    emd: &mut Emerald,
+    collider: &RectCollider,
+    transform: &Transform,
+    color: &Color,
+    style: DrawStyle,
+) {
+    if !has_size(&collider.shape) {
+        return;
+    }
+
+    let offset = collider.translation_vec();
+    let thickness = match style {
+        DrawStyle::Fill => 1.0,
+        DrawStyle::Outline { thickness } => thickness,
+    };
+
+    match &collider.shape {
+        ColliderShape::Rect { width, height } if style != DrawStyle::Fill => {
+            let (half_width, half_height) = (width / 2.0, height / 2.0);
+            let corners = [
+                Vector2::new(-half_width, -half_height),
+                Vector2::new(half_width, -half_height),
+                Vector2::new(half_width, half_height),
+                Vector2::new(-half_width, half_height),
+            ];
+            for i in 0..corners.len() {
+                let start = offset + rotate_vector(corners[i], collider.rotation);
+                let end =
+                    offset + rotate_vector(corners[(i + 1) % corners.len()], collider.rotation);
+                draw_line(emd, start, end, transform, color, thickness);
+            }
         }
+        ColliderShape::Rect { width, height } => {
+            let mut color_rect = ColorRect::new(color.clone(), *width as u32, *height as u32);
+            color_rect.offset = offset;
+
+            let mut rect_transform = transform.clone();
+            rect_transform.rotation += collider.rotation;
+            emd.graphics()
+                .draw_color_rect(&color_rect, &rect_transform)
+                .ok();
+        }
+        ColliderShape::Circle { radius } => {
+            for i in 0..CIRCLE_SEGMENTS {
+                let start = offset + point_on_circle(*radius, i);
+                let end = offset + point_on_circle(*radius, i + 1);
+                draw_line(emd, start, end, transform, color, thickness);
+            }
+        }
+        ColliderShape::Capsule {
+            half_height,
+            radius,
+            axis,
+        } => {
+            let points = capsule_outline(*half_height, *radius, *axis);
+            for i in 0..points.len() {
+                let start = offset + rotate_vector(points[i], collider.rotation);
+                let end = offset + rotate_vector(points[(i + 1) % points.len()], collider.rotation);
+                draw_line(emd, start, end, transform, color, thickness);
+            }
+        }
+        ColliderShape::Polygon { points } => {
+            for i in 0..points.len() {
+                let start = offset + rotate_vector(points[i], collider.rotation);
+                let end = offset + rotate_vector(points[(i + 1) % points.len()], collider.rotation);
+                draw_line(emd, start, end, transform, color, thickness);
+            }
+        }
+    }
+}
+
+/// Whether the shape covers any area, zero size colliders aren't drawn.
+fn has_size(shape: &ColliderShape) -> bool {
+    match shape {
+        ColliderShape::Rect { width, height } => *width > 0.0 && *height > 0.0,
+        ColliderShape::Circle { radius } => *radius > 0.0,
+        ColliderShape::Capsule { radius, .. } => *radius > 0.0,
+        ColliderShape::Polygon { points } => points.len() >= 3,
+    }
+}
+
+fn rotate_vector(vector: Vector2<f32>, rotation: f32) -> Vector2<f32> {
+    let (sin, cos) = rotation.sin_cos();
+    Vector2::new(
+        vector.x * cos - vector.y * sin,
+        vector.x * sin + vector.y * cos,
+    )
+}
+
+/// Draws a line between two points, relative to the given transform.
+fn draw_line(
+    emd: &mut Emerald,
+    start: Vector2<f32>,
+    end: Vector2<f32>,
+    transform: &Transform,
+    color: &Color,
+    thickness: f32,
+) {
+    let diff = end - start;
+    let mut color_rect =
+        ColorRect::new(color.clone(), diff.norm() as u32, thickness.max(1.0) as u32);
+    color_rect.offset = (start + end) / 2.0;
+
+    let mut line_transform = transform.clone();
+    line_transform.rotation += diff.y.atan2(diff.x);
+    emd.graphics()
+        .draw_color_rect(&color_rect, &line_transform)
+        .ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use emerald::{Color, Vector2};
+
+    use crate::{
+        draw::{has_size, DebugDrawConfig},
+        hurtboxes::ColliderShape,
+    };
+
+    #[test]
+    fn box_color_applies_opacity_and_dimming() {
+        let config = DebugDrawConfig {
+            opacity: 0.5,
+            ..DebugDrawConfig::default()
+        };
+        let color = Color::new(255, 0, 0, 200);
+
+        assert_eq!(config.box_color(&color, true, true).map(|c| c.a), Some(100));
+        assert_eq!(config.box_color(&color, true, false).map(|c| c.a), Some(50));
+        assert!(config.box_color(&color, false, true).is_none());
+    }
+
+    #[test]
+    fn zero_size_shapes_have_no_size() {
+        assert!(!has_size(&ColliderShape::Rect {
+            width: 0.0,
+            height: 10.0
+        }));
+        assert!(!has_size(&ColliderShape::Circle { radius: 0.0 }));
+        assert!(!has_size(&ColliderShape::Polygon {
+            points: vec![Vector2::new(0.0, 0.0), Vector2::new(1.0, 0.0)]
+        }));
+        assert!(has_size(&ColliderShape::Circle { radius: 4.0 }));
     }
 }
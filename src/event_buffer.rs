@@ -0,0 +1,126 @@
+use emerald::Entity;
+
+use crate::hitboxes::HitboxSequenceEvent;
+
+/// Every sequence event produced during the last `emd_hitme_system` tick,
+/// for game loops that prefer polling over registering callbacks.
+///
+/// Inserted as a resource by `emd_hitme::init`, and cleared at the start of every tick.
+#[derive(Debug, Default)]
+pub struct EventBuffer {
+    events: Vec<(Entity, HitboxSequenceEvent)>,
+}
+impl EventBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events of the last tick, paired with the owner of the hitbox set that produced them.
+    pub fn events(&self) -> &Vec<(Entity, HitboxSequenceEvent)> {
+        &self.events
+    }
+
+    /// Events of the last tick produced by the hitbox set of the given owner.
+    pub fn events_for(&self, owner: Entity) -> Vec<&HitboxSequenceEvent> {
+        self.events
+            .iter()
+            .filter(|(id, _)| *id == owner)
+            .map(|(_, event)| event)
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn push(&mut self, owner: Entity, event: HitboxSequenceEvent) {
+        self.events.push((owner, event));
+    }
+
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+}
+impl Extend<(Entity, HitboxSequenceEvent)> for EventBuffer {
+    fn extend<T: IntoIterator<Item = (Entity, HitboxSequenceEvent)>>(&mut self, iter: T) {
+        self.events.extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use emerald::{Entity, World};
+
+    use crate::{
+        collision_tests::spawn_hitbox_set,
+        event_buffer::EventBuffer,
+        hitboxes::{Hitbox, HitboxSequenceEvent, HitboxSet},
+        host::tests::HeadlessHost,
+        run_frame, HitmeConfig,
+    };
+
+    const JAB_TOML: &str = r#"
+        [hitboxes.punch]
+        colliders = [{ width = 10.0, height = 10.0 }]
+
+        [sequences]
+        jab = [{ duration = 1.0, name = "punch" }]
+    "#;
+
+    /// Runs a tick through the entry point of `emd_hitme_system`, returning the buffer it filled.
+    fn tick<'a>(world: &mut World, host: &'a mut HeadlessHost) -> &'a EventBuffer {
+        run_frame(host, world, None).unwrap();
+        host.event_buffer.as_ref().unwrap()
+    }
+
+    fn activated(buffer: &EventBuffer, owner: Entity, hitbox: Entity) -> bool {
+        buffer.events_for(owner).iter().any(|event| {
+            matches!(
+                event,
+                HitboxSequenceEvent::HitboxActivated { hitbox: id } if *id == hitbox
+            )
+        })
+    }
+
+    #[test]
+    fn buffer_collects_activated_hitboxes() {
+        let mut world = World::new();
+        let mut host = HeadlessHost::new(HitmeConfig::default(), 0.016);
+        let (owner, hitbox) = spawn_hitbox_set(&mut world, JAB_TOML);
+
+        world
+            .get::<&mut HitboxSet>(owner)
+            .unwrap()
+            .start_sequence("jab")
+            .unwrap();
+        let buffer = tick(&mut world, &mut host);
+
+        assert!(activated(buffer, owner, hitbox));
+        assert!(buffer.events_for(hitbox).is_empty());
+        assert!(world.get::<&Hitbox>(hitbox).unwrap().is_active());
+
+        // The next tick replaces the events of the last one
+        let buffer = tick(&mut world, &mut host);
+        assert!(!activated(buffer, owner, hitbox));
+    }
+
+    #[test]
+    fn buffer_collects_the_events_of_every_set_in_a_tick() {
+        let mut world = World::new();
+        let mut host = HeadlessHost::new(HitmeConfig::default(), 0.016);
+        let (first, first_hitbox) = spawn_hitbox_set(&mut world, JAB_TOML);
+        let (second, second_hitbox) = spawn_hitbox_set(&mut world, JAB_TOML);
+
+        for owner in [first, second] {
+            world
+                .get::<&mut HitboxSet>(owner)
+                .unwrap()
+                .start_sequence("jab")
+                .unwrap();
+        }
+        let buffer = tick(&mut world, &mut host);
+
+        assert!(activated(buffer, first, first_hitbox));
+        assert!(activated(buffer, second, second_hitbox));
+    }
+}
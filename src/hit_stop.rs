@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+
+use emerald::{Emerald, Entity, World};
+
+use crate::hitboxes::get_hitbox_owner;
+use crate::hurtboxes::get_hurtbox_owner;
+use crate::OnHitContext;
+
+/// Freezes entities for a short time, ex. both fighters pausing for a few frames on a big hit.
+/// Frozen entities, and the hitboxes and hurtboxes they own,
+/// receive a delta of 0 from `HitmeConfig::get_delta_for_entity`.
+///
+/// Optional, insert it as a resource to use it. Freeze timers advance by the real delta.
+#[derive(Debug, Default)]
+pub struct HitStop {
+    /// Entities and how long they stay frozen for
+    frozen: HashMap<Entity, f32>,
+
+    /// Entities frozen since the last `advance`, their freeze starts counting down next time
+    new_freezes: HashSet<Entity>,
+
+    /// How long `freeze_on_hit` freezes entities for
+    pub default_duration: f32,
+}
+impl HitStop {
+    pub fn new(default_duration: f32) -> Self {
+        Self {
+            frozen: HashMap::new(),
+            new_freezes: HashSet::new(),
+            default_duration,
+        }
+    }
+
+    /// Freezes the entities for the given duration.
+    /// Entities that are already frozen keep the longer of the two freezes.
+    pub fn freeze<I: IntoIterator<Item = Entity>>(&mut self, entities: I, duration: f32) {
+        for id in entities {
+            let remaining = self.frozen.entry(id).or_insert(0.0);
+            *remaining = remaining.max(duration);
+            self.new_freezes.insert(id);
+        }
+    }
+
+    pub fn unfreeze(&mut self, id: Entity) {
+        self.frozen.remove(&id);
+        self.new_freezes.remove(&id);
+    }
+
+    pub fn is_frozen(&self, id: Entity) -> bool {
+        self.frozen.contains_key(&id)
+    }
+
+    /// Whether the entity, or the owner of the hitbox or hurtbox, is frozen.
+    pub fn is_frozen_with_owner(&self, world: &World, id: Entity) -> bool {
+        get_hitbox_owner(world, id)
+            .or_else(|| get_hurtbox_owner(world, id))
+            .into_iter()
+            .chain([id])
+            .any(|id| self.is_frozen(id))
+    }
+
+    /// Returns how long the entity stays frozen for, if it's frozen
+    pub fn get_remaining(&self, id: Entity) -> Option<f32> {
+        self.frozen.get(&id).cloned()
+    }
+
+    /// Returns the delta the entity should progress by, 0 while it's frozen.
+    pub fn delta_for(&self, id: Entity, delta: f32) -> f32 {
+        if self.is_frozen(id) {
+            0.0
+        } else {
+            delta
+        }
+    }
+
+    /// Counts down every freeze, unfreezing entities once their freeze runs out.
+    /// Freezes made since the last call are skipped, so they last their full duration
+    /// when advancing after the frame's systems ran.
+    pub fn advance(&mut self, delta: f32) {
+        let new_freezes = std::mem::take(&mut self.new_freezes);
+        self.frozen
+            .iter_mut()
+            .filter(|(id, _)| !new_freezes.contains(id))
+            .for_each(|(_, remaining)| *remaining -= delta);
+        self.frozen.retain(|_, remaining| *remaining > 0.0);
+    }
+}
+
+/// An on hit callback freezing both the attacker and the hit entity
+/// for the `default_duration` of the `HitStop` resource.
+pub fn freeze_on_hit(emd: &mut Emerald, _: &mut World, ctx: OnHitContext) {
+    emd.resources().get_mut::<HitStop>().map(|hit_stop| {
+        let duration = hit_stop.default_duration;
+        hit_stop.freeze([ctx.hit_entity, ctx.hurt_entity], duration);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use emerald::{Entity, World};
+
+    use crate::{
+        collision_tests::spawn_hitbox_set, hit_stop::HitStop, hitboxes::HitboxSet, HitmeConfig,
+    };
+
+    const DELTA: f32 = 0.125;
+
+    /// Runs a frame the way `emd_hitme_system` orders it: the sequence progresses by the
+    /// owner's delta, then the freezes count down. Returns the delta the owner received.
+    fn run_frame(world: &mut World, hit_stop: &mut HitStop, owner: Entity) -> f32 {
+        let config = HitmeConfig::default();
+        let frozen = hit_stop.is_frozen_with_owner(world, owner);
+        let delta = config.get_delta_for_entity_with(world, owner, frozen, || DELTA);
+        world
+            .get::<&mut HitboxSet>(owner)
+            .unwrap()
+            .progress_active_sequence(delta);
+        hit_stop.advance(DELTA);
+
+        delta
+    }
+
+    fn spawn_attacker(world: &mut World) -> (Entity, Entity) {
+        let (owner, hitbox) = spawn_hitbox_set(
+            world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                attack = [{ duration = 0.25 }, { duration = 0.25 }]
+            "#,
+        );
+        world
+            .get::<&mut HitboxSet>(owner)
+            .unwrap()
+            .start_sequence("attack")
+            .unwrap();
+
+        (owner, hitbox)
+    }
+
+    #[test]
+    fn frozen_entity_sequence_does_not_advance() {
+        let mut world = World::new();
+        let (owner, _) = spawn_attacker(&mut world);
+        let mut hit_stop = HitStop::new(0.25);
+        // Frozen by a hit, after the frame's sequences already progressed
+        hit_stop.freeze([owner], 2.0 * DELTA);
+        hit_stop.advance(DELTA);
+
+        let deltas = (0..3)
+            .map(|_| run_frame(&mut world, &mut hit_stop, owner))
+            .collect::<Vec<f32>>();
+        assert_eq!(deltas, vec![0.0, 0.0, DELTA]);
+        assert!(!hit_stop.is_frozen(owner));
+    }
+
+    #[test]
+    fn one_frame_freeze_skips_one_frame() {
+        let mut world = World::new();
+        let (owner, _) = spawn_attacker(&mut world);
+        let mut hit_stop = HitStop::new(DELTA);
+        hit_stop.freeze([owner], DELTA);
+        hit_stop.advance(DELTA);
+
+        assert_eq!(run_frame(&mut world, &mut hit_stop, owner), 0.0);
+        assert_eq!(run_frame(&mut world, &mut hit_stop, owner), DELTA);
+    }
+
+    #[test]
+    fn freezing_an_owner_freezes_its_hitboxes() {
+        let mut world = World::new();
+        let (owner, hitbox) = spawn_attacker(&mut world);
+        let mut hit_stop = HitStop::new(DELTA);
+        hit_stop.freeze([owner], DELTA);
+
+        assert!(hit_stop.is_frozen_with_owner(&world, hitbox));
+        assert!(!hit_stop.is_frozen(hitbox));
+    }
+
+    #[test]
+    fn overlapping_freezes_keep_the_longest() {
+        let mut world = World::new();
+        let id = world.spawn(());
+        let mut hit_stop = HitStop::new(0.1);
+
+        hit_stop.freeze([id], 0.3);
+        hit_stop.freeze([id], 0.1);
+        assert_eq!(hit_stop.get_remaining(id), Some(0.3));
+    }
+}
@@ -1,9 +1,19 @@
-use std::collections::{HashMap, HashSet};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use crate::hurtboxes::RectCollider;
-use crate::tracker::SimpleTranslationTracker;
-use crate::{HitmeConfig, OnTagTriggerContext};
-use emerald::serde::Deserialize;
+use crate::active_box_index::{ensure_active_box_index, get_indexed_active_hitboxes, index_hitbox};
+use crate::host::HitmeHost;
+use crate::hurtboxes::{
+    get_all_active_hurtboxes, get_creatures_from_hurtboxes, toml_value_to_f32,
+    toml_value_to_groups, toml_value_to_strings, toml_value_to_team, toml_value_to_translation,
+    translation_to_vector, Hurtbox, HurtboxSet, RectCollider,
+};
+use crate::tracker::{posed_collider, SimpleTranslationTracker};
+use crate::{
+    event_buffer::EventBuffer, CombatBox, HitmeConfig, OnHitContext, OnHitFn, OnTagTriggerContext,
+};
+use emerald::rapier2d::prelude::RigidBodyHandle;
+use emerald::serde::{Deserialize, Serialize};
 use emerald::toml::Value;
 use emerald::{
     toml::value::Map, ColliderHandle, EmeraldError, Entity, RigidBodyBuilder, Transform, Vector2,
@@ -19,7 +29,31 @@ pub struct HitboxSet {
     pub hitboxes: HashMap<String, Entity>,
     pub owner: Entity,
     pub sequences: HashMap<String, Vec<HitboxSequenceFrame>>,
+
+    /// Settings of sequences defined as tables, by sequence name
+    pub sequence_settings: HashMap<String, HitboxSequenceSettings>,
     pub active_sequence: Option<ActiveSequenceData>,
+
+    /// Neutral hitboxes hit anything they touch, including their own owner.
+    /// Useful for traps and environmental hazards.
+    pub neutral: bool,
+
+    /// Names of hitboxes that the active sequence is not allowed to activate.
+    /// Cleared whenever a new sequence starts.
+    suppressed_hitboxes: HashSet<String>,
+
+    /// Sequences to start one after another, as the active sequence finishes
+    sequence_queue: VecDeque<String>,
+
+    /// Problems found while loading the sequences, see `get_warnings`
+    warnings: Vec<String>,
+
+    /// Start events of sequences started through `start_sequence_deferred`,
+    /// returned by the next `progress_active_sequence`.
+    pending_events: Vec<HitboxSequenceEvent>,
+
+    /// Given to every sequence the set starts, see `set_frames_per_second`
+    frames_per_second: f32,
 }
 impl HitboxSet {
     pub fn from_toml(
@@ -37,35 +71,48 @@ impl HitboxSet {
             .as_table()
             .unwrap_or(&default_map);
         let owner_transform = world.get::<&mut Transform>(owner)?.clone();
+        let (hurtbox_group, hitbox_group) =
+            toml_value_to_groups(value, hurtbox_group, hitbox_group);
+        let team = toml_value_to_team(value);
+        ensure_active_box_index(world);
+        let track_rotation = value
+            .get("track_rotation")
+            .map(|v| v.as_bool())
+            .flatten()
+            .unwrap_or(false);
         let hitboxes = hitboxes_table
             .into_iter()
             .map(|(key, value)| {
-                let hitbox = Hitbox::from_toml(world, value, owner)?;
-                let colliders = hitbox.raw_collider_data.clone();
+                let mut hitbox = Hitbox::from_toml(world, value, owner)?;
+                hitbox.team = hitbox.team.or(team);
+                // Clashing hitboxes also overlap other hitboxes, so opposing attacks can meet
+                let filter = if hitbox.clashes {
+                    hurtbox_group | hitbox_group
+                } else {
+                    hurtbox_group
+                };
+                hitbox.collision_groups = InteractionGroups::new(hitbox_group, filter);
+                // Places the hitbox relative to the owner, so collider definitions can be reused
+                let offset = value
+                    .get("offset")
+                    .map(toml_value_to_translation)
+                    .unwrap_or(Translation::new(0.0, 0.0));
+                let active = hitbox.active;
                 let (id, rbh) = world.spawn_with_body(
                     (
                         hitbox,
+                        CombatBox,
                         owner_transform.clone(),
                         SimpleTranslationTracker {
-                            target: owner,
-                            offset: Translation::new(0.0, 0.0),
+                            track_rotation,
+                            ..SimpleTranslationTracker::new(owner, offset)
                         },
                     ),
                     RigidBodyBuilder::dynamic(),
                 )?;
-                for collider in colliders {
-                    let name = collider.name.clone();
-                    let builder = collider
-                        .to_collider_builder()
-                        .collision_groups(InteractionGroups::new(hitbox_group, hurtbox_group));
-                    let handle = world.physics().build_collider(rbh, builder);
-
-                    if let Some(collider_name) = name {
-                        world
-                            .get::<&mut Hitbox>(id)?
-                            .colliders
-                            .insert(collider_name, handle);
-                    }
+                build_hitbox_colliders(world, id, rbh)?;
+                if active {
+                    index_hitbox(world, id);
                 }
 
                 Ok((key.clone(), id))
@@ -73,17 +120,41 @@ impl HitboxSet {
             .collect::<Result<HashMap<String, Entity>, EmeraldError>>()?;
 
         let mut sequences = HashMap::new();
+        let mut sequence_settings = HashMap::new();
+        let mut warnings = Vec::new();
         if let Some(s) = value.get("sequences") {
             if let Some(table) = s.as_table() {
                 for (key, value) in table {
                     let mut frames = Vec::new();
 
-                    if let Some(arr) = value.as_array() {
-                        for v in arr {
-                            if let Ok(sequence) =
-                                emerald::toml::from_str::<HitboxSequenceFrame>(&v.to_string())
-                            {
-                                frames.push(sequence);
+                    // Sequences are either an array of frames,
+                    // or a table of settings with the frames under the "frames" key.
+                    let frames_value = if value.is_table() {
+                        sequence_settings
+                            .insert(key.clone(), HitboxSequenceSettings::from_toml(value));
+                        value.get("frames")
+                    } else {
+                        Some(value)
+                    };
+
+                    if let Some(arr) = frames_value.map(|v| v.as_array()).flatten() {
+                        for (index, v) in arr.iter().enumerate() {
+                            match emerald::toml::from_str::<HitboxSequenceFrame>(&v.to_string()) {
+                                Ok(sequence) => {
+                                    for name in sequence.hitbox_names() {
+                                        if !hitboxes.contains_key(name) {
+                                            warnings.push(format!(
+                                                "Sequence {} frame {} references missing hitbox {}",
+                                                key, index, name
+                                            ));
+                                        }
+                                    }
+                                    frames.push(sequence);
+                                }
+                                Err(e) => warnings.push(format!(
+                                    "Sequence {} frame {} failed to parse: {}",
+                                    key, index, e
+                                )),
                             }
                         }
 
@@ -93,18 +164,66 @@ impl HitboxSet {
             }
         }
 
+        let neutral = value
+            .get("neutral")
+            .unwrap_or(&emerald::toml::Value::Boolean(false))
+            .as_bool()
+            .unwrap_or(false);
+
+        // Strict sets refuse to load with broken sequences, instead of skipping the broken parts
+        let strict = value
+            .get("strict")
+            .map(|v| v.as_bool())
+            .flatten()
+            .unwrap_or(false);
+        if strict && !warnings.is_empty() {
+            return Err(EmeraldError::new(warnings.join("\n")));
+        }
+
         Ok(Self {
             hitboxes,
             owner,
             sequences,
+            sequence_settings,
             active_sequence: None,
+            neutral,
+            suppressed_hitboxes: HashSet::new(),
+            sequence_queue: VecDeque::new(),
+            warnings,
+            pending_events: Vec::new(),
+            frames_per_second: default_frames_per_second(),
         })
     }
 
+    /// Sets the rate frame counts of sequence frames are converted to seconds at,
+    /// for the active sequence and every sequence started after it. Defaults to 60.
+    /// The sequence system keeps it in sync with `HitmeConfig::frames_per_second`.
+    /// Rates of 0 or less are ignored.
+    pub fn set_frames_per_second(&mut self, frames_per_second: f32) {
+        if frames_per_second <= 0.0 {
+            return;
+        }
+
+        self.frames_per_second = frames_per_second;
+        if let Some(active_sequence) = self.active_sequence.as_mut() {
+            active_sequence.frames_per_second = frames_per_second;
+        }
+    }
+
+    /// Frames that failed to parse and were skipped,
+    /// and frames activating hitboxes that don't exist in the set.
+    pub fn get_warnings(&self) -> &Vec<String> {
+        &self.warnings
+    }
+
+    /// Starts the named sequence from its first frame,
+    /// returning the `SequenceStarted` and first `FrameEntered` events.
+    /// Interrupting a sequence during an active frame also returns `HitboxDeactivated` events
+    /// for the hitboxes of that frame.
     pub fn start_sequence<T: Into<String>>(
         &mut self,
         sequence_name: T,
-    ) -> Result<(), EmeraldError> {
+    ) -> Result<Vec<HitboxSequenceEvent>, EmeraldError> {
         let name: String = sequence_name.into();
         if !self.has_sequence(&name) {
             return Err(EmeraldError::new(format!(
@@ -113,25 +232,192 @@ impl HitboxSet {
             )));
         }
 
-        let sequence = ActiveSequenceData::new(name);
+        let mut events = self
+            .active_sequence
+            .as_ref()
+            .filter(|active_sequence| active_sequence.is_current_frame_active(&self.sequences))
+            .map(|active_sequence| {
+                active_sequence.get_current_active_hitboxes(&self.sequences, &self.hitboxes)
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .map(|hitbox| HitboxSequenceEvent::HitboxDeactivated { hitbox })
+            .collect::<Vec<HitboxSequenceEvent>>();
+        events.push(HitboxSequenceEvent::SequenceStarted { name: name.clone() });
+        events.push(HitboxSequenceEvent::FrameEntered { frame: 0 });
+        let mut sequence = ActiveSequenceData::new(name);
+        sequence.frames_per_second = self.frames_per_second;
+        if let Some(settings) = self.sequence_settings.get(&sequence.name) {
+            sequence.looping = settings.looping;
+            sequence.loops_remaining = settings.loop_count;
+        }
         self.active_sequence = Some(sequence);
         self.reset_sequences();
+        self.suppressed_hitboxes.clear();
+
+        Ok(events)
+    }
+
+    /// Starts the named sequence like `start_sequence`, but holds on to its events until the
+    /// next `progress_active_sequence`, so the sequence system still receives them.
+    pub fn start_sequence_deferred<T: Into<String>>(
+        &mut self,
+        sequence_name: T,
+    ) -> Result<(), EmeraldError> {
+        let events = self.start_sequence(sequence_name)?;
+        self.pending_events.extend(events);
 
         Ok(())
     }
 
+    /// Sets the playback speed of the active sequence, see `ActiveSequenceData::speed`.
+    pub fn set_sequence_speed(&mut self, speed: f32) {
+        if let Some(active_sequence) = self.active_sequence.as_mut() {
+            active_sequence.speed = speed;
+        }
+    }
+
+    /// Halts the active sequence in place, until it is resumed.
+    pub fn pause_sequence(&mut self) {
+        if let Some(active_sequence) = self.active_sequence.as_mut() {
+            active_sequence.paused = true;
+        }
+    }
+
+    pub fn resume_sequence(&mut self) {
+        if let Some(active_sequence) = self.active_sequence.as_mut() {
+            active_sequence.paused = false;
+        }
+    }
+
+    /// If there is an active sequence, returns if it's paused
+    pub fn is_sequence_paused(&self) -> Option<bool> {
+        self.active_sequence
+            .as_ref()
+            .map(|active_sequence| active_sequence.paused)
+    }
+
     pub fn has_sequence<'a, T: Into<&'a String>>(&self, name: T) -> bool {
         self.sequences.contains_key(name.into())
     }
 
     pub fn progress_active_sequence(&mut self, delta: f32) -> Vec<HitboxSequenceEvent> {
+        let mut events = std::mem::take(&mut self.pending_events);
+        events.extend(
+            self.active_sequence
+                .as_mut()
+                .map(|sequence| sequence.progress(&mut self.sequences, &self.hitboxes, delta))
+                .unwrap_or_default(),
+        );
+
+        let suppressed = self.get_suppressed_hitboxes();
+        events.retain(|e| match e {
+            HitboxSequenceEvent::HitboxActivated { hitbox } => !suppressed.contains(hitbox),
+            _ => true,
+        });
+
+        if events
+            .iter()
+            .any(|e| matches!(e, HitboxSequenceEvent::Finished))
+        {
+            self.active_sequence = None;
+            if let Some(next) = self.sequence_queue.pop_front() {
+                events.extend(self.start_sequence(next).unwrap_or_default());
+            }
+        }
+
+        events
+    }
+
+    /// Steps the active sequence backward, see `ActiveSequenceData::rewind`
+    pub fn rewind_active_sequence(&mut self, delta: f32) -> Vec<HitboxSequenceEvent> {
         self.active_sequence
             .as_mut()
-            .map(|sequence| sequence.progress(&mut self.sequences, &self.hitboxes, delta))
+            .map(|sequence| sequence.rewind(&mut self.sequences, &self.hitboxes, delta))
             .unwrap_or_default()
     }
 
-    pub fn get_current_sequence_frame(&mut self) -> Option<&HitboxSequenceFrame> {
+    /// Queues a sequence to start when the active sequence finishes,
+    /// after any sequences queued before it. Starts right away if no sequence is active.
+    pub fn queue_sequence<T: Into<String>>(
+        &mut self,
+        sequence_name: T,
+    ) -> Result<(), EmeraldError> {
+        let name: String = sequence_name.into();
+        if !self.has_sequence(&name) {
+            return Err(EmeraldError::new(format!(
+                "Hitbox set does not have sequence {}",
+                &name
+            )));
+        }
+
+        if self.active_sequence.is_none() && self.sequence_queue.is_empty() {
+            self.start_sequence_deferred(name)?;
+        } else {
+            self.sequence_queue.push_back(name);
+        }
+
+        Ok(())
+    }
+
+    pub fn clear_sequence_queue(&mut self) {
+        self.sequence_queue.clear();
+    }
+
+    /// Returns how many sequences are waiting to start
+    pub fn queued_sequence_count(&self) -> usize {
+        self.sequence_queue.len()
+    }
+
+    /// Prevents the active sequence from activating the named hitbox.
+    /// Lasts until the hitbox is unsuppressed or a new sequence starts.
+    /// Returns the suppressed hitbox entity, if the set has a hitbox by that name.
+    pub fn suppress_hitbox<T: Into<String>>(&mut self, name: T) -> Option<Entity> {
+        let name: String = name.into();
+        let id = self.hitboxes.get(&name).cloned();
+        self.suppressed_hitboxes.insert(name);
+
+        id
+    }
+
+    /// Allows the active sequence to activate the named hitbox again.
+    /// Returns the hitbox entity if the active frame uses it, and it should be reactivated.
+    pub fn unsuppress_hitbox<T: Into<String>>(&mut self, name: T) -> Option<Entity> {
+        let name: String = name.into();
+        if !self.suppressed_hitboxes.remove(&name) {
+            return None;
+        }
+
+        let id = self.hitboxes.get(&name).cloned()?;
+        let frame_active = self
+            .active_sequence
+            .as_ref()
+            .map(|s| s.is_current_frame_active(&self.sequences))
+            .unwrap_or(false);
+        let in_current_frame = self
+            .active_sequence
+            .as_ref()
+            .map(|s| {
+                s.get_current_active_hitboxes(&self.sequences, &self.hitboxes)
+                    .contains(&id)
+            })
+            .unwrap_or(false);
+
+        (frame_active && in_current_frame).then(|| id)
+    }
+
+    pub fn is_hitbox_suppressed<'a, T: Into<&'a String>>(&self, name: T) -> bool {
+        self.suppressed_hitboxes.contains(name.into())
+    }
+
+    fn get_suppressed_hitboxes(&self) -> HashSet<Entity> {
+        self.suppressed_hitboxes
+            .iter()
+            .filter_map(|name| self.hitboxes.get(name).cloned())
+            .collect()
+    }
+
+    pub fn get_current_sequence_frame(&self) -> Option<&HitboxSequenceFrame> {
         if let Some(active_sequence) = &self.active_sequence {
             if let Some(frames) = &self.sequences.get(&active_sequence.name) {
                 return frames.get(active_sequence.frame);
@@ -141,12 +427,126 @@ impl HitboxSet {
         None
     }
 
+    /// Sets or clears a flag checked by frame branches of the active sequence.
+    /// Flags are cleared whenever a sequence starts.
+    pub fn set_branch_flag<T: Into<String>>(&mut self, tag: T, value: bool) {
+        if let Some(active_sequence) = self.active_sequence.as_mut() {
+            let tag: String = tag.into();
+            if value {
+                active_sequence.branch_flags.insert(tag);
+            } else {
+                active_sequence.branch_flags.remove(&tag);
+            }
+        }
+    }
+
+    /// Whether another sequence may interrupt the active one right now.
+    /// True when no sequence is active.
+    pub fn can_cancel(&self) -> bool {
+        self.active_sequence.is_none()
+            || self
+                .get_current_sequence_frame()
+                .map(|frame| frame.cancellable)
+                .unwrap_or(false)
+    }
+
+    /// Starts the named sequence if the active one can be cancelled, see `can_cancel`.
+    /// Returns whether the cancel succeeded.
+    pub fn try_cancel_into<T: Into<String>>(&mut self, sequence_name: T) -> bool {
+        self.can_cancel() && self.start_sequence_deferred(sequence_name).is_ok()
+    }
+
     pub fn reset_sequences(&mut self) {
         self.sequences.iter_mut().for_each(|(_, frames)| {
             frames.iter_mut().for_each(|f| f.reset());
         });
     }
 
+    /// If there is an active sequence, returns how long its current frame has been running
+    pub fn current_frame_elapsed(&self) -> Option<f32> {
+        self.active_sequence
+            .as_ref()
+            .map(|active_sequence| active_sequence.elapsed_time)
+    }
+
+    /// If there is an active sequence, returns how long it has been running
+    pub fn current_sequence_total_elapsed(&self) -> Option<f32> {
+        self.active_sequence
+            .as_ref()
+            .map(|active_sequence| active_sequence.total_elapsed_time)
+    }
+
+    /// If there is an active sequence, returns the progress of its current frame from 0.0 to 1.0
+    pub fn current_frame_progress(&self) -> Option<f32> {
+        let active_sequence = self.active_sequence.as_ref()?;
+        let frame = self
+            .sequences
+            .get(&active_sequence.name)?
+            .get(active_sequence.frame)?;
+
+        let fps = active_sequence.frames_per_second;
+        let duration = frame.duration_secs(fps);
+        if duration <= 0.0 {
+            return Some(1.0);
+        }
+
+        Some(((active_sequence.elapsed_time - frame.delay_secs(fps)) / duration).clamp(0.0, 1.0))
+    }
+
+    /// Returns the cancel flags of the active sequence, taking the current frame's overrides into
+    /// account. Empty if there is no active sequence.
+    pub fn current_cancel_flags(&self) -> HashSet<String> {
+        let active_sequence = match &self.active_sequence {
+            Some(active_sequence) => active_sequence,
+            None => return HashSet::new(),
+        };
+
+        let frame_flags = self
+            .sequences
+            .get(&active_sequence.name)
+            .map(|frames| frames.get(active_sequence.frame))
+            .flatten()
+            .map(|frame| frame.cancel_flags.clone())
+            .flatten();
+
+        frame_flags
+            .or_else(|| {
+                self.sequence_settings
+                    .get(&active_sequence.name)
+                    .map(|settings| settings.cancel_flags.clone())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Iterates over every sequence of the set and its frames, ex. for an editor timeline.
+    pub fn iter_sequences(&self) -> impl Iterator<Item = (&String, &[HitboxSequenceFrame])> {
+        self.sequences
+            .iter()
+            .map(|(name, frames)| (name, frames.as_slice()))
+    }
+
+    /// Returns the names of the current frame's tags that haven't triggered yet.
+    pub fn pending_tags(&self) -> Vec<&str> {
+        self.active_sequence
+            .as_ref()
+            .map(|active_sequence| {
+                self.sequences
+                    .get(&active_sequence.name)
+                    .map(|frames| frames.get(active_sequence.frame))
+                    .flatten()
+            })
+            .flatten()
+            .map(|frame| {
+                frame
+                    .tags
+                    .iter()
+                    .filter(|tag| !tag.triggered)
+                    .map(|tag| tag.name.as_str())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     /// If there is an active sequence, returns if its finjished
     pub fn is_current_sequence_finished(&self) -> Option<bool> {
         self.active_sequence
@@ -155,6 +555,45 @@ impl HitboxSet {
     }
 }
 
+/// Settings that apply to a whole sequence, rather than a single frame.
+#[derive(Clone, Debug, Default)]
+pub struct HitboxSequenceSettings {
+    /// Flags read by external state machines, ex. which actions may cancel the sequence.
+    /// The hitbox set only stores these, it does not act on them.
+    pub cancel_flags: HashSet<String>,
+
+    /// Restarts the sequence from its first frame after the last one completes.
+    /// Enabled by `loop = true`, or by setting a `loop_count`.
+    pub looping: bool,
+
+    /// How many times a looping sequence repeats before finishing, loops forever if None
+    pub loop_count: Option<u32>,
+}
+impl HitboxSequenceSettings {
+    pub fn from_toml(value: &emerald::toml::Value) -> Self {
+        let cancel_flags = toml_value_to_strings(value.get("cancel_flags"))
+            .into_iter()
+            .collect();
+        let loop_count = value
+            .get("loop_count")
+            .map(|v| v.as_integer())
+            .flatten()
+            .map(|count| count.max(0) as u32);
+        let looping = loop_count.is_some()
+            || value
+                .get("loop")
+                .map(|v| v.as_bool())
+                .flatten()
+                .unwrap_or(false);
+
+        Self {
+            cancel_flags,
+            looping,
+            loop_count,
+        }
+    }
+}
+
 fn default_tag_data() -> Value {
     Value::Table(emerald::toml::map::Map::new())
 }
@@ -183,6 +622,10 @@ pub struct HitboxSequenceFrame {
     #[serde(default)]
     pub duration: f32,
 
+    /// Time limit in animation frames, used when `duration` isn't set.
+    /// See `HitmeConfig::frames_per_second`.
+    pub frames: Option<u32>,
+
     /// Name of the collider to activate
     pub name: Option<String>,
 
@@ -193,19 +636,101 @@ pub struct HitboxSequenceFrame {
     #[serde(default)]
     pub delay: f32,
 
+    /// Delay in animation frames, used when `delay` isn't set
+    pub delay_frames: Option<u32>,
+
     /// Tags bound this frame, often used as "triggers" for other effects
     #[serde(default)]
     tags: Vec<HitboxSequenceFrameTag>,
 
     #[serde(default)]
     active: bool,
+
+    /// Overrides the sequence's cancel flags while this frame is active
+    pub cancel_flags: Option<HashSet<String>>,
+
+    /// If set, only these named colliders of the frame's hitboxes are active
+    pub colliders: Option<Vec<String>>,
+
+    /// Whether another sequence can interrupt this frame, see `HitboxSet::try_cancel_into`
+    #[serde(default)]
+    pub cancellable: bool,
+
+    /// Frames to jump to when this frame ends, instead of the next one.
+    /// The first branch whose flag is set wins, see `HitboxSet::set_branch_flag`.
+    #[serde(default)]
+    pub branches: Vec<HitboxSequenceBranch>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(crate = "emerald::serde")]
+pub struct HitboxSequenceBranch {
+    pub condition_tag: String,
+
+    /// Index of the frame to jump to
+    pub goto: usize,
 }
 impl HitboxSequenceFrame {
+    /// Sets the duration in seconds, ex. for tooling tweaking timings live.
+    /// Negative durations are treated as 0, which falls back to `frames` if set.
+    pub fn set_duration(&mut self, duration: f32) {
+        self.duration = duration.max(0.0);
+    }
+
+    /// Names of the hitboxes this frame activates, from both `name` and `names`
+    pub fn hitbox_names(&self) -> impl Iterator<Item = &String> {
+        self.name.iter().chain(self.names.iter().flatten())
+    }
+
+    pub fn tags(&self) -> &[HitboxSequenceFrameTag] {
+        &self.tags
+    }
+
+    /// Whether the frame's hitboxes have been activated by the active sequence
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
     pub fn reset(&mut self) {
         self.tags.iter_mut().for_each(|tag| tag.triggered = false);
         self.active = false;
     }
 
+    /// Returns the duration in seconds, converting `frames` if `duration` isn't set
+    pub fn duration_secs(&self, frames_per_second: f32) -> f32 {
+        if self.duration > 0.0 {
+            return self.duration;
+        }
+
+        self.frames
+            .map(|frames| frames_to_secs(frames, frames_per_second))
+            .unwrap_or(0.0)
+    }
+
+    /// Returns the delay in seconds, converting `delay_frames` if `delay` isn't set
+    pub fn delay_secs(&self, frames_per_second: f32) -> f32 {
+        if self.delay > 0.0 {
+            return self.delay;
+        }
+
+        self.delay_frames
+            .map(|frames| frames_to_secs(frames, frames_per_second))
+            .unwrap_or(0.0)
+    }
+
+    /// Whether both frames activate the same hitboxes by name
+    pub fn targets_same_hitboxes(&self, other: &HitboxSequenceFrame) -> bool {
+        self.name == other.name && self.names == other.names
+    }
+
+    /// Copies the progress of another frame, ie. whether it's active and which tags triggered
+    fn copy_progress(&mut self, other: &HitboxSequenceFrame) {
+        self.active = other.active;
+        for (tag, other_tag) in self.tags.iter_mut().zip(other.tags.iter()) {
+            tag.triggered = other_tag.triggered;
+        }
+    }
+
     pub fn get_hitboxes(&self, hitboxes: &HashMap<String, Entity>) -> Vec<Entity> {
         let mut entities = Vec::new();
 
@@ -227,12 +752,36 @@ impl HitboxSequenceFrame {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum HitboxSequenceEvent {
-    HitboxDeactivated { hitbox: Entity },
-    HitboxActivated { hitbox: Entity },
-    TagTriggered { name: String, data: Value },
+    HitboxDeactivated {
+        hitbox: Entity,
+    },
+    HitboxActivated {
+        hitbox: Entity,
+    },
+    TagTriggered {
+        name: String,
+        data: Value,
+
+        /// The sequence and frame the tag belongs to
+        sequence_name: String,
+        frame: usize,
+
+        /// Time elapsed since the sequence started
+        elapsed: f32,
+    },
     Finished,
+    SequenceStarted {
+        name: String,
+    },
+    FrameEntered {
+        frame: usize,
+    },
+    CollidersSelected {
+        hitbox: Entity,
+        colliders: Vec<String>,
+    },
 }
 impl HitboxSequenceEvent {
     pub fn get_activated_hitboxes(events: &Vec<HitboxSequenceEvent>) -> Vec<Entity> {
@@ -245,6 +794,16 @@ impl HitboxSequenceEvent {
             .collect()
     }
 
+    pub fn get_entered_frames(events: &Vec<HitboxSequenceEvent>) -> Vec<usize> {
+        events
+            .iter()
+            .filter_map(|e| match e {
+                HitboxSequenceEvent::FrameEntered { frame } => Some(*frame),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn get_deactivated_hitboxes(events: &Vec<HitboxSequenceEvent>) -> Vec<Entity> {
         events
             .iter()
@@ -260,14 +819,88 @@ pub fn activate_hitbox_sequence(world: &mut World, id: Entity, sequence: &str) {
     world
         .get::<&mut HitboxSet>(id)
         .ok()
-        .map(|mut h| h.start_sequence(sequence).ok());
+        .map(|mut h| h.start_sequence_deferred(sequence).ok());
 }
-#[derive(Debug)]
+/// Deactivates the named hitbox of the given hitbox set,
+/// and keeps the active sequence from reactivating it.
+pub fn suppress_hitbox(world: &mut World, id: Entity, name: &str) {
+    let hitbox = world
+        .get::<&mut HitboxSet>(id)
+        .ok()
+        .map(|mut h| h.suppress_hitbox(name))
+        .flatten();
+
+    hitbox.map(|hitbox| {
+        world.get::<&mut Hitbox>(hitbox).ok().map(|mut h| {
+            h.deactivate();
+        })
+    });
+}
+
+/// Sets a keyframed offset curve on a hitbox tracker, driven by the owner's frame progress.
+pub fn set_hitbox_offset_curve(world: &mut World, hitbox: Entity, curve: Vec<(f32, Translation)>) {
+    world
+        .get::<&mut SimpleTranslationTracker>(hitbox)
+        .ok()
+        .map(|mut tracker| tracker.set_offset_curve(curve));
+}
+
+/// Lifts the suppression of the named hitbox, reactivating it if the current frame uses it.
+pub fn unsuppress_hitbox(world: &mut World, id: Entity, name: &str) {
+    let hitbox = world
+        .get::<&mut HitboxSet>(id)
+        .ok()
+        .map(|mut h| h.unsuppress_hitbox(name))
+        .flatten();
+
+    hitbox.map(|hitbox| set_hitbox_active(world, hitbox, true));
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(crate = "emerald::serde")]
 pub struct ActiveSequenceData {
     /// Name of the active sequence
     pub name: String,
     pub frame: usize,
+
+    /// Time elapsed since the current frame started
     pub elapsed_time: f32,
+
+    /// Time elapsed since the sequence started, not reset between frames
+    pub total_elapsed_time: f32,
+
+    /// Restarts the sequence after its last frame, see `HitboxSequenceSettings::looping`
+    pub looping: bool,
+
+    /// Loops left before the sequence finishes, loops forever if None
+    pub loops_remaining: Option<u32>,
+
+    /// Playback speed multiplier, 0.0 freezes the sequence in place
+    pub speed: f32,
+
+    /// Paused sequences don't progress, their active hitboxes stay active
+    pub paused: bool,
+
+    /// Flags that frame branches check, see `HitboxSequenceFrame::branches`
+    #[serde(default)]
+    pub branch_flags: HashSet<String>,
+
+    /// Converts frame counts to seconds, kept in sync with `HitmeConfig::frames_per_second`
+    #[serde(default = "default_frames_per_second")]
+    pub frames_per_second: f32,
+}
+
+fn default_frames_per_second() -> f32 {
+    60.0
+}
+
+/// Rates of 0 or less fall back to the default 60 frames per second
+fn frames_to_secs(frames: u32, frames_per_second: f32) -> f32 {
+    if frames_per_second > 0.0 {
+        frames as f32 / frames_per_second
+    } else {
+        frames as f32 / default_frames_per_second()
+    }
 }
 impl ActiveSequenceData {
     pub fn new(name: String) -> Self {
@@ -275,9 +908,20 @@ impl ActiveSequenceData {
             name,
             frame: 0,
             elapsed_time: 0.0,
+            total_elapsed_time: 0.0,
+            looping: false,
+            loops_remaining: None,
+            speed: 1.0,
+            paused: false,
+            branch_flags: HashSet::new(),
+            frames_per_second: default_frames_per_second(),
         }
     }
 
+    pub fn has_loops_remaining(&self) -> bool {
+        self.looping && self.loops_remaining.map(|loops| loops > 0).unwrap_or(true)
+    }
+
     pub fn add_delay(&mut self, delay: f32) {
         self.elapsed_time -= delay;
     }
@@ -306,40 +950,38 @@ impl ActiveSequenceData {
         let mut entities = Vec::new();
 
         if let Some(frames) = sequences.get(&self.name) {
-            let total_frame_count = frames.len();
-            let diff = total_frame_count - self.frame;
-
-            if diff == 0 {
-                return entities;
-            }
-
-            for i in 1..diff {
-                if let Some(frame) = frames.get(self.frame + i) {
-                    entities.extend(frame.get_hitboxes(hitboxes));
-                }
+            for frame in frames.iter().skip(self.frame + 1) {
+                entities.extend(frame.get_hitboxes(hitboxes));
             }
         }
 
         entities
     }
 
+    /// Missing and empty sequences are always finished.
     pub fn is_finished(&self, sequences: &HashMap<String, Vec<HitboxSequenceFrame>>) -> bool {
-        let (last_frame, last_frame_limit) = sequences
-            .get(&self.name)
-            .map(|frames| {
-                (
-                    frames.len() - 1,
-                    frames.last().map(|f| f.duration).unwrap_or(0.0),
-                )
-            })
-            .unwrap_or((0, 0.0));
+        let frames = match sequences.get(&self.name) {
+            Some(frames) if !frames.is_empty() => frames,
+            _ => return true,
+        };
+
+        if self.frame >= frames.len() {
+            return true;
+        }
+
+        let last_frame_limit = frames
+            .last()
+            .map(|f| f.duration_secs(self.frames_per_second))
+            .unwrap_or(0.0);
 
-        self.frame == last_frame && self.elapsed_time >= last_frame_limit
+        !self.has_loops_remaining()
+            && self.frame == frames.len() - 1
+            && self.elapsed_time >= last_frame_limit
     }
 
     pub fn is_current_frame_active(
         &self,
-        sequences: &mut HashMap<String, Vec<HitboxSequenceFrame>>,
+        sequences: &HashMap<String, Vec<HitboxSequenceFrame>>,
     ) -> bool {
         sequences
             .get(&self.name)
@@ -354,13 +996,25 @@ impl ActiveSequenceData {
         delta: f32,
     ) -> Vec<HitboxSequenceEvent> {
         let mut events = Vec::new();
+        if self.paused || self.speed <= 0.0 {
+            return events;
+        }
+
+        // Empty sequences have nothing to play
+        if get_sequence_frame_count(sequences, &self.name).unwrap_or(0) == 0 {
+            events.push(HitboxSequenceEvent::Finished);
+            return events;
+        }
 
+        let delta = delta * self.speed;
+        let fps = self.frames_per_second;
         let delay = sequences
             .get(&self.name)
-            .map(|frames| frames.get(self.frame).map(|f| f.delay))
+            .map(|frames| frames.get(self.frame).map(|f| f.delay_secs(fps)))
             .flatten()
             .unwrap_or(0.0);
         self.elapsed_time += delta;
+        self.total_elapsed_time += delta;
 
         // First frame, activate hitboxes
         if self.elapsed_time >= delay && !self.is_current_frame_active(sequences) {
@@ -375,22 +1029,38 @@ impl ActiveSequenceData {
                         events.push(HitboxSequenceEvent::TagTriggered {
                             name: tag.name.clone(),
                             data: tag.data.clone(),
+                            sequence_name: self.name.clone(),
+                            frame: self.frame,
+                            elapsed: self.total_elapsed_time,
                         });
                     }
                 });
 
-                if self.elapsed_time >= frame.duration + delay {
+                if self.elapsed_time >= frame.duration_secs(fps) + delay {
+                    let branch = frame
+                        .branches
+                        .iter()
+                        .find(|branch| self.branch_flags.contains(&branch.condition_tag))
+                        .map(|branch| branch.goto);
                     self.deactivate_current_frame(sequences, hitboxes, &mut events);
 
                     self.elapsed_time = 0.0;
                     self.reset_current_frame(sequences);
-                    self.frame += 1;
+                    self.frame = branch.unwrap_or(self.frame + 1);
 
-                    get_sequence_frame_count(sequences, &self.name).map(|count| {
-                        if self.frame >= count {
+                    let count = get_sequence_frame_count(sequences, &self.name).unwrap_or(0);
+                    if self.frame >= count {
+                        if self.has_loops_remaining() {
+                            self.frame = 0;
+                            self.loops_remaining = self.loops_remaining.map(|loops| loops - 1);
+                        } else {
                             events.push(HitboxSequenceEvent::Finished);
                         }
-                    });
+                    }
+
+                    if self.frame < count {
+                        events.push(HitboxSequenceEvent::FrameEntered { frame: self.frame });
+                    }
                 }
             }
         }
@@ -398,7 +1068,58 @@ impl ActiveSequenceData {
         events
     }
 
-    fn reset_current_frame(&mut self, sequences: &mut HashMap<String, Vec<HitboxSequenceFrame>>) {
+    /// Steps the sequence backward, the mirror of `progress`.
+    /// Frames are deactivated as they're rewound past, and the frame rewound into is activated
+    /// if it's past its delay. Stops at the start of the first frame.
+    pub fn rewind(
+        &mut self,
+        sequences: &mut HashMap<String, Vec<HitboxSequenceFrame>>,
+        hitboxes: &HashMap<String, Entity>,
+        delta: f32,
+    ) -> Vec<HitboxSequenceEvent> {
+        let mut events = Vec::new();
+        let fps = self.frames_per_second;
+        let delta = delta * self.speed.max(0.0);
+        self.elapsed_time -= delta;
+        self.total_elapsed_time = (self.total_elapsed_time - delta).max(0.0);
+
+        while self.elapsed_time < 0.0 && self.frame > 0 {
+            if self.is_current_frame_active(sequences) {
+                self.deactivate_current_frame(sequences, hitboxes, &mut events);
+            }
+            self.reset_current_frame(sequences);
+            self.frame -= 1;
+
+            let (duration, delay) = sequences
+                .get(&self.name)
+                .map(|frames| {
+                    frames
+                        .get(self.frame)
+                        .map(|f| (f.duration_secs(fps), f.delay_secs(fps)))
+                })
+                .flatten()
+                .unwrap_or((0.0, 0.0));
+            self.elapsed_time += duration + delay;
+            events.push(HitboxSequenceEvent::FrameEntered { frame: self.frame });
+        }
+        self.elapsed_time = self.elapsed_time.max(0.0);
+
+        let delay = sequences
+            .get(&self.name)
+            .map(|frames| frames.get(self.frame).map(|f| f.delay_secs(fps)))
+            .flatten()
+            .unwrap_or(0.0);
+        let should_be_active = self.elapsed_time >= delay;
+        if should_be_active && !self.is_current_frame_active(sequences) {
+            self.activate_current_frame(sequences, hitboxes, &mut events);
+        } else if !should_be_active && self.is_current_frame_active(sequences) {
+            self.deactivate_current_frame(sequences, hitboxes, &mut events);
+        }
+
+        events
+    }
+
+    fn reset_current_frame(&mut self, sequences: &mut HashMap<String, Vec<HitboxSequenceFrame>>) {
         sequences
             .get_mut(&self.name)
             .map(|frames| frames.get_mut(self.frame).map(|f| f.reset()));
@@ -416,6 +1137,22 @@ impl ActiveSequenceData {
                 .map(|e| HitboxSequenceEvent::HitboxActivated { hitbox: e })
                 .collect::<Vec<HitboxSequenceEvent>>(),
         );
+        if let Some(colliders) = sequences
+            .get(&self.name)
+            .map(|frames| frames.get(self.frame))
+            .flatten()
+            .map(|frame| frame.colliders.clone())
+            .flatten()
+        {
+            events.extend(
+                self.get_current_active_hitboxes(sequences, hitboxes)
+                    .into_iter()
+                    .map(|hitbox| HitboxSequenceEvent::CollidersSelected {
+                        hitbox,
+                        colliders: colliders.clone(),
+                    }),
+            );
+        }
         sequences
             .get_mut(&self.name)
             .map(|frames| frames.get_mut(self.frame).map(|f| f.active = true));
@@ -438,6 +1175,7 @@ impl ActiveSequenceData {
     }
 }
 
+/// Returns the number of frames in the named sequence, 0 for empty sequences.
 pub fn get_sequence_frame_count<T: Into<String>>(
     sequences: &HashMap<String, Vec<HitboxSequenceFrame>>,
     name: T,
@@ -460,6 +1198,101 @@ pub fn get_hitbox_owner(world: &World, hitbox: Entity) -> Option<Entity> {
         .flatten()
 }
 
+/// Returns the world position of a hitbox, kept in sync with its owner by its tracker
+pub fn get_hitbox_position(world: &World, hitbox: Entity) -> Option<Vector2<f32>> {
+    if !world.has::<Hitbox>(hitbox) {
+        return None;
+    }
+
+    world
+        .get::<&Transform>(hitbox)
+        .ok()
+        .map(|transform| translation_to_vector(&transform.translation))
+}
+
+/// Returns the closest entity the hitbox could damage within the given range.
+/// Only owners of active hurtboxes are considered, the hitbox's own owner is excluded
+/// unless its set is neutral.
+pub fn nearest_target(world: &World, hitbox: Entity, max_range: f32) -> Option<Entity> {
+    let position = get_hitbox_position(world, hitbox)?;
+    let hitbox_owner = get_hitbox_owner(world, hitbox)?;
+    let neutral = world
+        .get::<&Hitbox>(hitbox)
+        .ok()
+        .map(|h| {
+            world
+                .get::<&HitboxSet>(h.parent_set)
+                .ok()
+                .map(|s| s.neutral)
+        })
+        .flatten()
+        .unwrap_or(false);
+
+    let targets = get_creatures_from_hurtboxes(world, get_all_active_hurtboxes(world));
+    let hitbox = world.get::<&Hitbox>(hitbox).ok()?;
+
+    targets
+        .into_iter()
+        .filter(|target| neutral || *target != hitbox_owner)
+        .filter(|target| hitbox.can_damage_entity(target))
+        .filter_map(|target| {
+            world.get::<&Transform>(target).ok().map(|t| {
+                (
+                    target,
+                    (translation_to_vector(&t.translation) - position).norm(),
+                )
+            })
+        })
+        .filter(|(_, distance)| *distance <= max_range)
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+        .map(|(target, _)| target)
+}
+
+/// Returns the hitbox and its siblings from the same set and hit group.
+pub fn get_hit_group_hitboxes(world: &World, hitbox: Entity) -> Vec<Entity> {
+    let (parent_set, hit_group) = match world.get::<&Hitbox>(hitbox) {
+        Ok(h) => (h.parent_set, h.hit_group.clone()),
+        Err(_) => return Vec::new(),
+    };
+
+    match hit_group {
+        Some(hit_group) => world
+            .query::<&Hitbox>()
+            .iter()
+            .filter(|(_, h)| h.parent_set == parent_set && h.hit_group.as_ref() == Some(&hit_group))
+            .map(|(id, _)| id)
+            .collect(),
+        None => vec![hitbox],
+    }
+}
+
+/// Returns the callback set with `Hitbox::set_on_hit`, if the hitbox has one.
+pub fn get_hitbox_on_hit(world: &World, hitbox: Entity) -> Option<OnHitFn> {
+    world
+        .get::<&Hitbox>(hitbox)
+        .ok()
+        .map(|h| h.get_on_hit())
+        .flatten()
+}
+
+/// Returns every hitbox of the owner, both those of its hitbox set
+/// and standalone hitboxes spawned with the owner as their parent.
+pub fn get_hitboxes_for_owner(world: &World, owner: Entity) -> Vec<Entity> {
+    let mut hitboxes = world
+        .get::<&HitboxSet>(owner)
+        .ok()
+        .map(|set| set.hitboxes.values().cloned().collect::<Vec<Entity>>())
+        .unwrap_or_default();
+
+    for (id, hitbox) in world.query::<&Hitbox>().iter() {
+        if hitbox.parent_set == owner && !hitboxes.contains(&id) {
+            hitboxes.push(id);
+        }
+    }
+
+    hitboxes
+}
+
 pub fn is_hitbox_owner(world: &World, id: Entity, hitbox_id: Entity) -> bool {
     get_hitbox_owner(world, hitbox_id)
         .map(|owner| owner == id)
@@ -475,21 +1308,128 @@ pub struct Hitbox {
     /// One time hitbox deactivation trigger, useful for spawned bullets/hitbox ents
     deactivate_after: Option<f32>,
 
+    /// Despawns the hitbox once this much time has elapsed, useful for projectiles
+    pub lifetime: Option<f32>,
+
     elapsed_time: f32,
 
     pub parent_set: Entity,
     pub raw_collider_data: Vec<RectCollider>,
     pub colliders: HashMap<String, ColliderHandle>,
 
+    /// Handles of every collider, in the same order as `raw_collider_data`
+    collider_handles: Vec<ColliderHandle>,
+
+    /// Size multiplier of the colliders, see `set_hitbox_scale`
+    scale: f32,
+
     /// How much time must progress before the hitbox is allowed to damage the same entity twice
     cooldown_per_entity: Option<f32>,
 
+    /// `HitmeConfig::default_cooldown_per_entity` as of the hitbox's last hit,
+    /// used while `cooldown_per_entity` is `None`
+    pub(crate) default_cooldown_per_entity: Option<f32>,
+
+    /// Damage over time, hits each entity inside the hitbox once every interval.
+    /// The first tick lands one interval after the entity enters. Overrides `cooldown_per_entity`.
+    pub tick_interval: Option<f32>,
+
+    /// How many distinct entities one activation may hit, until the next refresh.
+    /// None means unlimited.
+    pub max_targets: Option<u32>,
+
     /// Entities that have been damaged by this hitbox, and how much time has elapsed since they've been hit
     pub damaged_entities: HashMap<Entity, f32>,
 
+    /// Whether or not the hitbox is visible when debug drawing, defaults to true
     pub visible: bool,
+
+    /// Arbitrary gameplay data, loaded from any TOML keys the hitbox doesn't use itself
+    pub properties: HashMap<String, Value>,
+
+    pub hit_mode: HitMode,
+
+    /// If set, the hitbox only hits hurtboxes with at least one of these tags
+    pub target_hurtbox_tags: Option<Vec<String>>,
+
+    /// How much damage the hitbox deals, negative values heal
+    pub damage: f32,
+
+    /// Hitboxes of the same set and hit group are one attack, and only damage an entity once
+    pub hit_group: Option<String>,
+
+    /// When several hitboxes of an owner hit the same entity in one frame,
+    /// only those with the highest priority connect. Defaults to 0
+    pub priority: i32,
+
+    /// Knockback imparted on hit entities, the direction scaled by its force
+    pub knockback: Option<Vector2<f32>>,
+
+    /// The team the hitbox fights for, defaults to the team of its set.
+    /// Hitboxes don't hit hurtboxes of the same team, unless friendly fire is on.
+    pub team: Option<u32>,
+
+    pub friendly_fire: bool,
+
+    /// Deals a burst of hits to each entity it touches, instead of a single hit
+    pub burst: Option<BurstConfig>,
+
+    /// Status effects applied to the entities the hitbox hits, ex. ["stun", "poison"]
+    pub applied_effects: Vec<StatusEffect>,
+
+    /// How many burst hits each entity has taken since the last refresh
+    burst_hits: HashMap<Entity, u32>,
+
+    /// Entities this hitbox overlapped last frame, used by `HitMode::OnEnter`
+    overlapping_entities: HashSet<Entity>,
+
+    /// Names of colliders that have been switched off
+    inactive_colliders: HashSet<String>,
+
+    /// Collision groups given to the hitbox's colliders while they're active
+    collision_groups: InteractionGroups,
+
+    /// Called for hits landed by this hitbox only, after the global on hit callbacks
+    on_hit: Option<OnHitFn>,
+
+    /// Also hits hurtboxes the hitbox passed through since the last tick, ex. fast projectiles.
+    /// See `get_swept_hurtboxes`.
+    pub continuous: bool,
+
+    /// Translation at the end of the last tick, only recorded for continuous hitboxes
+    previous_translation: Option<Translation>,
+
+    /// Clashes with opposing hitboxes that also clash, see `HitmeConfig::add_on_clash_fn`.
+    /// Off by default, so hitboxes only overlap hurtboxes.
+    pub clashes: bool,
 }
 impl Hitbox {
+    /// TOML keys read by `Hitbox::from_toml`, any other key is stored as a property
+    const RESERVED_KEYS: &'static [&'static str] = &[
+        "active",
+        "colliders",
+        "activate_after",
+        "deactivate_after",
+        "lifetime",
+        "cooldown_per_entity",
+        "tick_interval",
+        "max_targets",
+        "visible",
+        "hit_mode",
+        "target_hurtbox_tags",
+        "burst",
+        "damage",
+        "knockback",
+        "team",
+        "friendly_fire",
+        "hit_group",
+        "applied_effects",
+        "priority",
+        "offset",
+        "continuous",
+        "clashes",
+    ];
+
     pub fn from_toml(
         world: &World,
         value: &emerald::toml::Value,
@@ -521,7 +1461,9 @@ impl Hitbox {
             .flatten()
             .map(|f| f as f32);
 
-        // default to 1 second
+        let lifetime = value.get("lifetime").map(toml_value_to_f32).flatten();
+
+        // No cooldown unless given, see `HitmeConfig::default_cooldown_per_entity`
         let mut cooldown_per_entity = None;
 
         if let Some(cd) = value.get("cooldown_per_entity") {
@@ -530,26 +1472,136 @@ impl Hitbox {
             }
         }
 
+        let tick_interval = value.get("tick_interval").map(toml_value_to_f32).flatten();
+
+        let max_targets = value
+            .get("max_targets")
+            .map(|v| v.as_integer())
+            .flatten()
+            .map(|n| n.max(0) as u32);
+
         let visible = value
             .get("visible")
-            .unwrap_or(&emerald::toml::Value::Boolean(false))
-            .as_bool()
+            .map(|v| v.as_bool())
+            .flatten()
+            .unwrap_or(true);
+
+        let hit_mode = value
+            .get("hit_mode")
+            .map(|v| v.as_str())
+            .flatten()
+            .map(HitMode::from_str)
+            .transpose()?
+            .unwrap_or(HitMode::Cooldown);
+
+        let target_hurtbox_tags = value
+            .get("target_hurtbox_tags")
+            .map(|v| toml_value_to_strings(Some(v)));
+
+        let burst = value.get("burst").map(BurstConfig::from_toml);
+
+        let applied_effects = toml_value_to_strings(value.get("applied_effects"))
+            .iter()
+            .map(|effect| StatusEffect::from_str(effect))
+            .collect::<Result<Vec<StatusEffect>, EmeraldError>>()?;
+
+        let damage = value
+            .get("damage")
+            .map(toml_value_to_f32)
+            .flatten()
+            .unwrap_or(0.0);
+
+        let knockback = value.get("knockback").map(toml_value_to_knockback);
+
+        let priority = value
+            .get("priority")
+            .map(|v| v.as_integer())
+            .flatten()
+            .map(|n| n as i32)
+            .unwrap_or(0);
+
+        let hit_group = value
+            .get("hit_group")
+            .map(|v| v.as_str())
+            .flatten()
+            .map(|s| s.to_string());
+        let team = toml_value_to_team(value);
+        let friendly_fire = value
+            .get("friendly_fire")
+            .map(|v| v.as_bool())
+            .flatten()
+            .unwrap_or(false);
+
+        let continuous = value
+            .get("continuous")
+            .map(|v| v.as_bool())
+            .flatten()
+            .unwrap_or(false);
+
+        let clashes = value
+            .get("clashes")
+            .map(|v| v.as_bool())
+            .flatten()
             .unwrap_or(false);
 
+        let properties = value
+            .as_table()
+            .map(|table| {
+                table
+                    .iter()
+                    .filter(|(key, _)| !Self::RESERVED_KEYS.contains(&key.as_str()))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
         Ok(Self {
             parent_set,
             colliders: HashMap::new(),
+            collider_handles: Vec::new(),
+            scale: 1.0,
             raw_collider_data: colliders,
             active,
             damaged_entities: HashMap::new(),
             activate_after,
             deactivate_after,
+            lifetime,
             cooldown_per_entity,
+            default_cooldown_per_entity: None,
+            tick_interval,
+            max_targets,
             elapsed_time: 0.0,
             visible,
+            properties,
+            hit_mode,
+            overlapping_entities: HashSet::new(),
+            inactive_colliders: HashSet::new(),
+            collision_groups: InteractionGroups::all(),
+            on_hit: None,
+            target_hurtbox_tags,
+            burst,
+            burst_hits: HashMap::new(),
+            applied_effects,
+            damage,
+            hit_group,
+            priority,
+            knockback,
+            team,
+            friendly_fire,
+            continuous,
+            previous_translation: None,
+            clashes,
         })
     }
 
+    pub fn get_property(&self, key: &str) -> Option<&Value> {
+        self.properties.get(key)
+    }
+
+    pub fn set_property<T: Into<String>>(&mut self, key: T, value: Value) -> Option<Value> {
+        self.properties.insert(key.into(), value)
+    }
+
     pub fn is_one_time(&self) -> bool {
         self.activate_after.is_some() || self.deactivate_after.is_some()
     }
@@ -558,23 +1610,182 @@ impl Hitbox {
         self.active
     }
 
+    /// Whether the hitbox has outlived its lifetime, and should be despawned.
+    pub fn is_expired(&self) -> bool {
+        self.lifetime
+            .map(|lifetime| self.elapsed_time >= lifetime)
+            .unwrap_or(false)
+    }
+
     pub fn deactivate(&mut self) {
         self.active = false;
+        self.overlapping_entities.clear();
     }
 
+    /// Activates the hitbox, without telling the active box index of its world.
+    /// Prefer `set_hitbox_active`, or call `mark_active_boxes_stale` afterwards.
     pub fn activate(&mut self) {
         self.active = true;
     }
 
+    pub fn get_collision_groups(&self) -> InteractionGroups {
+        self.collision_groups
+    }
+
+    /// Sets a callback for hits landed by this hitbox only, ex. the effect of a special bullet.
+    /// Called in addition to the global on hit callbacks.
+    pub fn set_on_hit(&mut self, handler: OnHitFn) {
+        self.on_hit = Some(handler);
+    }
+
+    pub fn clear_on_hit(&mut self) {
+        self.on_hit = None;
+    }
+
+    pub fn get_on_hit(&self) -> Option<OnHitFn> {
+        self.on_hit
+    }
+
+    pub fn get_scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets the size multiplier of the hitbox, `raw_collider_data` is left untouched.
+    /// Returns the collider handles and the scaled colliders they should now use,
+    /// use `set_hitbox_scale` to also apply them to the physics colliders.
+    pub fn set_scale(&mut self, scale: f32) -> Vec<(ColliderHandle, RectCollider)> {
+        // Tiny scales would leave the physics engine with degenerate shapes
+        self.scale = scale.max(MIN_HITBOX_SCALE);
+
+        self.physics_colliders()
+    }
+
+    /// The handles of the hitbox's physics colliders, with the colliders at the current scale
+    pub(crate) fn physics_colliders(&self) -> Vec<(ColliderHandle, RectCollider)> {
+        self.collider_handles
+            .iter()
+            .cloned()
+            .zip(self.scaled_colliders())
+            .collect()
+    }
+
+    /// The colliders at the hitbox's current scale
+    pub fn scaled_colliders(&self) -> Vec<RectCollider> {
+        self.raw_collider_data
+            .iter()
+            .map(|collider| collider.scaled(self.scale))
+            .collect()
+    }
+
+    /// The colliders at the hitbox's current scale, without named ones that were switched off
+    pub(crate) fn active_scaled_colliders(&self) -> Vec<RectCollider> {
+        self.scaled_colliders()
+            .into_iter()
+            .filter(|collider| {
+                collider
+                    .name
+                    .as_ref()
+                    .map(|name| self.is_collider_active(name))
+                    .unwrap_or(true)
+            })
+            .collect()
+    }
+
+    pub fn is_collider_active(&self, name: &str) -> bool {
+        self.colliders.contains_key(name) && !self.inactive_colliders.contains(name)
+    }
+
+    /// Marks a single named collider of the hitbox as on or off.
+    /// Returns the collider's handle and the collision groups it should now use,
+    /// use `set_hitbox_collider_active` to also apply them to the physics collider.
+    pub fn set_collider_active(
+        &mut self,
+        name: &str,
+        active: bool,
+    ) -> Option<(ColliderHandle, InteractionGroups)> {
+        let handle = self.colliders.get(name).cloned()?;
+
+        if active {
+            self.inactive_colliders.remove(name);
+            Some((handle, self.collision_groups))
+        } else {
+            self.inactive_colliders.insert(name.to_string());
+            Some((handle, InteractionGroups::none()))
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
     /// Refreshes the hitbox, clearing damaged entities
     pub fn refresh(&mut self) {
         self.damaged_entities = HashMap::new();
+        self.burst_hits = HashMap::new();
+    }
+
+    /// Whether the hitbox already hit `max_targets` entities, other than the given one.
+    pub fn is_at_max_targets(&self, other_entity: &Entity) -> bool {
+        match self.max_targets {
+            Some(max_targets) => {
+                !self.damaged_entities.contains_key(other_entity)
+                    && self.damaged_entities.len() >= max_targets as usize
+            }
+            None => false,
+        }
     }
 
+    /// Whether the hitbox's cooldowns allow hitting the entity again.
+    /// Hitboxes without their own `cooldown_per_entity` use the config's default
+    /// recorded by their last hit, so checks made without the config agree with the hits.
+    /// Entities hit during the current frame are also skipped by `resolve_hits`,
+    /// so overlapping several of their hurtboxes only lands one hit.
     pub fn can_damage_entity(&self, other_entity: &Entity) -> bool {
+        self.can_damage_entity_with_default(other_entity, self.default_cooldown_per_entity)
+    }
+
+    /// Like `can_damage_entity`, but a hitbox without its own `cooldown_per_entity`
+    /// uses the given cooldown, see `HitmeConfig::default_cooldown_per_entity`.
+    pub fn can_damage_entity_with_default(
+        &self,
+        other_entity: &Entity,
+        default_cooldown_per_entity: Option<f32>,
+    ) -> bool {
+        if self.is_at_max_targets(other_entity) {
+            return false;
+        }
+
+        if let Some(burst) = &self.burst {
+            return match self.damaged_entities.get(other_entity) {
+                Some(elapsed) => {
+                    let hits = self.burst_hits.get(other_entity).cloned().unwrap_or(0);
+                    hits < burst.count && *elapsed >= burst.interval
+                }
+                None => true,
+            };
+        }
+
+        if let Some(tick_interval) = self.tick_interval {
+            return self
+                .damaged_entities
+                .get(other_entity)
+                .map(|elapsed| *elapsed >= tick_interval)
+                .unwrap_or(false);
+        }
+
+        match self.hit_mode {
+            HitMode::Continuous => return true,
+            HitMode::OnEnter => return !self.overlapping_entities.contains(other_entity),
+            HitMode::Cooldown => {}
+        }
+
         if let Some(delta) = self.damaged_entities.get(other_entity) {
-            if let Some(cd) = &self.cooldown_per_entity {
-                return delta >= cd;
+            if let Some(cd) = self.cooldown_per_entity.or(default_cooldown_per_entity) {
+                return *delta >= cd;
             }
 
             true
@@ -583,6 +1794,19 @@ impl Hitbox {
         }
     }
 
+    /// Returns true if the hurtbox is on the same team, and friendly fire is off.
+    pub fn is_ally(&self, hurtbox: &Hurtbox) -> bool {
+        !self.friendly_fire && self.team.is_some() && self.team == hurtbox.team
+    }
+
+    /// Whether the hitbox targets a hurtbox with the given tags
+    pub fn targets_hurtbox(&self, hurtbox: &Hurtbox) -> bool {
+        self.target_hurtbox_tags
+            .as_ref()
+            .map(|tags| tags.iter().any(|tag| hurtbox.has_tag(tag)))
+            .unwrap_or(true)
+    }
+
     pub fn add_damaged_entity(&mut self, entity: Entity) {
         self.add_damaged_entities([entity].to_vec());
     }
@@ -590,80 +1814,716 @@ impl Hitbox {
     pub fn add_damaged_entities(&mut self, entities: Vec<Entity>) {
         for id in entities {
             self.damaged_entities.insert(id, 0.0);
+
+            if self.burst.is_some() {
+                *self.burst_hits.entry(id).or_insert(0) += 1;
+            }
         }
     }
+
+    /// For burst hitboxes, returns the index within the burst of the next hit on the given entity
+    pub fn next_burst_index(&self, entity: &Entity) -> Option<u32> {
+        self.burst
+            .as_ref()
+            .map(|_| self.burst_hits.get(entity).cloned().unwrap_or(0))
+    }
 }
 
-pub fn refresh_hitboxes(world: &mut World, id: Entity) {
-    let mut hitboxes = Vec::new();
-    if let Ok(set) = world.get::<&HitboxSet>(id) {
-        for (_, id) in &set.hitboxes {
-            hitboxes.push(id.clone());
+/// Records the entities each `HitMode::OnEnter` hitbox is overlapping this frame.
+/// Ticking hitboxes start timing entities that entered them, and forget those that left.
+/// `owners` maps each active hitbox to the owners of the hurtboxes it overlaps.
+pub(crate) fn update_hitbox_overlaps(world: &mut World, owners: &HashMap<Entity, HashSet<Entity>>) {
+    for (id, hitbox) in world.query::<&mut Hitbox>().iter() {
+        if hitbox.tick_interval.is_some() {
+            let overlapping = owners.get(&id).cloned().unwrap_or_default();
+            hitbox
+                .damaged_entities
+                .retain(|entity, _| overlapping.contains(entity));
+            for entity in overlapping {
+                hitbox.damaged_entities.entry(entity).or_insert(0.0);
+            }
+            continue;
         }
-    }
 
-    if world.has::<Hitbox>(id) {
-        hitboxes.push(id);
-    }
+        if hitbox.hit_mode != HitMode::OnEnter {
+            continue;
+        }
 
-    for id in hitboxes {
-        world.get::<&mut Hitbox>(id).map(|mut h| h.refresh()).ok();
+        hitbox.overlapping_entities = owners.get(&id).cloned().unwrap_or_default();
     }
 }
 
-#[derive(Clone)]
-pub enum StatusEffect {
-    Stun,
-    Poison,
+/// Returns the active hurtboxes a continuous hitbox passed through since the last tick.
+/// The hitbox's colliders are cast along the whole path, so no hurtbox is too thin to hit.
+pub fn get_swept_hurtboxes(world: &World, hitbox_id: Entity) -> Vec<Entity> {
+    get_swept_hurtbox_contacts(world, hitbox_id)
+        .into_iter()
+        .map(|(hurtbox, _)| hurtbox)
+        .collect()
 }
 
-pub fn get_all_active_hitboxes(world: &World) -> Vec<Entity> {
+/// Returns the active hurtboxes a continuous hitbox passed through since the last tick,
+/// along with when along the path it first touched them, from 0.0 to 1.0.
+/// Switched off colliders, and hurtboxes the hitbox's collision groups exclude, are skipped.
+pub fn get_swept_hurtbox_contacts(world: &World, hitbox_id: Entity) -> Vec<(Entity, f32)> {
+    let (colliders, groups, from) = match world.get::<&Hitbox>(hitbox_id) {
+        Ok(hitbox) if hitbox.continuous => match hitbox.previous_translation.clone() {
+            Some(from) => (
+                hitbox.active_scaled_colliders(),
+                hitbox.collision_groups,
+                from,
+            ),
+            None => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+    let transform = match world.get::<&Transform>(hitbox_id) {
+        Ok(transform) => transform.clone(),
+        Err(_) => return Vec::new(),
+    };
+
+    let travel = translation_to_vector(&transform.translation) - translation_to_vector(&from);
+    if colliders.is_empty() || travel.norm() == 0.0 {
+        return Vec::new();
+    }
+    let mut start = transform;
+    start.translation = from;
+
     world
-        .query::<&Hitbox>()
+        .query::<(&Hurtbox, &Transform)>()
         .iter()
-        .filter_map(|(id, hitbox)| hitbox.active.then(|| id))
+        .filter(|(_, (hurtbox, _))| hurtbox.active && !hurtbox.is_invulnerable())
+        .filter(|(_, (hurtbox, _))| {
+            world
+                .get::<&HurtboxSet>(hurtbox.parent_set)
+                .map(|set| groups.test(set.get_collision_groups()))
+                .unwrap_or(true)
+        })
+        .filter_map(|(id, (hurtbox, hurtbox_transform))| {
+            hurtbox
+                .colliders
+                .iter()
+                .flat_map(|collider| {
+                    colliders.iter().filter_map(|hitbox_collider| {
+                        hitbox_collider.first_contact_time(
+                            &start,
+                            &travel,
+                            collider,
+                            hurtbox_transform,
+                        )
+                    })
+                })
+                .reduce(f32::min)
+                .map(|time| (id, time))
+        })
         .collect()
 }
 
-/// Updates hitboxes
-pub fn hitbox_system(
-    emd: &mut Emerald,
-    world: &mut World,
-    config: &HitmeConfig,
-) -> Result<(), EmeraldError> {
-    hitbox_one_time_system(emd, world, config)?;
-    hitbox_damaged_entity_delta_system(emd, world, config);
-    hitbox_sequence_system(emd, world, config)?;
+/// Records where continuous hitboxes are, for the next tick's sweep.
+pub(crate) fn record_hitbox_translations(world: &mut World) {
+    for (_, (hitbox, transform)) in world.query::<(&mut Hitbox, &Transform)>().iter() {
+        if hitbox.continuous {
+            hitbox.previous_translation = Some(transform.translation.clone());
+        }
+    }
+}
 
-    Ok(())
+/// Reads a knockback table of `x`, `y` and an optional `force`.
+/// When a force is given, the direction is normalized and scaled by it.
+fn toml_value_to_knockback(value: &emerald::toml::Value) -> Vector2<f32> {
+    let direction = translation_to_vector(&toml_value_to_translation(value));
+    let force = value.get("force").map(toml_value_to_f32).flatten();
+
+    match force {
+        Some(force) if direction.norm() > 0.0 => direction.normalize() * force,
+        _ => direction,
+    }
 }
 
-fn hitbox_damaged_entity_delta_system(emd: &mut Emerald, world: &mut World, config: &HitmeConfig) {
-    let delta = config.get_delta(emd, world);
+/// A number of hits dealt to an entity over time, by a single hitbox activation.
+/// Overrides the hitbox's `hit_mode`, `tick_interval` and `cooldown_per_entity`,
+/// each entity it touches takes up to `count` hits spaced `interval` apart until it's refreshed.
+#[derive(Clone, Debug)]
+pub struct BurstConfig {
+    /// How many hits are dealt to each entity
+    pub count: u32,
 
-    for (_, hitbox) in world.query::<&mut Hitbox>().iter() {
-        for (_, e_d) in &mut hitbox.damaged_entities {
-            *e_d = *e_d + delta;
-        }
+    /// Time between each hit of the burst
+    pub interval: f32,
+}
+impl BurstConfig {
+    pub fn from_toml(value: &emerald::toml::Value) -> Self {
+        let count = value
+            .get("count")
+            .map(|v| v.as_integer())
+            .flatten()
+            .unwrap_or(1)
+            .max(0) as u32;
+        let interval = value
+            .get("interval")
+            .map(|v| v.as_float())
+            .flatten()
+            .unwrap_or(0.0) as f32;
+
+        Self { count, interval }
     }
 }
 
-fn hitbox_one_time_system(
-    emd: &mut Emerald,
-    world: &mut World,
-    config: &HitmeConfig,
-) -> Result<(), EmeraldError> {
-    for (id, hitbox) in world
-        .query::<&mut Hitbox>()
-        .iter()
-        .filter(|(_, h)| h.is_one_time())
-    {
-        hitbox.elapsed_time += config.get_delta_for_entity(emd, world, id);
+/// Determines how often a hitbox may hit an entity it is overlapping
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HitMode {
+    /// Hits again once `cooldown_per_entity` has elapsed
+    Cooldown,
 
-        if let Some(trigger) = &hitbox.activate_after {
+    /// Hits once when the overlap begins, and again only after separating and re-entering
+    OnEnter,
+
+    /// Hits every frame while overlapping
+    Continuous,
+}
+impl HitMode {
+    pub fn from_str(value: &str) -> Result<Self, EmeraldError> {
+        match value {
+            "cooldown" => Ok(HitMode::Cooldown),
+            "on_enter" => Ok(HitMode::OnEnter),
+            "continuous" => Ok(HitMode::Continuous),
+            _ => Err(EmeraldError::new(format!("Unknown hit mode {}", value))),
+        }
+    }
+}
+
+pub fn refresh_hitboxes(world: &mut World, id: Entity) {
+    let mut hitboxes = Vec::new();
+    if let Ok(set) = world.get::<&HitboxSet>(id) {
+        for (_, id) in &set.hitboxes {
+            hitboxes.push(id.clone());
+        }
+    }
+
+    if world.has::<Hitbox>(id) {
+        hitboxes.push(id);
+    }
+
+    for id in hitboxes {
+        world.get::<&mut Hitbox>(id).map(|mut h| h.refresh()).ok();
+    }
+}
+
+/// Refreshes every hitbox in the world, clearing their damaged entities
+pub fn refresh_all_hitboxes(world: &mut World) {
+    for (_, hitbox) in world.query::<&mut Hitbox>().iter() {
+        hitbox.refresh();
+    }
+}
+
+/// Time elapsed since a hitbox last damaged an entity, see `Hitbox::damaged_entities`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(crate = "emerald::serde")]
+pub struct DamagedEntitySnapshot {
+    /// The entity's bits, see `Entity::to_bits`
+    pub entity: u64,
+    pub elapsed: f32,
+}
+
+/// The runtime state of a hitbox, colliders are rebuilt from TOML rather than saved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(crate = "emerald::serde")]
+pub struct HitboxSnapshot {
+    pub active: bool,
+    pub elapsed_time: f32,
+    pub damaged_entities: Vec<DamagedEntitySnapshot>,
+}
+
+/// The runtime state of a hitbox set and its hitboxes by name, ex. for save games.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(crate = "emerald::serde")]
+pub struct HitboxSetSnapshot {
+    /// Whether the current frame of the active sequence has activated its hitboxes
+    pub current_frame_active: bool,
+
+    /// Which tags of the current frame have triggered, in order
+    pub triggered_tags: Vec<bool>,
+
+    pub active_sequence: Option<ActiveSequenceData>,
+    pub hitboxes: HashMap<String, HitboxSnapshot>,
+
+    /// Sequences waiting to start after the active one, in order
+    #[serde(default)]
+    pub sequence_queue: Vec<String>,
+}
+
+/// Captures the runtime state of the owner's hitbox set.
+pub fn snapshot_hitbox_set(world: &World, owner: Entity) -> Option<HitboxSetSnapshot> {
+    let hitbox_set = world.get::<&HitboxSet>(owner).ok()?;
+    let current_frame = hitbox_set
+        .active_sequence
+        .as_ref()
+        .map(|active_sequence| {
+            hitbox_set
+                .sequences
+                .get(&active_sequence.name)
+                .map(|frames| frames.get(active_sequence.frame))
+                .flatten()
+        })
+        .flatten();
+
+    let hitboxes = hitbox_set
+        .hitboxes
+        .iter()
+        .filter_map(|(name, id)| {
+            let hitbox = world.get::<&Hitbox>(*id).ok()?;
+            let damaged_entities = hitbox
+                .damaged_entities
+                .iter()
+                .map(|(entity, elapsed)| DamagedEntitySnapshot {
+                    entity: u64::from(entity.to_bits()),
+                    elapsed: *elapsed,
+                })
+                .collect();
+
+            Some((
+                name.clone(),
+                HitboxSnapshot {
+                    active: hitbox.active,
+                    elapsed_time: hitbox.elapsed_time,
+                    damaged_entities,
+                },
+            ))
+        })
+        .collect();
+
+    Some(HitboxSetSnapshot {
+        current_frame_active: current_frame.map(|frame| frame.active).unwrap_or(false),
+        triggered_tags: current_frame
+            .map(|frame| frame.tags.iter().map(|tag| tag.triggered).collect())
+            .unwrap_or_default(),
+        active_sequence: hitbox_set.active_sequence.clone(),
+        hitboxes,
+        sequence_queue: hitbox_set.sequence_queue.iter().cloned().collect(),
+    })
+}
+
+/// Restores a snapshot onto the owner's hitbox set, which must already be loaded from TOML.
+/// Saved entities are re-linked through the entity map, keyed by their saved bits.
+/// Damaged entities missing from the map are dropped.
+pub fn restore_from_snapshot(
+    world: &mut World,
+    owner: Entity,
+    snapshot: &HitboxSetSnapshot,
+    entity_map: &HashMap<u64, Entity>,
+) -> Result<(), EmeraldError> {
+    let hitboxes = {
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner)?;
+        hitbox_set.reset_sequences();
+        hitbox_set.active_sequence = snapshot.active_sequence.clone();
+        hitbox_set.sequence_queue = snapshot
+            .sequence_queue
+            .iter()
+            .filter(|name| hitbox_set.sequences.contains_key(*name))
+            .cloned()
+            .collect();
+
+        if let Some(active_sequence) = snapshot.active_sequence.as_ref() {
+            let current_frame = hitbox_set
+                .sequences
+                .get_mut(&active_sequence.name)
+                .map(|frames| frames.get_mut(active_sequence.frame))
+                .flatten();
+            if let Some(frame) = current_frame {
+                frame.active = snapshot.current_frame_active;
+                for (tag, triggered) in frame.tags.iter_mut().zip(snapshot.triggered_tags.iter()) {
+                    tag.triggered = *triggered;
+                }
+            }
+        }
+
+        hitbox_set.hitboxes.clone()
+    };
+
+    for (name, hitbox_snapshot) in &snapshot.hitboxes {
+        let id = match hitboxes.get(name) {
+            Some(id) => *id,
+            None => continue,
+        };
+
+        {
+            let mut hitbox = world.get::<&mut Hitbox>(id)?;
+            hitbox.active = hitbox_snapshot.active;
+            hitbox.elapsed_time = hitbox_snapshot.elapsed_time;
+            hitbox.damaged_entities = hitbox_snapshot
+                .damaged_entities
+                .iter()
+                .filter_map(|damaged| {
+                    entity_map
+                        .get(&damaged.entity)
+                        .map(|entity| (*entity, damaged.elapsed))
+                })
+                .collect();
+        }
+        if hitbox_snapshot.active {
+            index_hitbox(world, id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the owner's hitbox set from new TOML, ex. after editing it while the game runs.
+/// Old hitbox entities and their colliders are despawned, and new ones are built.
+/// Hitboxes that kept their name keep their active state and damaged entities.
+/// The active sequence keeps its progress if its current frame still targets the same hitboxes.
+pub fn reload_hitbox_set(
+    world: &mut World,
+    owner: Entity,
+    value: &emerald::toml::Value,
+    hurtbox_group: Group,
+    hitbox_group: Group,
+) -> Result<(), EmeraldError> {
+    let mut hitbox_set = HitboxSet::from_toml(world, value, owner, hurtbox_group, hitbox_group)?;
+    let mut old_hitbox_set = match world.remove_one::<HitboxSet>(owner) {
+        Ok(old_hitbox_set) => old_hitbox_set,
+        Err(_) => {
+            world.insert_one(owner, hitbox_set)?;
+            return Ok(());
+        }
+    };
+
+    fn get_frame<'a>(
+        set: &'a HitboxSet,
+        active_sequence: &ActiveSequenceData,
+    ) -> Option<&'a HitboxSequenceFrame> {
+        set.sequences
+            .get(&active_sequence.name)
+            .map(|frames| frames.get(active_sequence.frame))
+            .flatten()
+    }
+    let sequence_kept = old_hitbox_set
+        .active_sequence
+        .as_ref()
+        .map(|active_sequence| {
+            let old_frame = get_frame(&old_hitbox_set, active_sequence);
+            let new_frame = get_frame(&hitbox_set, active_sequence);
+            match (old_frame, new_frame) {
+                (Some(old_frame), Some(new_frame)) => new_frame.targets_same_hitboxes(old_frame),
+                _ => false,
+            }
+        })
+        .unwrap_or(true);
+
+    let old_active_sequence = old_hitbox_set.active_sequence.take();
+    if let Some(active_sequence) = old_active_sequence.filter(|_| sequence_kept) {
+        let old_frame = get_frame(&old_hitbox_set, &active_sequence);
+        let new_frame = hitbox_set
+            .sequences
+            .get_mut(&active_sequence.name)
+            .map(|frames| frames.get_mut(active_sequence.frame))
+            .flatten();
+        if let (Some(old_frame), Some(new_frame)) = (old_frame, new_frame) {
+            new_frame.copy_progress(old_frame);
+        }
+        hitbox_set.active_sequence = Some(active_sequence);
+    }
+    hitbox_set.suppressed_hitboxes = old_hitbox_set.suppressed_hitboxes;
+    // Queued sequences the new TOML no longer defines are dropped
+    hitbox_set.sequence_queue = old_hitbox_set
+        .sequence_queue
+        .into_iter()
+        .filter(|name| hitbox_set.sequences.contains_key(name))
+        .collect();
+
+    for (name, old_id) in old_hitbox_set.hitboxes {
+        let old_state = world
+            .get::<&Hitbox>(old_id)
+            .ok()
+            .map(|hitbox| (hitbox.active, hitbox.damaged_entities.clone()));
+        let new_id = hitbox_set.hitboxes.get(&name).cloned();
+
+        if let (Some((active, damaged_entities)), Some(new_id)) = (old_state, new_id) {
+            world.get::<&mut Hitbox>(new_id).ok().map(|mut hitbox| {
+                if sequence_kept {
+                    hitbox.active = active;
+                }
+                hitbox.damaged_entities = damaged_entities;
+            });
+            if sequence_kept && active {
+                index_hitbox(world, new_id);
+            }
+        }
+
+        world.despawn(old_id).ok();
+    }
+
+    world.insert_one(owner, hitbox_set)?;
+
+    Ok(())
+}
+
+/// Loads a hitbox set onto the owner.
+/// Returns the owner and the hitbox entities of the set, useful for tweaking them right away.
+pub fn load_hitbox_set(
+    world: &mut World,
+    value: &emerald::toml::Value,
+    owner: Entity,
+    hurtbox_group: Group,
+    hitbox_group: Group,
+) -> Result<(Entity, Vec<Entity>), EmeraldError> {
+    let hitbox_set = HitboxSet::from_toml(world, value, owner, hurtbox_group, hitbox_group)?;
+    let hitboxes = hitbox_set.hitboxes.values().cloned().collect();
+    world.insert_one(owner, hitbox_set)?;
+
+    Ok((owner, hitboxes))
+}
+
+/// Removes the hitbox set from its owner, and despawns every hitbox of the set.
+/// Returns how many hitbox entities were despawned.
+pub fn despawn_hitbox_set(world: &mut World, owner: Entity) -> usize {
+    let hitbox_set = match world.remove_one::<HitboxSet>(owner) {
+        Ok(hitbox_set) => hitbox_set,
+        Err(_) => return 0,
+    };
+
+    hitbox_set
+        .hitboxes
+        .into_values()
+        .filter(|id| world.despawn(id.clone()).is_ok())
+        .count()
+}
+
+/// Stops the active sequence of every hitbox set in the world
+pub fn cancel_all_sequences(world: &mut World) {
+    for (_, hitbox_set) in world.query::<&mut HitboxSet>().iter() {
+        hitbox_set.active_sequence = None;
+        hitbox_set.reset_sequences();
+    }
+}
+
+/// Switches a single named collider of a hitbox on or off.
+/// Inactive colliders are removed from all collision groups, so they overlap nothing.
+pub fn set_hitbox_collider_active(
+    world: &mut World,
+    hitbox_id: Entity,
+    name: &str,
+    active: bool,
+) -> Result<(), EmeraldError> {
+    let (handle, groups) = world
+        .get::<&mut Hitbox>(hitbox_id)?
+        .set_collider_active(name, active)
+        .ok_or_else(|| EmeraldError::new(format!("Hitbox has no collider named {}", name)))?;
+
+    world
+        .physics()
+        .collider_mut(handle)
+        .map(|collider| collider.set_collision_groups(groups));
+
+    Ok(())
+}
+
+/// Builds the physics colliders of a hitbox on the given body, at the hitbox's current scale.
+/// Their handles are recorded on the hitbox, so they can be scaled and switched on and off.
+pub fn build_hitbox_colliders(
+    world: &mut World,
+    hitbox_id: Entity,
+    rbh: RigidBodyHandle,
+) -> Result<(), EmeraldError> {
+    let (colliders, collision_groups) = {
+        let hitbox = world.get::<&Hitbox>(hitbox_id)?;
+        (hitbox.scaled_colliders(), hitbox.collision_groups)
+    };
+
+    for collider in colliders {
+        let name = collider.name.clone();
+        let builder = collider
+            .to_collider_builder()
+            .collision_groups(collision_groups);
+        let handle = world.physics().build_collider(rbh, builder);
+
+        let mut hitbox = world.get::<&mut Hitbox>(hitbox_id)?;
+        hitbox.collider_handles.push(handle);
+        if let Some(collider_name) = name {
+            hitbox.colliders.insert(collider_name, handle);
+        }
+    }
+
+    Ok(())
+}
+
+/// The smallest scale a hitbox can be set to.
+pub const MIN_HITBOX_SCALE: f32 = 0.01;
+
+/// Scales the hitbox's physics colliders, ex. for charged attacks or power ups.
+/// Scaling is always relative to `raw_collider_data`, so a scale of 1.0 restores the original size.
+pub fn set_hitbox_scale(
+    world: &mut World,
+    hitbox_id: Entity,
+    scale: f32,
+) -> Result<(), EmeraldError> {
+    let colliders = world.get::<&mut Hitbox>(hitbox_id)?.set_scale(scale);
+    let (facing, rotation) = world
+        .get::<&SimpleTranslationTracker>(hitbox_id)
+        .ok()
+        .map(|tracker| tracker.posed)
+        .flatten()
+        .unwrap_or((1.0, 0.0));
+
+    for (handle, collider) in colliders {
+        let builder = posed_collider(&collider, facing, rotation).to_collider_builder();
+        world.physics().collider_mut(handle).map(|collider| {
+            collider.set_shape(builder.shape.clone());
+            collider.set_position_wrt_parent(builder.position);
+        });
+    }
+
+    Ok(())
+}
+
+/// Only leaves the named colliders of a hitbox active, switching off all of its others.
+pub fn select_hitbox_colliders(
+    world: &mut World,
+    hitbox_id: Entity,
+    names: &[String],
+) -> Result<(), EmeraldError> {
+    let all_names = world
+        .get::<&Hitbox>(hitbox_id)?
+        .colliders
+        .keys()
+        .cloned()
+        .collect::<Vec<String>>();
+    for name in all_names {
+        set_hitbox_collider_active(world, hitbox_id, &name, names.contains(&name))?;
+    }
+
+    Ok(())
+}
+
+/// Deactivates every hitbox in the world
+pub fn deactivate_all_hitboxes(world: &mut World) {
+    for (_, hitbox) in world.query::<&mut Hitbox>().iter() {
+        hitbox.deactivate();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StatusEffect {
+    Stun,
+    Poison,
+}
+impl StatusEffect {
+    pub fn from_str(value: &str) -> Result<Self, EmeraldError> {
+        match value {
+            "stun" => Ok(StatusEffect::Stun),
+            "poison" => Ok(StatusEffect::Poison),
+            _ => Err(EmeraldError::new(format!(
+                "Unknown status effect {}",
+                value
+            ))),
+        }
+    }
+}
+
+/// A component that status effects can be applied to, see `apply_status_effects_on_hit`.
+pub trait StatusEffectTarget {
+    fn apply_status_effect(&mut self, effect: StatusEffect);
+}
+
+/// Applies the effects to the entity's `T` component, if it has one.
+pub fn apply_status_effects<T: StatusEffectTarget + Send + Sync + 'static>(
+    world: &mut World,
+    id: Entity,
+    effects: &Vec<StatusEffect>,
+) {
+    world.get::<&mut T>(id).ok().map(|mut target| {
+        for effect in effects {
+            target.apply_status_effect(*effect);
+        }
+    });
+}
+
+/// An on hit callback applying the hitbox's effects to the `T` component of the hurt entity.
+/// ex. `config.add_on_hit_fn(apply_status_effects_on_hit::<Status>)`
+pub fn apply_status_effects_on_hit<T: StatusEffectTarget + Send + Sync + 'static>(
+    _: &mut Emerald,
+    world: &mut World,
+    ctx: OnHitContext,
+) {
+    apply_status_effects::<T>(world, ctx.hurt_entity, &ctx.applied_effects);
+}
+
+/// Returns the active hitboxes of the world, see `ActiveBoxIndex`.
+pub fn get_all_active_hitboxes(world: &World) -> Vec<Entity> {
+    get_indexed_active_hitboxes(world)
+}
+
+/// Activates or deactivates the hitbox, keeping the active box index up to date.
+pub fn set_hitbox_active(world: &mut World, id: Entity, active: bool) {
+    if let Ok(mut hitbox) = world.get::<&mut Hitbox>(id) {
+        if active {
+            hitbox.activate();
+        } else {
+            hitbox.deactivate();
+        }
+    }
+    if active {
+        index_hitbox(world, id);
+    }
+}
+
+/// Updates hitboxes.
+/// Tag handlers are looked up in the `HitmeConfig` resource, rather than the given config.
+pub fn hitbox_system(
+    emd: &mut Emerald,
+    world: &mut World,
+    config: &HitmeConfig,
+) -> Result<(), EmeraldError> {
+    run_hitbox_system(emd, world, config)
+}
+
+pub(crate) fn run_hitbox_system(
+    host: &mut impl HitmeHost,
+    world: &mut World,
+    config: &HitmeConfig,
+) -> Result<(), EmeraldError> {
+    hitbox_one_time_system(host, world, config)?;
+    hitbox_damaged_entity_delta_system(host, world, config);
+    hitbox_sequence_system(host, world, config)?;
+
+    Ok(())
+}
+
+fn hitbox_damaged_entity_delta_system(
+    host: &mut impl HitmeHost,
+    world: &mut World,
+    config: &HitmeConfig,
+) {
+    let delta = config.get_host_delta(host, world);
+    advance_damaged_entities(world, delta);
+}
+
+/// Advances the time since each hitbox last damaged each entity.
+pub(crate) fn advance_damaged_entities(world: &mut World, delta: f32) {
+    for (_, hitbox) in world.query::<&mut Hitbox>().iter() {
+        for (_, e_d) in &mut hitbox.damaged_entities {
+            *e_d = *e_d + delta;
+        }
+    }
+}
+
+fn hitbox_one_time_system(
+    host: &mut impl HitmeHost,
+    world: &mut World,
+    config: &HitmeConfig,
+) -> Result<(), EmeraldError> {
+    for (id, hitbox) in world
+        .query::<&mut Hitbox>()
+        .iter()
+        .filter(|(_, h)| h.is_one_time() || h.lifetime.is_some())
+    {
+        hitbox.elapsed_time += config.get_host_delta_for_entity(host, world, id);
+
+        if let Some(trigger) = &hitbox.activate_after {
             if &hitbox.elapsed_time >= trigger {
                 hitbox.activate();
                 hitbox.activate_after.take();
+                index_hitbox(world, id);
             }
         } else {
             if let Some(trigger) = &hitbox.deactivate_after {
@@ -674,190 +2534,1916 @@ fn hitbox_one_time_system(
             }
         }
     }
+
+    despawn_expired_hitboxes(world);
+    Ok(())
+}
+
+/// Despawns every hitbox that has outlived its lifetime, along with its colliders and tracker,
+/// and removes it from its hitbox set. Returns how many hitboxes were despawned.
+pub fn despawn_expired_hitboxes(world: &mut World) -> usize {
+    let expired = world
+        .query::<&Hitbox>()
+        .iter()
+        .filter(|(_, hitbox)| hitbox.is_expired())
+        .map(|(id, hitbox)| (id, hitbox.parent_set))
+        .collect::<Vec<(Entity, Entity)>>();
+
+    for (id, parent_set) in &expired {
+        world
+            .get::<&mut HitboxSet>(*parent_set)
+            .ok()
+            .map(|mut hitbox_set| {
+                hitbox_set.hitboxes.retain(|_, hitbox| hitbox != id);
+            });
+        world.despawn(*id).ok();
+    }
+
+    expired.len()
+}
+
+/// Updates hitbox sequences.
+/// Deactivates hitboxes associated with a finished frame.
+/// Activates hitboxes associated with a starting frame.
+fn hitbox_sequence_system(
+    host: &mut impl HitmeHost,
+    world: &mut World,
+    config: &HitmeConfig,
+) -> Result<(), EmeraldError> {
+    let events = progress_hitbox_sequences(world, config, &mut |world, id| {
+        config.get_host_delta_for_entity(host, world, id)
+    });
+    let tag_triggers = apply_sequence_events(world, events, host.event_buffer())?;
+
+    for ctx in tag_triggers {
+        let handlers = host
+            .config()
+            .map(|config| config.get_tag_handlers(&ctx.tag))
+            .unwrap_or_default();
+        for f in handlers {
+            host.call_tag_handler(f, world, ctx.clone())
+        }
+    }
+
     Ok(())
 }
 
-/// Updates hitbox sequences.
-/// Deactivates hitboxes associated with a finished frame.
-/// Activates hitboxes associated with a starting frame.
-fn hitbox_sequence_system(
-    emd: &mut Emerald,
-    world: &mut World,
-    config: &HitmeConfig,
-) -> Result<(), EmeraldError> {
-    let mut to_deactivate = Vec::new();
-    let mut to_activate = Vec::new();
-    let mut tag_triggers = Vec::new();
+/// Progresses the active sequence of every hitbox set by the delta of its owner.
+/// Returns the events produced, paired with the owner of the set.
+pub(crate) fn progress_hitbox_sequences(
+    world: &World,
+    config: &HitmeConfig,
+    delta_for: &mut dyn FnMut(&World, Entity) -> f32,
+) -> Vec<(Entity, HitboxSequenceEvent)> {
+    let mut events = Vec::new();
+
+    for (id, hitbox_set) in world.query::<&mut HitboxSet>().iter() {
+        if hitbox_set.active_sequence.is_none() && hitbox_set.pending_events.is_empty() {
+            continue;
+        }
+
+        let delta = delta_for(world, id);
+
+        hitbox_set.set_frames_per_second(config.frames_per_second);
+        let sequence_events = hitbox_set.progress_active_sequence(delta);
+        events.extend(sequence_events.into_iter().map(|event| (id, event)));
+    }
+
+    events
+}
+
+/// Activates and deactivates the hitboxes named by sequence events, and adds the events
+/// to the event buffer, if there is one. Returns the tags triggered, for the tag handlers.
+pub(crate) fn apply_sequence_events(
+    world: &mut World,
+    events: Vec<(Entity, HitboxSequenceEvent)>,
+    buffer: Option<&mut EventBuffer>,
+) -> Result<Vec<OnTagTriggerContext>, EmeraldError> {
+    let mut to_deactivate = Vec::new();
+    let mut to_activate = Vec::new();
+    let mut tag_triggers = Vec::new();
+    let mut collider_selections = Vec::new();
+
+    buffer.map(|buffer| buffer.extend(events.iter().cloned()));
+
+    for (id, event) in events {
+        match event {
+            HitboxSequenceEvent::HitboxDeactivated { hitbox } => {
+                to_deactivate.push(hitbox);
+            }
+            HitboxSequenceEvent::HitboxActivated { hitbox } => {
+                to_activate.push(hitbox);
+            }
+            // The set already cleared the sequence, or started the next queued one
+            HitboxSequenceEvent::Finished => {}
+            HitboxSequenceEvent::TagTriggered {
+                name,
+                data,
+                sequence_name,
+                frame,
+                elapsed,
+            } => {
+                tag_triggers.push(OnTagTriggerContext {
+                    tag: name,
+                    hitbox_set_owner: id,
+                    data,
+                    sequence_name,
+                    frame,
+                    elapsed,
+                });
+            }
+            HitboxSequenceEvent::CollidersSelected { hitbox, colliders } => {
+                collider_selections.push((hitbox, colliders));
+            }
+            HitboxSequenceEvent::SequenceStarted { .. }
+            | HitboxSequenceEvent::FrameEntered { .. } => {}
+        }
+    }
+
+    for id in to_activate {
+        world.get::<&mut Hitbox>(id).ok().map(|mut hitbox| {
+            hitbox.activate();
+        });
+        index_hitbox(world, id);
+    }
+
+    for id in to_deactivate {
+        world.get::<&mut Hitbox>(id).ok().map(|mut hitbox| {
+            hitbox.deactivate();
+            hitbox.refresh();
+        });
+
+        // Frames that select colliders only do so while they're active
+        let inactive_colliders = world
+            .get::<&Hitbox>(id)
+            .ok()
+            .map(|hitbox| {
+                hitbox
+                    .inactive_colliders
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        for name in inactive_colliders {
+            set_hitbox_collider_active(world, id, &name, true)?;
+        }
+    }
+
+    for (id, colliders) in collider_selections {
+        select_hitbox_colliders(world, id, &colliders)?;
+    }
+
+    Ok(tag_triggers)
+}
+
+#[cfg(test)]
+mod sequence_tests {
+
+    use std::collections::HashMap;
+
+    use emerald::{toml::Value, Emerald, Entity, Group, Transform, Translation, World};
+
+    use crate::{
+        collision_tests::{spawn_hitbox_set, spawn_hurtbox_set},
+        emd_hitme_system, get_hit_collisions_with,
+        hitboxes::{
+            advance_damaged_entities, frames_to_secs, hitbox_sequence_system, nearest_target,
+            record_hitbox_translations, set_hitbox_collider_active, ActiveSequenceData,
+            HitboxSequenceEvent, HitboxSequenceFrame, HitboxSet,
+        },
+        host::tests::HeadlessHost,
+        hurtboxes::HurtboxSet,
+        overlap::OverlapSource,
+        resolve_hits, run_hit_pass, HitHandler, HitmeConfig, HitmeConfigBuilder, OnHitContext,
+        OnHitFilterContext, OnTagTriggerContext, OnTagTriggerFn,
+    };
+
+    const TEST_SEQUENCE_NAME: &str = "test";
+    const HITBOX_ENTITY_NAME: &str = "hitbox";
+
+    fn get_test_package() -> (
+        ActiveSequenceData,
+        HashMap<String, Vec<HitboxSequenceFrame>>,
+        HashMap<String, Entity>,
+    ) {
+        let mut world = World::new();
+        let test_sequence_name = String::from(TEST_SEQUENCE_NAME);
+        let hitbox_name = String::from(HITBOX_ENTITY_NAME);
+        let mut sequences = HashMap::new();
+        let mut hitboxes = HashMap::new();
+        let sequence_frames = vec![HitboxSequenceFrame {
+            duration: 2.0,
+            frames: None,
+            name: Some(hitbox_name.clone()),
+            names: None,
+            delay: 0.0,
+            delay_frames: None,
+            tags: Vec::new(),
+            active: false,
+            cancel_flags: None,
+            colliders: None,
+            cancellable: false,
+            branches: Vec::new(),
+        }];
+
+        let hitbox_entity = world.spawn((Transform::default(),));
+        hitboxes.insert(hitbox_name.clone(), hitbox_entity.clone());
+        sequences.insert(test_sequence_name.clone(), sequence_frames);
+
+        let active_sequence = ActiveSequenceData::new(test_sequence_name.clone());
+
+        (active_sequence, sequences, hitboxes)
+    }
+
+    #[test]
+    fn first_frame_activates_hitboxes() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 0.016);
+
+        assert_eq!(events.len(), 1);
+        let hitbox_entity = hitboxes.get(HITBOX_ENTITY_NAME).unwrap().clone();
+        assert_eq!(
+            hitbox_entity,
+            HitboxSequenceEvent::get_activated_hitboxes(&events).remove(0)
+        );
+    }
+
+    #[test]
+    fn frame_accessors_reflect_progress_and_live_edits() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        assert!(!sequences[TEST_SEQUENCE_NAME][0].is_active());
+
+        active_sequence.progress(&mut sequences, &hitboxes, 0.016);
+        let frame = &mut sequences.get_mut(TEST_SEQUENCE_NAME).unwrap()[0];
+        assert!(frame.is_active());
+        assert_eq!(
+            (frame.duration_secs(60.0), frame.delay_secs(60.0)),
+            (2.0, 0.0)
+        );
+
+        frame.set_duration(-1.0);
+        assert_eq!(frame.duration_secs(60.0), 0.0);
+        frame.set_duration(0.5);
+        assert_eq!(frame.duration_secs(60.0), 0.5);
+    }
+
+    #[test]
+    fn frame_rate_carries_over_to_new_sequences() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                attack = [{ frames = 4 }, { frames = 4 }]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        hitbox_set.set_frames_per_second(8.0);
+        hitbox_set.set_frames_per_second(0.0);
+        hitbox_set.start_sequence("attack").unwrap();
+
+        hitbox_set.progress_active_sequence(0.75);
+        assert_eq!(hitbox_set.active_sequence.as_ref().unwrap().frame, 1);
+        assert_eq!(frames_to_secs(6, 0.0), 0.1);
+    }
+
+    #[test]
+    fn first_frame_respects_delay() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        sequences.get_mut(TEST_SEQUENCE_NAME).unwrap()[0].delay = 0.2;
+
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 0.016);
+
+        assert_eq!(events.len(), 0);
+        assert_eq!(
+            0,
+            HitboxSequenceEvent::get_activated_hitboxes(&events).len()
+        );
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 0.2);
+
+        assert_eq!(events.len(), 1);
+        let hitbox_entity = hitboxes.get(HITBOX_ENTITY_NAME).unwrap().clone();
+        assert_eq!(
+            hitbox_entity,
+            HitboxSequenceEvent::get_activated_hitboxes(&events).remove(0)
+        );
+    }
+
+    #[test]
+    fn progressing_past_limit_deactivates_hitboxes() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 40.0);
+
+        let hitbox_entity = hitboxes.get(HITBOX_ENTITY_NAME).unwrap().clone();
+        assert_eq!(
+            hitbox_entity,
+            HitboxSequenceEvent::get_deactivated_hitboxes(&events).remove(0)
+        );
+    }
+
+    #[test]
+    fn total_elapsed_time_persists_across_frames() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        active_sequence.progress(&mut sequences, &hitboxes, 1.5);
+        active_sequence.progress(&mut sequences, &hitboxes, 1.0);
+
+        assert_eq!(active_sequence.elapsed_time, 0.0);
+        assert_eq!(active_sequence.total_elapsed_time, 2.5);
+    }
+
+    #[test]
+    fn frame_cancel_flags_override_sequence_cancel_flags() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [sequences.attack]
+                cancel_flags = ["jump"]
+                frames = [
+                    { duration = 1.0, name = "hitbox" },
+                    { duration = 1.0, cancel_flags = ["jump", "block"] },
+                ]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        assert!(hitbox_set.current_cancel_flags().is_empty());
+
+        hitbox_set.start_sequence("attack").unwrap();
+        assert_eq!(hitbox_set.current_cancel_flags().len(), 1);
+
+        hitbox_set.progress_active_sequence(1.5);
+        let flags = hitbox_set.current_cancel_flags();
+        assert!(flags.contains("jump") && flags.contains("block"));
+    }
+
+    #[test]
+    fn queued_sequences_start_when_the_previous_one_finishes() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                jab = [{ duration = 1.0 }]
+                kick = [{ duration = 1.0 }]
+                sweep = [{ duration = 1.0 }]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        hitbox_set.queue_sequence("jab").unwrap();
+        hitbox_set.queue_sequence("kick").unwrap();
+        hitbox_set.queue_sequence("sweep").unwrap();
+        assert_eq!(hitbox_set.active_sequence.as_ref().unwrap().name, "jab");
+        assert_eq!(hitbox_set.queued_sequence_count(), 2);
+
+        let events = hitbox_set.progress_active_sequence(1.0);
+        assert!(events.iter().any(|e| matches!(
+            e,
+            HitboxSequenceEvent::SequenceStarted { name } if name == "kick"
+        )));
+        assert_eq!(hitbox_set.active_sequence.as_ref().unwrap().name, "kick");
+        assert_eq!(hitbox_set.queued_sequence_count(), 1);
+
+        hitbox_set.clear_sequence_queue();
+        hitbox_set.progress_active_sequence(1.0);
+        assert!(hitbox_set.active_sequence.is_none());
+    }
+
+    #[test]
+    fn queued_start_events_reach_the_next_progress() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                attack = [{ duration = 1.0 }]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        hitbox_set.queue_sequence("attack").unwrap();
+
+        let events = hitbox_set.progress_active_sequence(0.0);
+        assert!(events.iter().any(|e| matches!(
+            e,
+            HitboxSequenceEvent::SequenceStarted { name } if name == "attack"
+        )));
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, HitboxSequenceEvent::FrameEntered { frame: 0 })));
+        assert!(!hitbox_set
+            .progress_active_sequence(0.0)
+            .iter()
+            .any(|e| matches!(e, HitboxSequenceEvent::SequenceStarted { .. })));
+    }
+
+    #[test]
+    fn cancels_are_only_accepted_on_cancellable_frames() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                attack = [{ duration = 1.0 }, { duration = 1.0, cancellable = true }]
+                special = [{ duration = 1.0 }]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        assert!(hitbox_set.try_cancel_into("attack"));
+
+        assert!(!hitbox_set.try_cancel_into("special"));
+        assert_eq!(hitbox_set.active_sequence.as_ref().unwrap().name, "attack");
+
+        hitbox_set.progress_active_sequence(1.0);
+        assert!(hitbox_set.try_cancel_into("special"));
+        assert_eq!(hitbox_set.active_sequence.as_ref().unwrap().name, "special");
+    }
+
+    #[test]
+    fn cancelling_an_active_frame_deactivates_its_hitboxes() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.fist]
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [hitboxes.foot]
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [sequences]
+                jab = [{ duration = 1.0, name = "fist", cancellable = true }]
+                kick = [{ duration = 1.0, name = "foot" }]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        let fist = hitbox_set.hitboxes["fist"];
+        hitbox_set.start_sequence("jab").unwrap();
+        let events = hitbox_set.progress_active_sequence(0.25);
+        assert!(HitboxSequenceEvent::get_activated_hitboxes(&events).contains(&fist));
+
+        assert!(hitbox_set.try_cancel_into("kick"));
+        let events = hitbox_set.progress_active_sequence(0.0);
+        assert!(events.iter().any(|e| matches!(
+            e,
+            HitboxSequenceEvent::HitboxDeactivated { hitbox } if *hitbox == fist
+        )));
+    }
+
+    #[test]
+    fn branch_flags_pick_the_next_frame() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                charge = [
+                    { duration = 1.0, branches = [{ condition_tag = "charged", goto = 2 }] },
+                    { duration = 1.0 },
+                    { duration = 1.0 },
+                ]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+
+        hitbox_set.start_sequence("charge").unwrap();
+        hitbox_set.progress_active_sequence(1.0);
+        assert_eq!(hitbox_set.active_sequence.as_ref().unwrap().frame, 1);
+
+        hitbox_set.start_sequence("charge").unwrap();
+        hitbox_set.set_branch_flag("charged", true);
+        hitbox_set.progress_active_sequence(1.0);
+        assert_eq!(hitbox_set.active_sequence.as_ref().unwrap().frame, 2);
+
+        hitbox_set.start_sequence("charge").unwrap();
+        assert!(hitbox_set
+            .active_sequence
+            .as_ref()
+            .unwrap()
+            .branch_flags
+            .is_empty());
+    }
+
+    #[test]
+    fn rewinding_past_a_frame_deactivates_its_hitbox() {
+        let mut world = World::new();
+        let (owner, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                parry = [{ duration = 1.0 }, { duration = 1.0, name = "hitbox" }]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        hitbox_set.start_sequence("parry").unwrap();
+        hitbox_set.progress_active_sequence(1.0);
+        let events = hitbox_set.progress_active_sequence(0.5);
+        assert_eq!(
+            HitboxSequenceEvent::get_activated_hitboxes(&events),
+            vec![hitbox]
+        );
+
+        let events = hitbox_set.rewind_active_sequence(1.0);
+        assert_eq!(
+            HitboxSequenceEvent::get_deactivated_hitboxes(&events),
+            vec![hitbox]
+        );
+        assert_eq!(HitboxSequenceEvent::get_entered_frames(&events), vec![0]);
+        let active_sequence = hitbox_set.active_sequence.as_ref().unwrap();
+        assert_eq!(
+            (active_sequence.frame, active_sequence.elapsed_time),
+            (0, 0.5)
+        );
+
+        hitbox_set.rewind_active_sequence(5.0);
+        let active_sequence = hitbox_set.active_sequence.as_ref().unwrap();
+        assert_eq!(
+            (active_sequence.frame, active_sequence.elapsed_time),
+            (0, 0.0)
+        );
+    }
+
+    #[test]
+    fn frame_counts_convert_to_seconds() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                jab = [{ frames = 6 }, { frames = 6, duration = 1.0 }]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        let frame = &hitbox_set.sequences["jab"][0];
+        assert_eq!(frame.duration_secs(60.0), 0.1);
+        assert_eq!(hitbox_set.sequences["jab"][1].duration_secs(60.0), 1.0);
+
+        hitbox_set.start_sequence("jab").unwrap();
+        hitbox_set.progress_active_sequence(0.05);
+        assert_eq!(hitbox_set.active_sequence.as_ref().unwrap().frame, 0);
+        hitbox_set.progress_active_sequence(0.05);
+        assert_eq!(hitbox_set.active_sequence.as_ref().unwrap().frame, 1);
+    }
+
+    #[test]
+    fn pending_tags_excludes_triggered_tags() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                attack = [
+                    { duration = 1.0, tags = [{ name = "early" }, { name = "late", delay = 0.5 }] },
+                ]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        hitbox_set.start_sequence("attack").unwrap();
+        assert_eq!(hitbox_set.pending_tags(), vec!["early", "late"]);
+
+        hitbox_set.progress_active_sequence(0.1);
+        assert_eq!(hitbox_set.pending_tags(), vec!["late"]);
+    }
+
+    #[test]
+    fn sequences_and_frames_can_be_enumerated() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                jab = [{ duration = 0.5, tags = [{ name = "swing" }] }, { duration = 0.25 }]
+                kick = [{ duration = 1.0 }]
+            "#,
+        );
+        let hitbox_set = world.get::<&HitboxSet>(owner).unwrap();
+
+        let mut sequences = hitbox_set
+            .iter_sequences()
+            .map(|(name, frames)| {
+                let durations = frames.iter().map(|f| f.duration).collect::<Vec<f32>>();
+                (name.clone(), durations)
+            })
+            .collect::<Vec<(String, Vec<f32>)>>();
+        sequences.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            sequences,
+            vec![
+                (String::from("jab"), vec![0.5, 0.25]),
+                (String::from("kick"), vec![1.0]),
+            ]
+        );
+
+        let (_, jab) = hitbox_set
+            .iter_sequences()
+            .find(|(name, _)| name.as_str() == "jab")
+            .unwrap();
+        assert_eq!(jab[0].tags()[0].name, "swing");
+        assert!(jab[1].tags().is_empty());
+    }
+
+    fn swing_sound(_: &mut Emerald, _: &mut World, _: OnTagTriggerContext) {}
+    fn land_sound(_: &mut Emerald, _: &mut World, _: OnTagTriggerContext) {}
+
+    #[test]
+    fn sequence_system_calls_the_tag_handlers() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                jab = [{ duration = 0.5, tags = [{ name = "swing" }] }]
+            "#,
+        );
+        world
+            .get::<&mut HitboxSet>(owner)
+            .unwrap()
+            .start_sequence("jab")
+            .unwrap();
+        let config = HitmeConfigBuilder::new()
+            .add_tag_handler_by_name("swing", swing_sound)
+            .add_tag_handler_by_name("land", land_sound)
+            .build();
+        let mut host = HeadlessHost::new(config, 0.1);
+
+        hitbox_sequence_system(&mut host, &mut world, &HitmeConfig::default()).unwrap();
+        assert_eq!(
+            host.tag_handler_calls,
+            vec![(
+                swing_sound as OnTagTriggerFn as usize,
+                String::from("swing")
+            )]
+        );
+    }
+
+    #[test]
+    fn tag_events_carry_sequence_and_frame() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                attack = [
+                    { duration = 1.0 },
+                    { duration = 1.0, tags = [{ name = "swing", delay = 0.25 }] },
+                ]
+            "#,
+        );
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        hitbox_set.start_sequence("attack").unwrap();
+        hitbox_set.progress_active_sequence(1.0);
+
+        let events = hitbox_set.progress_active_sequence(0.5);
+        let tag = events.into_iter().find_map(|e| match e {
+            HitboxSequenceEvent::TagTriggered {
+                name,
+                sequence_name,
+                frame,
+                elapsed,
+                ..
+            } => Some((name, sequence_name, frame, elapsed)),
+            _ => None,
+        });
+        assert_eq!(
+            tag,
+            Some((String::from("swing"), String::from("attack"), 1, 1.5))
+        );
+    }
+
+    #[derive(Default)]
+    struct CountingHitHandler {
+        hits: u32,
+        hitboxes: Vec<Entity>,
+        burst_indices: Vec<Option<u32>>,
+        scalings: Vec<f32>,
+    }
+    impl HitHandler for CountingHitHandler {
+        fn filter(&mut self, _: &mut World, _: OnHitFilterContext) -> bool {
+            true
+        }
+
+        fn on_hit(&mut self, _: &mut World, ctx: OnHitContext) {
+            self.hits += 1;
+            self.hitboxes.push(ctx.hitbox);
+            self.burst_indices.push(ctx.burst_index);
+            self.scalings.push(ctx.scaling);
+        }
+    }
+
+    #[test]
+    fn attack_sequence_can_only_deal_one_instance_of_damage_with_multiple_hitboxes() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.blade]
+                active = true
+                hit_group = "slash"
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [hitboxes.hilt]
+                active = true
+                hit_group = "slash"
+                colliders = [{ width = 4.0, height = 4.0 }]
+            "#,
+        );
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        let hitboxes = world
+            .get::<&HitboxSet>(owner)
+            .unwrap()
+            .hitboxes
+            .values()
+            .cloned()
+            .collect::<Vec<Entity>>();
+        let overlaps = hitboxes
+            .iter()
+            .map(|id| (id.clone(), vec![hurtbox]))
+            .collect::<HashMap<Entity, Vec<Entity>>>();
+
+        let mut handler = CountingHitHandler::default();
+        let config = HitmeConfig::default();
+        resolve_hits(&mut world, &config, &overlaps, &hitboxes, &mut handler);
+
+        assert_eq!(handler.hits, 1);
+    }
+
+    #[test]
+    fn hitbox_overlapping_two_hurtboxes_of_one_entity_hits_once() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.blade]
+                active = true
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let defender = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                [[hurtboxes]]
+                active = true
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [[hurtboxes]]
+                active = true
+                colliders = [{ width = 4.0, height = 4.0 }]
+            "#,
+        )
+        .unwrap();
+        let hurtbox_set =
+            HurtboxSet::from_toml(&mut world, &value, defender, Group::GROUP_1, Group::GROUP_2)
+                .unwrap();
+        let overlaps = HashMap::from([(hitbox, hurtbox_set.hurtboxes.clone())]);
+        world.insert_one(defender, hurtbox_set).unwrap();
+
+        let mut handler = CountingHitHandler::default();
+        let config = HitmeConfig::default();
+        resolve_hits(&mut world, &config, &overlaps, &vec![hitbox], &mut handler);
+
+        assert_eq!(handler.hits, 1);
+    }
+
+    #[test]
+    fn burst_overrides_hit_mode_and_cooldown() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.aura]
+                active = true
+                hit_mode = "on_enter"
+                cooldown_per_entity = 10.0
+                burst = { count = 3, interval = 0.1 }
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+
+        let mut handler = CountingHitHandler::default();
+        let config = HitmeConfig::default();
+        for _ in 0..20 {
+            resolve_hits(&mut world, &config, &overlaps, &vec![hitbox], &mut handler);
+            advance_damaged_entities(&mut world, 0.05);
+        }
+
+        assert_eq!(handler.burst_indices, vec![Some(0), Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn combo_scaling_reaches_the_hits_only_while_combos_are_tracked() {
+        let hit_twice = |combo_timeout: Option<f32>| {
+            let mut world = World::new();
+            let (_, hitbox) = spawn_hitbox_set(
+                &mut world,
+                r#"
+                    [hitboxes.jab]
+                    active = true
+                    colliders = [{ width = 10.0, height = 10.0 }]
+                "#,
+            );
+            let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+            let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+
+            let mut handler = CountingHitHandler::default();
+            let config = HitmeConfig {
+                combo_timeout,
+                combo_scaling: 0.75,
+                ..HitmeConfig::default()
+            };
+            for _ in 0..2 {
+                resolve_hits(&mut world, &config, &overlaps, &vec![hitbox], &mut handler);
+                advance_damaged_entities(&mut world, 0.1);
+            }
+
+            handler.scalings
+        };
+
+        assert_eq!(hit_twice(Some(1.0)), vec![1.0, 0.75]);
+        assert_eq!(hit_twice(None), vec![1.0, 1.0]);
+    }
+
+    const BULLET_TOML: &str = r#"
+        [hitboxes.bullet]
+        active = true
+        continuous = true
+        colliders = [{ name = "core", width = 4.0, height = 4.0 }]
+    "#;
+
+    /// Fires the bullet from the origin to x = 600 in a single tick
+    fn fire_bullet(world: &mut World, hitbox: Entity) {
+        record_hitbox_translations(world);
+        world.get::<&mut Transform>(hitbox).unwrap().translation = Translation::new(600.0, 0.0);
+    }
+
+    #[test]
+    fn continuous_hitbox_hits_through_the_hit_pass_however_far_it_moves() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, BULLET_TOML);
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world.get::<&mut Transform>(hurtbox).unwrap().translation = Translation::new(500.0, 0.0);
+
+        let mut handler = CountingHitHandler::default();
+        let config = HitmeConfig::default();
+        fire_bullet(&mut world, hitbox);
+        run_hit_pass(&mut world, &config, &mut handler);
+
+        assert_eq!(handler.hits, 1);
+    }
+
+    #[test]
+    fn sweeps_skip_switched_off_colliders_and_excluded_groups() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, BULLET_TOML);
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world.get::<&mut Transform>(hurtbox).unwrap().translation = Translation::new(300.0, 0.0);
+        let ghost = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                [[hurtboxes]]
+                active = true
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        )
+        .unwrap();
+        let ghost_set =
+            HurtboxSet::from_toml(&mut world, &value, ghost, Group::GROUP_3, Group::GROUP_4)
+                .unwrap();
+        let ghost_hurtbox = ghost_set.hurtboxes[0];
+        world.insert_one(ghost, ghost_set).unwrap();
+        world
+            .get::<&mut Transform>(ghost_hurtbox)
+            .unwrap()
+            .translation = Translation::new(200.0, 0.0);
+
+        let mut handler = CountingHitHandler::default();
+        let config = HitmeConfig::default();
+        set_hitbox_collider_active(&mut world, hitbox, "core", false).unwrap();
+        fire_bullet(&mut world, hitbox);
+        run_hit_pass(&mut world, &config, &mut handler);
+        assert_eq!(handler.hits, 0);
+
+        world.get::<&mut Transform>(hitbox).unwrap().translation = Translation::new(0.0, 0.0);
+        set_hitbox_collider_active(&mut world, hitbox, "core", true).unwrap();
+        fire_bullet(&mut world, hitbox);
+        run_hit_pass(&mut world, &config, &mut handler);
+        assert_eq!(handler.hits, 1);
+    }
+
+    #[test]
+    fn config_cooldown_applies_to_hitboxes_without_their_own() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.jab]
+                active = true
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [hitboxes.kick]
+                active = true
+                cooldown_per_entity = 0.1
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        let (jab, kick) = {
+            let hitbox_set = world.get::<&HitboxSet>(owner).unwrap();
+            (hitbox_set.hitboxes["jab"], hitbox_set.hitboxes["kick"])
+        };
+        let overlaps = HashMap::from([(jab, vec![hurtbox]), (kick, vec![hurtbox])]);
+
+        let mut handler = CountingHitHandler::default();
+        let config = HitmeConfig {
+            default_cooldown_per_entity: Some(0.5),
+            ..Default::default()
+        };
+        for _ in 0..4 {
+            resolve_hits(
+                &mut world,
+                &config,
+                &overlaps,
+                &vec![jab, kick],
+                &mut handler,
+            );
+            advance_damaged_entities(&mut world, 0.15);
+        }
+
+        let hits = |hitbox: Entity| handler.hitboxes.iter().filter(|h| **h == hitbox).count();
+        assert_eq!(hits(jab), 1);
+        assert_eq!(hits(kick), 4);
+    }
+
+    #[test]
+    fn config_cooldown_applies_outside_of_hit_resolution() {
+        let mut world = World::new();
+        let (_, jab) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.jab]
+                active = true
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        let overlaps = HashMap::from([(jab, vec![hurtbox])]);
+
+        let mut handler = CountingHitHandler::default();
+        let config = HitmeConfig {
+            default_cooldown_per_entity: Some(0.5),
+            ..Default::default()
+        };
+        resolve_hits(&mut world, &config, &overlaps, &vec![jab], &mut handler);
+        assert_eq!(handler.hits, 1);
+
+        advance_damaged_entities(&mut world, 0.25);
+        assert!(get_hit_collisions_with(&world, &overlaps).is_empty());
+        assert_eq!(nearest_target(&world, jab, 100.0), None);
+
+        advance_damaged_entities(&mut world, 0.25);
+        assert_eq!(get_hit_collisions_with(&world, &overlaps).len(), 1);
+        assert_eq!(nearest_target(&world, jab, 100.0), Some(defender));
+    }
+
+    #[test]
+    fn only_the_highest_priority_hitbox_connects() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.graze]
+                active = true
+                priority = 1
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [hitboxes.clean]
+                active = true
+                priority = 5
+                colliders = [{ width = 4.0, height = 4.0 }]
+            "#,
+        );
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        let (graze, clean) = {
+            let hitbox_set = world.get::<&HitboxSet>(owner).unwrap();
+            (hitbox_set.hitboxes["graze"], hitbox_set.hitboxes["clean"])
+        };
+        let overlaps = HashMap::from([(graze, vec![hurtbox]), (clean, vec![hurtbox])]);
+
+        let mut handler = CountingHitHandler::default();
+        let config = HitmeConfig::default();
+        resolve_hits(
+            &mut world,
+            &config,
+            &overlaps,
+            &vec![graze, clean],
+            &mut handler,
+        );
+
+        assert_eq!(handler.hitboxes, vec![clean]);
+    }
+
+    /// Overlaps with scripted contact times, as a sub-stepping source would report them.
+    struct TimedOverlaps {
+        overlaps: HashMap<Entity, Vec<Entity>>,
+        contact_times: HashMap<Entity, f32>,
+    }
+    impl OverlapSource for TimedOverlaps {
+        fn colliding(&self, id: Entity) -> Vec<Entity> {
+            self.overlaps.get(&id).cloned().unwrap_or_default()
+        }
+
+        fn contact_time(&self, id: Entity, _other: Entity) -> f32 {
+            self.contact_times[&id]
+        }
+    }
+
+    #[test]
+    fn hits_are_dispatched_in_contact_order() {
+        const HITBOX_TOML: &str = r#"
+            [hitboxes.punch]
+            active = true
+            colliders = [{ width = 10.0, height = 10.0 }]
+        "#;
+        let mut world = World::new();
+        let (_, first) = spawn_hitbox_set(&mut world, HITBOX_TOML);
+        let (_, second) = spawn_hitbox_set(&mut world, HITBOX_TOML);
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        // Later spawned entities sort after earlier ones, so contact times must win the order
+        let overlaps = TimedOverlaps {
+            overlaps: HashMap::from([(first, vec![hurtbox]), (second, vec![hurtbox])]),
+            contact_times: HashMap::from([(first, 0.75), (second, 0.25)]),
+        };
+
+        let mut handler = CountingHitHandler::default();
+        let config = HitmeConfig::default();
+        resolve_hits(
+            &mut world,
+            &config,
+            &overlaps,
+            &vec![first, second],
+            &mut handler,
+        );
+
+        assert_eq!(handler.hitboxes, vec![second, first]);
+    }
+
+    #[test]
+    fn single_target_hitbox_only_hits_one_of_two_hurtboxes() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.bullet]
+                active = true
+                max_targets = 1
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let (_, first) = spawn_hurtbox_set(&mut world, None);
+        let (_, second) = spawn_hurtbox_set(&mut world, None);
+        let overlaps = HashMap::from([(hitbox, vec![first, second])]);
+
+        let mut handler = CountingHitHandler::default();
+        let config = HitmeConfig::default();
+        resolve_hits(&mut world, &config, &overlaps, &vec![hitbox], &mut handler);
+
+        assert_eq!(handler.hits, 1);
+    }
+
+    #[test]
+    fn sequence_speed_scales_elapsed_time() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        active_sequence.speed = 0.5;
+        active_sequence.progress(&mut sequences, &hitboxes, 1.0);
+
+        assert_eq!(active_sequence.elapsed_time, 0.5);
+        assert_eq!(active_sequence.total_elapsed_time, 0.5);
+    }
+
+    #[test]
+    fn zero_speed_freezes_sequence() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        active_sequence.speed = 0.0;
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 40.0);
+
+        assert!(events.is_empty());
+        assert_eq!(active_sequence.elapsed_time, 0.0);
+    }
+
+    #[test]
+    fn paused_sequence_keeps_hitboxes_active_without_refiring() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 1.0);
+        assert_eq!(
+            HitboxSequenceEvent::get_activated_hitboxes(&events).len(),
+            1
+        );
+
+        active_sequence.paused = true;
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 40.0);
+        assert!(events.is_empty());
+        assert_eq!(active_sequence.elapsed_time, 1.0);
+        assert!(active_sequence.is_current_frame_active(&sequences));
+
+        active_sequence.paused = false;
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 0.5);
+        assert!(HitboxSequenceEvent::get_activated_hitboxes(&events).is_empty());
+        assert_eq!(active_sequence.elapsed_time, 1.5);
+    }
+
+    #[test]
+    fn advancing_frames_emits_frame_entered() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        sequences
+            .get_mut(TEST_SEQUENCE_NAME)
+            .unwrap()
+            .push(HitboxSequenceFrame {
+                duration: 2.0,
+                frames: None,
+                name: None,
+                names: None,
+                delay: 0.0,
+                delay_frames: None,
+                tags: Vec::new(),
+                active: false,
+                cancel_flags: None,
+                colliders: None,
+                cancellable: false,
+                branches: Vec::new(),
+            });
+
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 1.0);
+        assert!(HitboxSequenceEvent::get_entered_frames(&events).is_empty());
+
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 1.0);
+        assert_eq!(HitboxSequenceEvent::get_entered_frames(&events), vec![1]);
+
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 2.0);
+        assert!(HitboxSequenceEvent::get_entered_frames(&events).is_empty());
+        assert_eq!(count_finished(&events), 1);
+    }
+
+    #[test]
+    fn future_hitboxes_include_every_remaining_frame() {
+        let mut world = World::new();
+        let mut hitboxes = HashMap::new();
+        let mut frames = Vec::new();
+        for name in ["first", "second", "third"] {
+            hitboxes.insert(name.to_string(), world.spawn((Transform::default(),)));
+            frames.push(HitboxSequenceFrame {
+                duration: 1.0,
+                frames: None,
+                name: Some(name.to_string()),
+                names: None,
+                delay: 0.0,
+                delay_frames: None,
+                tags: Vec::new(),
+                active: false,
+                cancel_flags: None,
+                colliders: None,
+                cancellable: false,
+                branches: Vec::new(),
+            });
+        }
+        let mut sequences = HashMap::new();
+        sequences.insert(TEST_SEQUENCE_NAME.to_string(), frames);
+
+        let mut active_sequence = ActiveSequenceData::new(TEST_SEQUENCE_NAME.to_string());
+        assert_eq!(
+            active_sequence.get_future_hitboxes_to_be_activated(&sequences, &hitboxes),
+            vec![hitboxes["second"], hitboxes["third"]]
+        );
+
+        active_sequence.frame = 2;
+        assert!(active_sequence
+            .get_future_hitboxes_to_be_activated(&sequences, &hitboxes)
+            .is_empty());
+    }
+
+    #[test]
+    fn empty_sequence_is_immediately_finished() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [sequences]
+                empty = []
+            "#,
+        );
+
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        hitbox_set.start_sequence("empty").unwrap();
+        assert_eq!(hitbox_set.is_current_sequence_finished(), Some(true));
+
+        let events = hitbox_set.progress_active_sequence(0.016);
+        assert_eq!(count_finished(&events), 1);
+    }
+
+    fn count_finished(events: &Vec<HitboxSequenceEvent>) -> usize {
+        events
+            .iter()
+            .filter(|e| matches!(e, HitboxSequenceEvent::Finished))
+            .count()
+    }
+
+    #[test]
+    fn looping_sequence_repeats_loop_count_times_before_finishing() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        active_sequence.looping = true;
+        active_sequence.loops_remaining = Some(2);
 
-    for (id, hitbox_set) in world.query::<&mut HitboxSet>().iter() {
-        if hitbox_set.active_sequence.is_none() {
-            continue;
+        for _ in 0..2 {
+            let events = active_sequence.progress(&mut sequences, &hitboxes, 2.0);
+            assert_eq!(count_finished(&events), 0);
+            assert_eq!(active_sequence.frame, 0);
+            assert!(!active_sequence.is_finished(&sequences));
         }
 
-        let delta = config.get_delta_for_entity(emd, world, id);
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 2.0);
+        assert_eq!(count_finished(&events), 1);
+        assert_eq!(active_sequence.loops_remaining, Some(0));
+    }
 
-        let sequence_events = hitbox_set.progress_active_sequence(delta);
-        for event in sequence_events {
-            match event {
-                HitboxSequenceEvent::HitboxDeactivated { hitbox } => {
-                    to_deactivate.push(hitbox);
-                }
-                HitboxSequenceEvent::HitboxActivated { hitbox } => {
-                    to_activate.push(hitbox);
-                }
-                HitboxSequenceEvent::Finished => {
-                    hitbox_set.active_sequence = None;
-                }
-                HitboxSequenceEvent::TagTriggered { name, data } => {
-                    tag_triggers.push((name, id, data));
-                }
-            }
+    #[test]
+    fn infinitely_looping_sequence_never_finishes() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        active_sequence.looping = true;
+
+        for _ in 0..100 {
+            let events = active_sequence.progress(&mut sequences, &hitboxes, 2.0);
+            assert_eq!(count_finished(&events), 0);
+            assert!(!active_sequence.is_finished(&sequences));
         }
     }
 
-    for (tag, hitbox_set_owner, data) in tag_triggers {
-        let mut handlers = config.tag_handlers.clone();
+    #[test]
+    fn loop_count_is_read_from_sequence_settings() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+                colliders = [{ width = 10.0, height = 10.0 }]
 
-        config.tag_handlers_by_name.get(&tag).map(|f| {
-            handlers.push(*f);
-        });
+                [sequences.spin]
+                loop_count = 3
+                frames = [{ name = "hitbox", duration = 1.0 }]
+            "#,
+        );
 
-        for f in handlers {
-            f(
-                emd,
-                world,
-                OnTagTriggerContext {
-                    tag: tag.clone(),
-                    hitbox_set_owner,
-                    data: data.clone(),
-                },
-            )
-        }
-    }
+        let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+        hitbox_set.start_sequence("spin").unwrap();
+        let active_sequence = hitbox_set.active_sequence.as_ref().unwrap();
 
-    for id in to_activate {
-        world.get::<&mut Hitbox>(id).ok().map(|mut hitbox| {
-            hitbox.activate();
-        });
+        assert!(active_sequence.looping);
+        assert_eq!(active_sequence.loops_remaining, Some(3));
     }
 
-    for id in to_deactivate {
-        world.get::<&mut Hitbox>(id).ok().map(|mut hitbox| {
-            hitbox.deactivate();
-            hitbox.refresh();
-        });
+    #[test]
+    fn frames_referencing_missing_hitboxes_produce_warnings() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [sequences]
+                jab = [{ name = "hitbox", duration = 1.0 }, { name = "hitbx", duration = 1.0 }]
+                kick = [{ duration = "long" }]
+            "#,
+        );
+
+        let hitbox_set = world.get::<&HitboxSet>(owner).unwrap();
+        let warnings = hitbox_set.get_warnings();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings
+            .iter()
+            .any(|warning| warning.contains("jab frame 1") && warning.contains("hitbx")));
+        assert!(warnings
+            .iter()
+            .any(|warning| warning.contains("kick frame 0")));
+        assert!(hitbox_set.sequences["kick"].is_empty());
     }
 
-    Ok(())
+    #[test]
+    fn progressing_past_limit_of_all_frames_finishes_sequence() {
+        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
+        let events = active_sequence.progress(&mut sequences, &hitboxes, 40.0);
+        assert!(
+            events
+                .into_iter()
+                .filter(|e| match e {
+                    HitboxSequenceEvent::Finished => true,
+                    _ => false,
+                })
+                .collect::<Vec<HitboxSequenceEvent>>()
+                .len()
+                == 1
+        );
+    }
 }
 
 #[cfg(test)]
-mod sequence_tests {
-
-    use std::collections::HashMap;
+mod hitbox_tests {
+    use std::collections::{HashMap, HashSet};
 
-    use emerald::{Entity, Transform, World};
+    use emerald::{toml::Value, Emerald, Entity, Group, Transform, Translation, World};
 
     use crate::{
-        emd_hitme_system,
-        hitboxes::{ActiveSequenceData, HitboxSequenceEvent, HitboxSequenceFrame},
+        collision_tests::{spawn_hitbox_set, spawn_hurtbox_set},
+        hit_callbacks,
+        hitboxes::{
+            despawn_expired_hitboxes, despawn_hitbox_set, get_hitbox_on_hit,
+            get_hitboxes_for_owner, get_swept_hurtbox_contacts, get_swept_hurtboxes,
+            load_hitbox_set, nearest_target, record_hitbox_translations, reload_hitbox_set,
+            restore_from_snapshot, set_hitbox_collider_active, set_hitbox_scale,
+            snapshot_hitbox_set, update_hitbox_overlaps, Hitbox, HitboxSet, HitboxSetSnapshot,
+            MIN_HITBOX_SCALE,
+        },
+        hurtboxes::ColliderShape,
+        resolve_hits,
+        tracker::{track_targets, SimpleTranslationTracker},
+        Facing, HitHandler, HitmeConfig, OnHitContext, OnHitFilterContext, OnHitFn,
     };
 
-    const TEST_SEQUENCE_NAME: &str = "test";
-    const HITBOX_ENTITY_NAME: &str = "hitbox";
+    fn load_hitbox(world: &mut World, toml: &str) -> Hitbox {
+        let parent_set = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(toml).unwrap();
+        Hitbox::from_toml(world, &value, parent_set).unwrap()
+    }
 
-    fn get_test_package() -> (
-        ActiveSequenceData,
-        HashMap<String, Vec<HitboxSequenceFrame>>,
-        HashMap<String, Entity>,
-    ) {
+    #[test]
+    fn despawning_a_hitbox_set_despawns_its_hitboxes() {
         let mut world = World::new();
-        let test_sequence_name = String::from(TEST_SEQUENCE_NAME);
-        let hitbox_name = String::from(HITBOX_ENTITY_NAME);
-        let mut sequences = HashMap::new();
-        let mut hitboxes = HashMap::new();
-        let sequence_frames = vec![HitboxSequenceFrame {
-            duration: 2.0,
-            name: Some(hitbox_name.clone()),
-            names: None,
-            delay: 0.0,
-            tags: Vec::new(),
-            active: false,
-        }];
+        let (owner, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.jab]
+                colliders = [{ width = 10.0, height = 10.0 }]
 
-        let hitbox_entity = world.spawn((Transform::default(),));
-        hitboxes.insert(hitbox_name.clone(), hitbox_entity.clone());
-        sequences.insert(test_sequence_name.clone(), sequence_frames);
+                [hitboxes.kick]
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
 
-        let active_sequence = ActiveSequenceData::new(test_sequence_name.clone());
+        assert_eq!(despawn_hitbox_set(&mut world, owner), 2);
+        assert!(!world.contains(hitbox));
+        assert!(world.contains(owner) && !world.has::<HitboxSet>(owner));
+        assert_eq!(despawn_hitbox_set(&mut world, owner), 0);
+    }
 
-        (active_sequence, sequences, hitboxes)
+    #[test]
+    fn reloading_adds_hitboxes_and_keeps_active_sequence() {
+        let mut world = World::new();
+        let (owner, jab) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.jab]
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [sequences]
+                attack = [{ duration = 1.0 }, { duration = 1.0, name = "jab" }]
+            "#,
+        );
+        {
+            let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+            hitbox_set.start_sequence("attack").unwrap();
+            hitbox_set.progress_active_sequence(1.0);
+            hitbox_set.progress_active_sequence(0.5);
+            hitbox_set.queue_sequence("attack").unwrap();
+        }
+
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                [hitboxes.jab]
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [hitboxes.kick]
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [sequences]
+                attack = [{ duration = 1.0 }, { duration = 1.0, name = "jab" }]
+            "#,
+        )
+        .unwrap();
+        reload_hitbox_set(&mut world, owner, &value, Group::GROUP_1, Group::GROUP_2).unwrap();
+
+        assert!(!world.contains(jab));
+        let hitbox_set = world.get::<&HitboxSet>(owner).unwrap();
+        assert!(hitbox_set.hitboxes.contains_key("kick"));
+        let active_sequence = hitbox_set.active_sequence.as_ref().unwrap();
+        assert_eq!(active_sequence.name, "attack");
+        assert_eq!(active_sequence.frame, 1);
+        assert_eq!(active_sequence.elapsed_time, 0.5);
+        assert_eq!(hitbox_set.queued_sequence_count(), 1);
     }
 
     #[test]
-    fn first_frame_activates_hitboxes() {
-        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
-        let events = active_sequence.progress(&mut sequences, &hitboxes, 0.016);
+    fn snapshot_round_trips_through_toml() {
+        let toml = r#"
+            [hitboxes.jab]
+            colliders = [{ width = 10.0, height = 10.0 }]
 
-        assert_eq!(events.len(), 1);
-        let hitbox_entity = hitboxes.get(HITBOX_ENTITY_NAME).unwrap().clone();
+            [sequences]
+            attack = [{ duration = 1.0, name = "jab" }]
+        "#;
+        let mut world = World::new();
+        let (owner, jab) = spawn_hitbox_set(&mut world, toml);
+        let target = world.spawn((Transform::default(),));
+        {
+            let mut hitbox_set = world.get::<&mut HitboxSet>(owner).unwrap();
+            hitbox_set.start_sequence("attack").unwrap();
+            hitbox_set.progress_active_sequence(0.5);
+            hitbox_set.queue_sequence("attack").unwrap();
+        }
+        world.get::<&mut Hitbox>(jab).unwrap().activate();
+        world
+            .get::<&mut Hitbox>(jab)
+            .unwrap()
+            .add_damaged_entity(target);
+
+        let snapshot = snapshot_hitbox_set(&world, owner).unwrap();
+        let saved = emerald::toml::to_string(&snapshot).unwrap();
+        let loaded = emerald::toml::from_str::<HitboxSetSnapshot>(&saved).unwrap();
+
+        let (new_owner, new_jab) = spawn_hitbox_set(&mut world, toml);
+        let new_target = world.spawn((Transform::default(),));
+        let entity_map = HashMap::from([(u64::from(target.to_bits()), new_target)]);
+        restore_from_snapshot(&mut world, new_owner, &loaded, &entity_map).unwrap();
+
+        let hitbox = world.get::<&Hitbox>(new_jab).unwrap();
+        assert!(hitbox.is_active());
+        assert_eq!(hitbox.damaged_entities.get(&new_target), Some(&0.0));
+        let hitbox_set = world.get::<&HitboxSet>(new_owner).unwrap();
+        let active_sequence = hitbox_set.active_sequence.as_ref().unwrap();
+        assert_eq!(active_sequence.name, "attack");
+        assert_eq!(active_sequence.elapsed_time, 0.5);
+        assert!(hitbox_set.sequences["attack"][0].active);
+        assert_eq!(hitbox_set.queued_sequence_count(), 1);
+    }
+
+    #[test]
+    fn loaded_hitbox_set_returns_its_hitboxes() {
+        let mut world = World::new();
+        let owner = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                [hitboxes.jab]
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [hitboxes.kick]
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        )
+        .unwrap();
+
+        let (set_owner, hitboxes) =
+            load_hitbox_set(&mut world, &value, owner, Group::GROUP_1, Group::GROUP_2).unwrap();
+
+        assert_eq!(set_owner, owner);
+        let hitbox_set = world.get::<&HitboxSet>(owner).unwrap();
         assert_eq!(
-            hitbox_entity,
-            HitboxSequenceEvent::get_activated_hitboxes(&events).remove(0)
+            hitboxes.into_iter().collect::<HashSet<_>>(),
+            hitbox_set
+                .hitboxes
+                .values()
+                .cloned()
+                .collect::<HashSet<_>>()
         );
+        assert_eq!(hitbox_set.hitboxes.len(), 2);
     }
 
     #[test]
-    fn first_frame_respects_delay() {
-        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
-        sequences.get_mut(TEST_SEQUENCE_NAME).unwrap()[0].delay = 0.2;
+    fn activating_one_collider_leaves_its_sibling_inactive() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [[hitboxes.sword.colliders]]
+                name = "tip"
+                width = 4.0
+                height = 4.0
 
-        let events = active_sequence.progress(&mut sequences, &hitboxes, 0.016);
+                [[hitboxes.sword.colliders]]
+                name = "hilt"
+                width = 4.0
+                height = 4.0
+            "#,
+        );
 
-        assert_eq!(events.len(), 0);
+        set_hitbox_collider_active(&mut world, hitbox, "tip", false).unwrap();
+        set_hitbox_collider_active(&mut world, hitbox, "hilt", false).unwrap();
+        set_hitbox_collider_active(&mut world, hitbox, "tip", true).unwrap();
+
+        let hitbox = world.get::<&Hitbox>(hitbox).unwrap();
+        assert!(hitbox.is_collider_active("tip"));
+        assert!(!hitbox.is_collider_active("hilt"));
+        assert!(!hitbox.is_collider_active("blade"));
+    }
+
+    #[test]
+    fn nearest_target_picks_closest_owner_in_range() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.homing]
+                active = true
+            "#,
+        );
+        let (near, _) = spawn_hurtbox_set(&mut world, None);
+        let (far, _) = spawn_hurtbox_set(&mut world, None);
+        *world.get::<&mut Transform>(near).unwrap() =
+            Transform::from_translation(Translation::new(10.0, 0.0));
+        *world.get::<&mut Transform>(far).unwrap() =
+            Transform::from_translation(Translation::new(50.0, 0.0));
+
+        assert_eq!(nearest_target(&world, hitbox, 100.0), Some(near));
+        assert_eq!(nearest_target(&world, hitbox, 5.0), None);
+    }
+
+    #[test]
+    fn burst_hitbox_hits_count_times_at_interval() {
+        let mut world = World::new();
+        let mut hitbox = load_hitbox(&mut world, "burst = { count = 2, interval = 0.1 }");
+        let target = world.spawn((Transform::default(),));
+
+        assert_eq!(hitbox.next_burst_index(&target), Some(0));
+        hitbox.add_damaged_entity(target);
+        assert!(!hitbox.can_damage_entity(&target));
+
+        hitbox.damaged_entities.insert(target, 0.1);
+        assert!(hitbox.can_damage_entity(&target));
+        assert_eq!(hitbox.next_burst_index(&target), Some(1));
+        hitbox.add_damaged_entity(target);
+
+        hitbox.damaged_entities.insert(target, 0.1);
+        assert!(!hitbox.can_damage_entity(&target));
+    }
+
+    #[test]
+    fn tick_hitbox_rehits_target_every_interval() {
+        let mut world = World::new();
+        let hitbox = load_hitbox(&mut world, "tick_interval = 0.5");
+        let target = world.spawn((Transform::default(),));
+        let hitbox = world.spawn((hitbox,));
+        let owners = HashMap::from([(hitbox, HashSet::from([target]))]);
+
+        let mut hits = 0;
+        let mut elapsed = 0.0;
+        while elapsed < 1.1 {
+            let mut hitbox = world.get::<&mut Hitbox>(hitbox).unwrap();
+            hitbox
+                .damaged_entities
+                .values_mut()
+                .for_each(|e| *e += 0.25);
+            if hitbox.can_damage_entity(&target) {
+                hitbox.add_damaged_entity(target);
+                hits += 1;
+            }
+            drop(hitbox);
+
+            update_hitbox_overlaps(&mut world, &owners);
+            elapsed += 0.25;
+        }
+
+        assert_eq!(hits, 2);
+    }
+
+    #[test]
+    fn hitbox_is_visible_by_default() {
+        let mut world = World::new();
+        assert!(load_hitbox(&mut world, "active = true").is_visible());
+
+        let mut hitbox = load_hitbox(&mut world, "visible = false");
+        assert!(!hitbox.is_visible());
+        hitbox.set_visible(true);
+        assert!(hitbox.is_visible());
+    }
+
+    #[test]
+    fn damage_defaults_to_zero_and_allows_negatives() {
+        let mut world = World::new();
+
+        assert_eq!(load_hitbox(&mut world, "").damage, 0.0);
+        assert_eq!(load_hitbox(&mut world, "damage = 12").damage, 12.0);
+        assert_eq!(load_hitbox(&mut world, "damage = -2.5").damage, -2.5);
+    }
+
+    #[test]
+    fn unknown_keys_are_loaded_as_properties() {
+        let mut world = World::new();
+        let hitbox = load_hitbox(&mut world, "active = true\nelement = \"fire\"");
+
+        assert!(hitbox.get_property("active").is_none());
         assert_eq!(
-            0,
-            HitboxSequenceEvent::get_activated_hitboxes(&events).len()
+            hitbox.get_property("element").unwrap().as_str(),
+            Some("fire")
         );
-        let events = active_sequence.progress(&mut sequences, &hitboxes, 0.2);
+    }
 
-        assert_eq!(events.len(), 1);
-        let hitbox_entity = hitboxes.get(HITBOX_ENTITY_NAME).unwrap().clone();
+    #[test]
+    fn on_enter_hitbox_rearms_after_separating() {
+        let mut world = World::new();
+        let hitbox = load_hitbox(&mut world, "hit_mode = \"on_enter\"");
+        let target = world.spawn((Transform::default(),));
+        let id = world.spawn((hitbox,));
+        let can_damage =
+            |world: &World| world.get::<&Hitbox>(id).unwrap().can_damage_entity(&target);
+
+        assert!(can_damage(&world));
+        update_hitbox_overlaps(&mut world, &HashMap::from([(id, HashSet::from([target]))]));
+        assert!(!can_damage(&world));
+        update_hitbox_overlaps(&mut world, &HashMap::new());
+        assert!(can_damage(&world));
+    }
+
+    #[test]
+    fn expired_hitboxes_are_despawned() {
+        let mut world = World::new();
+        let (owner, bullet) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.bullet]
+                active = true
+                lifetime = 0.5
+                colliders = [{ width = 4.0, height = 4.0 }]
+            "#,
+        );
+
+        world.get::<&mut Hitbox>(bullet).unwrap().elapsed_time += 0.4;
+        assert_eq!(despawn_expired_hitboxes(&mut world), 0);
+        assert!(world.contains(bullet));
+
+        world.get::<&mut Hitbox>(bullet).unwrap().elapsed_time += 0.2;
+        assert_eq!(despawn_expired_hitboxes(&mut world), 1);
+        assert!(!world.contains(bullet));
+        assert!(world.get::<&HitboxSet>(owner).unwrap().hitboxes.is_empty());
+    }
+
+    #[test]
+    fn owner_hitboxes_include_set_and_standalone_hitboxes() {
+        let mut world = World::new();
+        let (owner, set_hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.jab]
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let value = emerald::toml::from_str::<Value>("active = true").unwrap();
+        let standalone = Hitbox::from_toml(&world, &value, owner).unwrap();
+        let standalone = world.spawn((standalone,));
+        let (other_owner, other_hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.kick]
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+
+        let hitboxes = get_hitboxes_for_owner(&world, owner)
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(hitboxes, HashSet::from([set_hitbox, standalone]));
         assert_eq!(
-            hitbox_entity,
-            HitboxSequenceEvent::get_activated_hitboxes(&events).remove(0)
+            get_hitboxes_for_owner(&world, other_owner),
+            vec![other_hitbox]
         );
+        assert!(get_hitboxes_for_owner(&world, standalone).is_empty());
     }
 
     #[test]
-    fn progressing_past_limit_deactivates_hitboxes() {
-        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
-        let events = active_sequence.progress(&mut sequences, &hitboxes, 40.0);
+    fn scaling_a_hitbox_doubles_its_extents() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.punch]
+                colliders = [{ width = 10.0, height = 10.0, translation = { x = 2.0, y = 0.0 } }]
+            "#,
+        );
+        let target = load_hitbox(&mut world, "colliders = [{ width = 2.0, height = 2.0 }]");
+        let target_transform = Transform::from_translation(Translation::new(13.0, 0.0));
+        let reaches_target = |world: &World| {
+            world.get::<&Hitbox>(hitbox).unwrap().scaled_colliders()[0].intersects(
+                &Transform::default(),
+                &target.raw_collider_data[0],
+                &target_transform,
+            )
+        };
+        assert!(!reaches_target(&world));
 
-        let hitbox_entity = hitboxes.get(HITBOX_ENTITY_NAME).unwrap().clone();
+        set_hitbox_scale(&mut world, hitbox, 2.0).unwrap();
+        let scaled = world
+            .get::<&Hitbox>(hitbox)
+            .unwrap()
+            .scaled_colliders()
+            .remove(0);
+        assert!(matches!(
+            scaled.shape,
+            ColliderShape::Rect { width, height } if width == 20.0 && height == 20.0
+        ));
+        assert_eq!(scaled.translation.x, 4.0);
+        assert!(reaches_target(&world));
+
+        let handle = world.get::<&Hitbox>(hitbox).unwrap().collider_handles[0];
+        let mut physics = world.physics();
+        let collider = physics.collider_mut(handle).unwrap();
+        let half_extents = collider.shape().compute_local_aabb().half_extents();
+        assert_eq!((half_extents.x, half_extents.y), (10.0, 10.0));
+        assert_eq!(collider.position_wrt_parent().unwrap().translation.x, 4.0);
+        drop(physics);
+
+        set_hitbox_scale(&mut world, hitbox, 0.0).unwrap();
+        let hitbox = world.get::<&Hitbox>(hitbox).unwrap();
+        assert_eq!(hitbox.get_scale(), MIN_HITBOX_SCALE);
+        assert!(matches!(
+            hitbox.raw_collider_data[0].shape,
+            ColliderShape::Rect { width, .. } if width == 10.0
+        ));
+    }
+
+    #[test]
+    fn authored_offsets_seed_the_hitbox_tracker() {
+        let mut world = World::new();
+        let (owner, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.jab]
+                offset = { x = 10, y = -2.5 }
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        world.get::<&mut Transform>(owner).unwrap().translation = Translation::new(5.0, 5.0);
+
+        let tracker = world.get::<&SimpleTranslationTracker>(hitbox).unwrap();
+        let owner_transform = world.get::<&Transform>(owner).unwrap();
+        let transform = tracker.tracked_transform(&owner_transform, 1.0, None);
         assert_eq!(
-            hitbox_entity,
-            HitboxSequenceEvent::get_deactivated_hitboxes(&events).remove(0)
+            (transform.translation.x, transform.translation.y),
+            (15.0, 2.5)
         );
+        assert!(world
+            .get::<&Hitbox>(hitbox)
+            .unwrap()
+            .get_property("offset")
+            .is_none());
     }
 
     #[test]
-    fn attack_sequence_can_only_deal_one_instance_of_damage_with_multiple_hitboxes() {}
+    fn left_facing_owner_mirrors_translated_colliders() {
+        let mut world = World::new();
+        let (owner, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.jab]
+                offset = { x = 10, y = 0 }
+
+                [[hitboxes.jab.colliders]]
+                width = 4.0
+                height = 2.0
+                rotation = 0.3
+                translation = { x = 6.0, y = 1.0 }
+            "#,
+        );
+        world.insert_one(owner, Facing(-1.0)).unwrap();
+        track_targets(&mut world, 0.016);
+
+        assert_eq!(
+            world.get::<&Transform>(hitbox).unwrap().translation.x,
+            -10.0
+        );
+        let handle = world.get::<&Hitbox>(hitbox).unwrap().collider_handles[0];
+        let mut physics = world.physics();
+        let pose = *physics
+            .collider_mut(handle)
+            .unwrap()
+            .position_wrt_parent()
+            .unwrap();
+        assert_eq!((pose.translation.x, pose.translation.y), (-6.0, 1.0));
+        assert!((pose.rotation.angle() + 0.3).abs() < 0.0001);
+    }
+
+    fn special_bullet_on_hit(_: &mut Emerald, _: &mut World, _: OnHitContext) {}
 
     #[test]
-    fn progressing_past_limit_of_all_frames_finishes_sequence() {
-        let (mut active_sequence, mut sequences, hitboxes) = get_test_package();
-        let events = active_sequence.progress(&mut sequences, &hitboxes, 40.0);
-        assert!(
-            events
+    fn continuous_hitbox_hits_hurtbox_it_jumped_over() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.bullet]
+                active = true
+                continuous = true
+                colliders = [{ width = 4.0, height = 4.0 }]
+            "#,
+        );
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world.get::<&mut Transform>(hurtbox).unwrap().translation = Translation::new(50.0, 0.0);
+
+        record_hitbox_translations(&mut world);
+        world.get::<&mut Transform>(hitbox).unwrap().translation = Translation::new(100.0, 0.0);
+        assert_eq!(get_swept_hurtboxes(&world, hitbox), vec![hurtbox]);
+
+        let (_, time) = get_swept_hurtbox_contacts(&world, hitbox)[0];
+        assert!(time > 0.3 && time < 0.5);
+
+        world.get::<&mut Hitbox>(hitbox).unwrap().continuous = false;
+        assert!(get_swept_hurtboxes(&world, hitbox).is_empty());
+    }
+
+    fn play_hit_sound(_: &mut Emerald, _: &mut World, _: OnHitContext) {}
+
+    /// Records the callbacks the callback hit handler would call for each hit
+    struct RecordingHitHandler<'a> {
+        config: &'a HitmeConfig,
+        calls: Vec<(Entity, Vec<usize>)>,
+    }
+    impl<'a> HitHandler for RecordingHitHandler<'a> {
+        fn filter(&mut self, _: &mut World, _: OnHitFilterContext) -> bool {
+            true
+        }
+
+        fn on_hit(&mut self, world: &mut World, ctx: OnHitContext) {
+            let callbacks = hit_callbacks(world, self.config, ctx.hitbox)
                 .into_iter()
-                .filter(|e| match e {
-                    HitboxSequenceEvent::Finished => true,
-                    _ => false,
-                })
-                .collect::<Vec<HitboxSequenceEvent>>()
-                .len()
-                == 1
+                .map(|f| f as usize)
+                .collect();
+            self.calls.push((ctx.hitbox, callbacks));
+        }
+    }
+
+    #[test]
+    fn on_hit_callback_only_belongs_to_its_own_hitbox() {
+        let mut world = World::new();
+        let toml = r#"
+            [hitboxes.bullet]
+            active = true
+            colliders = [{ width = 4.0, height = 4.0 }]
+        "#;
+        let (_, special) = spawn_hitbox_set(&mut world, toml);
+        let (_, regular) = spawn_hitbox_set(&mut world, toml);
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world
+            .get::<&mut Hitbox>(special)
+            .unwrap()
+            .set_on_hit(special_bullet_on_hit);
+
+        let mut config = HitmeConfig::default();
+        config.add_on_hit_fn(play_hit_sound);
+        let overlaps = HashMap::from([(special, vec![hurtbox]), (regular, vec![hurtbox])]);
+        let mut handler = RecordingHitHandler {
+            config: &config,
+            calls: Vec::new(),
+        };
+        resolve_hits(
+            &mut world,
+            &config,
+            &overlaps,
+            &vec![special, regular],
+            &mut handler,
         );
+
+        let sound = play_hit_sound as OnHitFn as usize;
+        let special_bullet = special_bullet_on_hit as OnHitFn as usize;
+        let calls = handler
+            .calls
+            .into_iter()
+            .collect::<HashMap<Entity, Vec<usize>>>();
+        assert_eq!(calls[&special], vec![sound, special_bullet]);
+        assert_eq!(calls[&regular], vec![sound]);
+
+        world.get::<&mut Hitbox>(special).unwrap().clear_on_hit();
+        assert!(get_hitbox_on_hit(&world, special).is_none());
     }
 }
@@ -0,0 +1,174 @@
+use emerald::{Emerald, Entity, World};
+
+use crate::{
+    event_buffer::EventBuffer, hit_stop::HitStop, GetDeltaFn, GetDeltaForEntityFn, HitmeConfig,
+    OnClashContext, OnClashFn, OnHitContext, OnHitFilterContext, OnHitFilterFn, OnHitFn,
+    OnTagTriggerContext, OnTagTriggerFn,
+};
+
+/// What the combat systems need from the game running them: its resources, its delta,
+/// and a way to call the user's callbacks.
+/// Implemented by `Emerald`, and by a headless host in tests, since an `Emerald` needs a window.
+pub(crate) trait HitmeHost {
+    fn config(&mut self) -> Option<&mut HitmeConfig>;
+    fn event_buffer(&mut self) -> Option<&mut EventBuffer>;
+    fn hit_stop(&mut self) -> Option<&mut HitStop>;
+
+    /// The delta of the frame, used when the config has no alternate delta function.
+    fn frame_delta(&mut self) -> f32;
+
+    fn call_delta_fn(&mut self, f: GetDeltaFn, world: &World) -> f32;
+    fn call_delta_for_entity_fn(
+        &mut self,
+        f: GetDeltaForEntityFn,
+        world: &World,
+        id: Entity,
+    ) -> f32;
+    fn call_hit_filter(
+        &mut self,
+        f: OnHitFilterFn,
+        world: &mut World,
+        ctx: OnHitFilterContext,
+    ) -> bool;
+    fn call_on_hit(&mut self, f: OnHitFn, world: &mut World, ctx: OnHitContext);
+    fn call_on_clash(&mut self, f: OnClashFn, world: &mut World, ctx: OnClashContext);
+    fn call_tag_handler(&mut self, f: OnTagTriggerFn, world: &mut World, ctx: OnTagTriggerContext);
+}
+impl HitmeHost for Emerald {
+    fn config(&mut self) -> Option<&mut HitmeConfig> {
+        self.resources().get_mut::<HitmeConfig>()
+    }
+
+    fn event_buffer(&mut self) -> Option<&mut EventBuffer> {
+        self.resources().get_mut::<EventBuffer>()
+    }
+
+    fn hit_stop(&mut self) -> Option<&mut HitStop> {
+        self.resources().get_mut::<HitStop>()
+    }
+
+    fn frame_delta(&mut self) -> f32 {
+        self.delta()
+    }
+
+    fn call_delta_fn(&mut self, f: GetDeltaFn, world: &World) -> f32 {
+        f(self, world)
+    }
+
+    fn call_delta_for_entity_fn(
+        &mut self,
+        f: GetDeltaForEntityFn,
+        world: &World,
+        id: Entity,
+    ) -> f32 {
+        f(self, world, id)
+    }
+
+    fn call_hit_filter(
+        &mut self,
+        f: OnHitFilterFn,
+        world: &mut World,
+        ctx: OnHitFilterContext,
+    ) -> bool {
+        f(self, world, ctx)
+    }
+
+    fn call_on_hit(&mut self, f: OnHitFn, world: &mut World, ctx: OnHitContext) {
+        f(self, world, ctx)
+    }
+
+    fn call_on_clash(&mut self, f: OnClashFn, world: &mut World, ctx: OnClashContext) {
+        f(self, world, ctx)
+    }
+
+    fn call_tag_handler(&mut self, f: OnTagTriggerFn, world: &mut World, ctx: OnTagTriggerContext) {
+        f(self, world, ctx)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use emerald::{Entity, World};
+
+    use crate::{
+        event_buffer::EventBuffer, hit_stop::HitStop, host::HitmeHost, GetDeltaFn,
+        GetDeltaForEntityFn, HitmeConfig, OnClashContext, OnClashFn, OnHitContext,
+        OnHitFilterContext, OnHitFilterFn, OnHitFn, OnTagTriggerContext, OnTagTriggerFn,
+    };
+
+    /// Runs the systems without a game. Callbacks take an `Emerald`, so instead of being called
+    /// tag handlers and on hit callbacks are recorded, filters let every hit through, and every delta is `delta`.
+    #[derive(Default)]
+    pub(crate) struct HeadlessHost {
+        pub config: Option<HitmeConfig>,
+        pub event_buffer: Option<EventBuffer>,
+        pub hit_stop: Option<HitStop>,
+        pub delta: f32,
+
+        /// Tag handlers called, with the tag they were called for
+        pub tag_handler_calls: Vec<(usize, String)>,
+
+        /// On hit callbacks called, with the hitbox that hit
+        pub on_hit_calls: Vec<(usize, Entity)>,
+    }
+    impl HeadlessHost {
+        /// A host set up the way `emd_hitme::init` sets up a game.
+        pub fn new(config: HitmeConfig, delta: f32) -> Self {
+            Self {
+                config: Some(config),
+                event_buffer: Some(EventBuffer::new()),
+                delta,
+                ..Default::default()
+            }
+        }
+    }
+    impl HitmeHost for HeadlessHost {
+        fn config(&mut self) -> Option<&mut HitmeConfig> {
+            self.config.as_mut()
+        }
+
+        fn event_buffer(&mut self) -> Option<&mut EventBuffer> {
+            self.event_buffer.as_mut()
+        }
+
+        fn hit_stop(&mut self) -> Option<&mut HitStop> {
+            self.hit_stop.as_mut()
+        }
+
+        fn frame_delta(&mut self) -> f32 {
+            self.delta
+        }
+
+        fn call_delta_fn(&mut self, _: GetDeltaFn, _: &World) -> f32 {
+            self.delta
+        }
+
+        fn call_delta_for_entity_fn(
+            &mut self,
+            _: GetDeltaForEntityFn,
+            _: &World,
+            _: Entity,
+        ) -> f32 {
+            self.delta
+        }
+
+        fn call_hit_filter(
+            &mut self,
+            _: OnHitFilterFn,
+            _: &mut World,
+            _: OnHitFilterContext,
+        ) -> bool {
+            true
+        }
+
+        fn call_on_hit(&mut self, f: OnHitFn, _: &mut World, ctx: OnHitContext) {
+            self.on_hit_calls.push((f as usize, ctx.hitbox));
+        }
+
+        fn call_on_clash(&mut self, _: OnClashFn, _: &mut World, _: OnClashContext) {}
+
+        fn call_tag_handler(&mut self, f: OnTagTriggerFn, _: &mut World, ctx: OnTagTriggerContext) {
+            self.tag_handler_calls.push((f as usize, ctx.tag));
+        }
+    }
+}
@@ -1,16 +1,34 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
+use emerald::rapier2d::parry::query::{intersection_test, time_of_impact};
+use emerald::rapier2d::prelude::{Isometry, Point};
 use emerald::{
-    ColliderBuilder, EmeraldError, Entity, Group, InteractionGroups, RigidBodyBuilder, Transform,
-    Translation, Vector2, World,
+    ColliderBuilder, ColliderHandle, EmeraldError, Entity, Group, InteractionGroups,
+    RigidBodyBuilder, Transform, Translation, Vector2, World,
 };
 
+use crate::active_box_index::{
+    ensure_active_box_index, get_indexed_active_hurtboxes, index_hurtbox,
+};
+use crate::hitboxes::Hitbox;
+use crate::host::HitmeHost;
+use crate::overlap::{OverlapSource, PhysicsOverlapSource};
 use crate::tracker::SimpleTranslationTracker;
+use crate::{get_facing, CombatBox, HitmeConfig};
 
 pub struct HurtboxSet {
     pub hurtboxes: Vec<Entity>,
     /// The entity that owns this hurtbox, and will receive damage from it
     pub owner: Entity,
+
+    /// Whether the owner is guarding, see `OnHitContext::blocked`
+    blocking: bool,
+
+    /// How much of a blocked hit's damage is negated, from 0.0 to 1.0. Defaults to 1.0
+    pub block_reduction: f32,
+
+    /// Collision groups of the set's colliders, see `toml_value_to_groups`
+    collision_groups: InteractionGroups,
 }
 impl HurtboxSet {
     pub fn from_toml(
@@ -21,6 +39,21 @@ impl HurtboxSet {
         hitbox_group: Group,
     ) -> Result<Self, EmeraldError> {
         let owner_transform = world.get::<&mut Transform>(owner)?.clone();
+        let (hurtbox_group, hitbox_group) =
+            toml_value_to_groups(value, hurtbox_group, hitbox_group);
+        let collision_groups = InteractionGroups::new(hurtbox_group, hitbox_group);
+        let track_rotation = value
+            .get("track_rotation")
+            .unwrap_or(&emerald::toml::Value::Boolean(false))
+            .as_bool()
+            .unwrap_or(false);
+        let team = toml_value_to_team(value);
+        ensure_active_box_index(world);
+        let block_reduction = value
+            .get("block_reduction")
+            .map(toml_value_to_f32)
+            .flatten()
+            .unwrap_or(1.0);
         let hurtboxes = value
             .get("hurtboxes")
             .unwrap_or(&emerald::toml::Value::Array(Vec::new()))
@@ -30,15 +63,18 @@ impl HurtboxSet {
             .map(|value| Hurtbox::from_toml(value, owner))
             .collect::<Result<Vec<Hurtbox>, EmeraldError>>()?
             .into_iter()
-            .map(|hurtbox| {
+            .map(|mut hurtbox| {
+                hurtbox.team = hurtbox.team.or(team);
                 let colliders = hurtbox.colliders.clone();
+                let active = hurtbox.active;
                 let (id, rbh) = world.spawn_with_body(
                     (
                         hurtbox,
+                        CombatBox,
                         owner_transform.clone(),
                         SimpleTranslationTracker {
-                            target: owner,
-                            offset: Translation::new(0.0, 0.0),
+                            track_rotation,
+                            ..SimpleTranslationTracker::new(owner, Translation::new(0.0, 0.0))
                         },
                     ),
                     RigidBodyBuilder::dynamic(),
@@ -47,15 +83,37 @@ impl HurtboxSet {
                 for collider in colliders {
                     let builder = collider
                         .to_collider_builder()
-                        .collision_groups(InteractionGroups::new(hurtbox_group, hitbox_group));
-                    world.physics().build_collider(rbh, builder);
+                        .collision_groups(collision_groups);
+                    let handle = world.physics().build_collider(rbh, builder);
+                    world.get::<&mut Hurtbox>(id)?.collider_handles.push(handle);
+                }
+                if active {
+                    index_hurtbox(world, id);
                 }
 
                 Ok(id)
             })
             .collect::<Result<Vec<Entity>, EmeraldError>>()?;
 
-        Ok(Self { hurtboxes, owner })
+        Ok(Self {
+            hurtboxes,
+            owner,
+            blocking: false,
+            block_reduction,
+            collision_groups,
+        })
+    }
+
+    pub fn get_collision_groups(&self) -> InteractionGroups {
+        self.collision_groups
+    }
+
+    pub fn is_blocking(&self) -> bool {
+        self.blocking
+    }
+
+    pub fn set_blocking(&mut self, blocking: bool) {
+        self.blocking = blocking;
     }
 
     fn get_active_hurtboxes(world: &World, hurtbox_entities: Vec<Entity>) -> Vec<Entity> {
@@ -72,6 +130,17 @@ impl HurtboxSet {
     }
 }
 
+/// Returns the block state of the set owning the hurtbox, and its block reduction if blocking.
+pub fn get_hurtbox_block_reduction(world: &World, hurtbox_id: Entity) -> Option<f32> {
+    world
+        .get::<&Hurtbox>(hurtbox_id)
+        .ok()
+        .map(|h| world.get::<&HurtboxSet>(h.parent_set).ok())
+        .flatten()
+        .filter(|set| set.blocking)
+        .map(|set| set.block_reduction)
+}
+
 pub fn get_hurtbox_owner(world: &World, hurtbox_id: Entity) -> Option<Entity> {
     world
         .get::<&Hurtbox>(hurtbox_id)
@@ -86,12 +155,76 @@ pub fn get_hurtbox_owner(world: &World, hurtbox_id: Entity) -> Option<Entity> {
         .flatten()
 }
 
+/// Returns every hurtbox of the owner, both those of its hurtbox set
+/// and standalone hurtboxes spawned with the owner as their parent.
+pub fn get_hurtboxes_for_owner(world: &World, owner: Entity) -> Vec<Entity> {
+    let mut hurtboxes = world
+        .get::<&HurtboxSet>(owner)
+        .ok()
+        .map(|set| set.hurtboxes.clone())
+        .unwrap_or_default();
+
+    for (id, hurtbox) in world.query::<&Hurtbox>().iter() {
+        if hurtbox.parent_set == owner && !hurtboxes.contains(&id) {
+            hurtboxes.push(id);
+        }
+    }
+
+    hurtboxes
+}
+
+/// Returns the direction the hurtbox is facing, based on its rotation,
+/// mirrored horizontally when its owner faces left, see `get_facing`.
+/// Hurtbox sets with `track_rotation` enabled face the same direction as their owner.
+pub fn get_hurtbox_facing(world: &World, hurtbox_id: Entity) -> Option<Vector2<f32>> {
+    if !world.has::<Hurtbox>(hurtbox_id) {
+        return None;
+    }
+
+    let owner = get_hurtbox_owner(world, hurtbox_id).unwrap_or(hurtbox_id);
+    let facing = get_facing(world, owner);
+    world
+        .get::<&Transform>(hurtbox_id)
+        .ok()
+        .map(|transform| Vector2::new(facing * transform.rotation.cos(), transform.rotation.sin()))
+}
+
 pub struct Hurtbox {
+    /// Writing this directly isn't seen by the active box index,
+    /// prefer `set_hurtbox_active`, or call `mark_active_boxes_stale` afterwards.
     pub active: bool,
     pub parent_set: Entity,
     pub colliders: Vec<RectCollider>,
-    /// Whether or not the hurtbox is visible when debug drawing
+    /// Whether or not the hurtbox is visible when debug drawing, defaults to true
     pub visible: bool,
+
+    /// How many more hits the hurtbox absorbs without the owner reacting to them.
+    /// Armored hits still deal damage, see `OnHitContext::broke_armor`.
+    pub armor_hits: Option<u32>,
+
+    /// Gameplay tags, ex. "weapon". Hitboxes can choose to only hit hurtboxes with certain tags.
+    pub tags: Vec<String>,
+
+    /// The material of the hurtbox, ex. "metal" or "flesh", used to pick hit effects
+    pub surface: Option<String>,
+
+    /// The team the hurtbox belongs to, defaults to the team of its set
+    pub team: Option<u32>,
+
+    /// Scales the damage of hits landing on this hurtbox, ex. 2.0 for a weak point
+    pub damage_multiplier: f32,
+
+    /// How long the hurtbox ignores hits for, see `Hurtbox::make_invulnerable`
+    pub invulnerable_for: Option<f32>,
+
+    /// Time elapsed since the hurtbox was made invulnerable
+    pub invulnerable_elapsed: f32,
+
+    /// Named countdowns in seconds, ex. "block_stun", removed once they run out
+    timers: HashMap<String, f32>,
+
+    /// Handles of the physics colliders, in the same order as `colliders`
+    collider_handles: Vec<ColliderHandle>,
 }
 impl Hurtbox {
     pub fn from_toml(
@@ -115,43 +248,458 @@ impl Hurtbox {
 
         let visible = value
             .get("visible")
-            .unwrap_or(&emerald::toml::Value::Boolean(false))
-            .as_bool()
-            .unwrap_or(false);
+            .map(|v| v.as_bool())
+            .flatten()
+            .unwrap_or(true);
+        let armor_hits = value
+            .get("armor_hits")
+            .map(|v| v.as_integer())
+            .flatten()
+            .map(|n| n.max(0) as u32);
+
+        let tags = toml_value_to_strings(value.get("tags"));
+        let surface = value
+            .get("surface")
+            .map(|v| v.as_str())
+            .flatten()
+            .map(|s| s.to_string());
+        let damage_multiplier = value
+            .get("damage_multiplier")
+            .map(toml_value_to_f32)
+            .flatten()
+            .unwrap_or(1.0);
+        let team = toml_value_to_team(value);
+
         Ok(Self {
             active,
             parent_set,
             colliders,
             visible,
+            armor_hits,
+            tags,
+            surface,
+            team,
+            damage_multiplier,
+            invulnerable_for: None,
+            invulnerable_elapsed: 0.0,
+            timers: HashMap::new(),
+            collider_handles: Vec::new(),
         })
     }
+
+    /// The handles of the hurtbox's physics colliders, with the colliders they were built from
+    pub(crate) fn physics_colliders(&self) -> Vec<(ColliderHandle, RectCollider)> {
+        self.collider_handles
+            .iter()
+            .cloned()
+            .zip(self.colliders.iter().cloned())
+            .collect()
+    }
+
+    /// Name of the hurtbox collider built as the given physics collider, if it has one.
+    pub fn get_collider_name(&self, handle: ColliderHandle) -> Option<String> {
+        self.physics_colliders()
+            .into_iter()
+            .find(|(collider_handle, _)| *collider_handle == handle)
+            .map(|(_, collider)| collider.name)
+            .flatten()
+    }
+
+    /// Makes the hurtbox ignore hits for the given duration, ex. i-frames after being hit.
+    pub fn make_invulnerable(&mut self, duration: f32) {
+        self.invulnerable_for = Some(duration);
+        self.invulnerable_elapsed = 0.0;
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_for
+            .map(|duration| self.invulnerable_elapsed < duration)
+            .unwrap_or(false)
+    }
+
+    /// Advances the invulnerability timer, clearing it once it runs out.
+    pub fn advance_invulnerability(&mut self, delta: f32) {
+        if self.invulnerable_for.is_none() {
+            return;
+        }
+
+        self.invulnerable_elapsed += delta;
+        if !self.is_invulnerable() {
+            self.invulnerable_for = None;
+            self.invulnerable_elapsed = 0.0;
+        }
+    }
+
+    /// Starts a named countdown on the hurtbox, replacing any running timer of the same name.
+    pub fn start_timer<T: Into<String>>(&mut self, name: T, duration: f32) {
+        self.timers.insert(name.into(), duration);
+    }
+
+    /// Returns how long the named timer has left, if it's running
+    pub fn get_timer_remaining(&self, name: &str) -> Option<f32> {
+        self.timers.get(name).cloned()
+    }
+
+    pub fn is_timer_running(&self, name: &str) -> bool {
+        self.timers.contains_key(name)
+    }
+
+    /// Whether the hurtbox has any state that `advance_timers` needs to progress
+    pub fn has_timers(&self) -> bool {
+        self.invulnerable_for.is_some() || !self.timers.is_empty()
+    }
+
+    /// Advances invulnerability and every named timer, removing timers that ran out.
+    pub fn advance_timers(&mut self, delta: f32) {
+        self.advance_invulnerability(delta);
+
+        self.timers
+            .values_mut()
+            .for_each(|remaining| *remaining -= delta);
+        self.timers.retain(|_, remaining| *remaining > 0.0);
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Consumes an armor point, returns true if this consumed the last one.
+    pub fn consume_armor(&mut self) -> bool {
+        match self.armor_hits.as_mut() {
+            Some(armor_hits) if *armor_hits > 0 => {
+                *armor_hits -= 1;
+                *armor_hits == 0
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ColliderShape {
+    Rect {
+        width: f32,
+        height: f32,
+    },
+
+    Circle {
+        radius: f32,
+    },
+
+    /// A rectangle with rounded ends, its length runs along the axis
+    Capsule {
+        half_height: f32,
+        radius: f32,
+        axis: CapsuleAxis,
+    },
+
+    /// A convex outline, points are relative to the collider translation
+    Polygon {
+        points: Vec<Vector2<f32>>,
+    },
+}
+impl ColliderShape {
+    pub fn from_toml(value: &emerald::toml::Value) -> Result<Self, EmeraldError> {
+        let shape = value
+            .get("shape")
+            .map(|v| v.as_str())
+            .flatten()
+            .unwrap_or("rect");
+
+        match shape {
+            "rect" => {
+                let width = value
+                    .get("width")
+                    .unwrap_or(&emerald::toml::Value::Float(0.0))
+                    .as_float()
+                    .unwrap_or(0.0) as f32;
+                let height = value
+                    .get("height")
+                    .unwrap_or(&emerald::toml::Value::Float(0.0))
+                    .as_float()
+                    .unwrap_or(0.0) as f32;
+
+                Ok(ColliderShape::Rect { width, height })
+            }
+            "circle" => {
+                let radius = value
+                    .get("radius")
+                    .map(toml_value_to_f32)
+                    .flatten()
+                    .ok_or(EmeraldError::new("Circle collider is missing a radius"))?;
+
+                Ok(ColliderShape::Circle { radius })
+            }
+            "capsule" => {
+                let half_height = value
+                    .get("half_height")
+                    .map(toml_value_to_f32)
+                    .flatten()
+                    .ok_or(EmeraldError::new(
+                        "Capsule collider is missing a half_height",
+                    ))?;
+                let radius = value
+                    .get("radius")
+                    .map(toml_value_to_f32)
+                    .flatten()
+                    .ok_or(EmeraldError::new("Capsule collider is missing a radius"))?;
+                let axis = match value.get("axis").map(|v| v.as_str()).flatten() {
+                    None | Some("y") => CapsuleAxis::Y,
+                    Some("x") => CapsuleAxis::X,
+                    Some(axis) => {
+                        return Err(EmeraldError::new(format!(
+                            "Unknown capsule axis {}, expected x or y",
+                            axis
+                        )))
+                    }
+                };
+
+                Ok(ColliderShape::Capsule {
+                    half_height,
+                    radius,
+                    axis,
+                })
+            }
+            "polygon" => {
+                let points = value
+                    .get("points")
+                    .map(|v| v.as_array())
+                    .flatten()
+                    .ok_or(EmeraldError::new(
+                        "Polygon collider is missing a points array",
+                    ))?
+                    .into_iter()
+                    .map(|p| translation_to_vector(&toml_value_to_translation(p)))
+                    .collect::<Vec<Vector2<f32>>>();
+                validate_polygon(&points)?;
+
+                Ok(ColliderShape::Polygon { points })
+            }
+            _ => Err(EmeraldError::new(format!(
+                "Unknown collider shape {}",
+                shape
+            ))),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapsuleAxis {
+    X,
+    Y,
+}
+
+/// Polygons must have at least 3 points, enclose an area, be convex, and not cross themselves.
+fn validate_polygon(points: &Vec<Vector2<f32>>) -> Result<(), EmeraldError> {
+    if points.len() < 3 {
+        return Err(EmeraldError::new(format!(
+            "Polygon collider requires at least 3 points, found {}",
+            points.len()
+        )));
+    }
+
+    let mut has_positive_turn = false;
+    let mut has_negative_turn = false;
+    // Sum of the turns between edges, a simple polygon turns around exactly once
+    let mut total_turn = 0.0;
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        let c = points[(i + 2) % points.len()];
+        let cross = (b.x - a.x) * (c.y - b.y) - (b.y - a.y) * (c.x - b.x);
+        let dot = (b.x - a.x) * (c.x - b.x) + (b.y - a.y) * (c.y - b.y);
+        total_turn += cross.atan2(dot);
+
+        if cross > f32::EPSILON {
+            has_positive_turn = true;
+        } else if cross < -f32::EPSILON {
+            has_negative_turn = true;
+        }
+    }
+
+    if !has_positive_turn && !has_negative_turn {
+        return Err(EmeraldError::new(
+            "Polygon collider is degenerate, its points do not enclose an area",
+        ));
+    }
+
+    if has_positive_turn && has_negative_turn {
+        return Err(EmeraldError::new(
+            "Polygon collider is not convex, split it into multiple convex colliders",
+        ));
+    }
+
+    // Turning around more than once means the outline crosses itself, ex. a star
+    if total_turn.abs() > 3.0 * std::f32::consts::PI {
+        return Err(EmeraldError::new(
+            "Polygon collider intersects itself, list its points in order around the outline",
+        ));
+    }
+
+    Ok(())
 }
 
 #[derive(Clone, Debug)]
 pub struct RectCollider {
-    pub width: f32,
-    pub height: f32,
+    pub shape: ColliderShape,
     pub name: Option<String>,
     pub translation: Translation,
+
+    /// Rotation of the collider around its translation, in radians
+    pub rotation: f32,
+
+    /// Builds a solid collider the physics engine pushes bodies out of, ex. shields or pushboxes.
+    /// Defaults to false, a sensor that only detects overlaps.
+    pub solid: bool,
 }
 impl RectCollider {
     pub fn to_collider_builder(self) -> ColliderBuilder {
-        ColliderBuilder::cuboid(self.width / 2.0, self.height / 2.0)
-            .translation(Vector2::new(self.translation.x, self.translation.y))
-            .sensor(true)
+        let builder = match self.shape {
+            ColliderShape::Rect { width, height } => {
+                ColliderBuilder::cuboid(width / 2.0, height / 2.0)
+            }
+            ColliderShape::Circle { radius } => ColliderBuilder::ball(radius),
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+                axis,
+            } => match axis {
+                CapsuleAxis::X => ColliderBuilder::capsule_x(half_height, radius),
+                CapsuleAxis::Y => ColliderBuilder::capsule_y(half_height, radius),
+            },
+            ColliderShape::Polygon { points } => {
+                let points = points.into_iter().map(Point::from).collect::<Vec<_>>();
+                ColliderBuilder::convex_hull(&points)
+                    .unwrap_or_else(|| ColliderBuilder::polyline(points, None))
+            }
+        };
+
+        builder
+            .translation(self.translation_vec())
+            .rotation(self.rotation)
+            .sensor(!self.solid)
+    }
+
+    /// Returns a copy of the collider flipped horizontally, as authored for a left-facing owner.
+    pub fn mirrored(&self) -> Self {
+        let shape = match &self.shape {
+            ColliderShape::Polygon { points } => ColliderShape::Polygon {
+                points: points
+                    .iter()
+                    .rev()
+                    .map(|point| Vector2::new(-point.x, point.y))
+                    .collect(),
+            },
+            shape => shape.clone(),
+        };
+
+        Self {
+            shape,
+            translation: Translation::new(-self.translation.x, self.translation.y),
+            rotation: -self.rotation,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a copy of the collider with its size and translation multiplied by the scale.
+    pub fn scaled(&self, scale: f32) -> Self {
+        let shape = match &self.shape {
+            ColliderShape::Rect { width, height } => ColliderShape::Rect {
+                width: width * scale,
+                height: height * scale,
+            },
+            ColliderShape::Circle { radius } => ColliderShape::Circle {
+                radius: radius * scale,
+            },
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+                axis,
+            } => ColliderShape::Capsule {
+                half_height: half_height * scale,
+                radius: radius * scale,
+                axis: *axis,
+            },
+            ColliderShape::Polygon { points } => ColliderShape::Polygon {
+                points: points.iter().map(|point| point * scale).collect(),
+            },
+        };
+
+        Self {
+            shape,
+            translation: Translation::new(self.translation.x * scale, self.translation.y * scale),
+            ..self.clone()
+        }
+    }
+
+    pub fn translation_vec(&self) -> Vector2<f32> {
+        translation_to_vector(&self.translation)
+    }
+
+    /// Returns true if this collider, on an entity with the given transform,
+    /// intersects the other collider on its entity.
+    pub fn intersects(
+        &self,
+        transform: &Transform,
+        other: &RectCollider,
+        other_transform: &Transform,
+    ) -> bool {
+        let shape = self.clone().to_collider_builder().build();
+        let other_shape = other.clone().to_collider_builder().build();
+
+        intersection_test(
+            &self.world_isometry(transform),
+            shape.shape(),
+            &other.world_isometry(other_transform),
+            other_shape.shape(),
+        )
+        .unwrap_or(false)
+    }
+
+    /// Returns when this collider, moving by `travel` from the given transform, first touches
+    /// the other collider on its entity, from 0.0 to 1.0. None if it never does.
+    pub fn first_contact_time(
+        &self,
+        transform: &Transform,
+        travel: &Vector2<f32>,
+        other: &RectCollider,
+        other_transform: &Transform,
+    ) -> Option<f32> {
+        let shape = self.clone().to_collider_builder().build();
+        let other_shape = other.clone().to_collider_builder().build();
+
+        time_of_impact(
+            &self.world_isometry(transform),
+            travel,
+            shape.shape(),
+            &other.world_isometry(other_transform),
+            &Vector2::zeros(),
+            other_shape.shape(),
+            1.0,
+        )
+        .ok()
+        .flatten()
+        .map(|toi| toi.toi)
+    }
+
+    fn world_isometry(&self, transform: &Transform) -> Isometry<f32> {
+        let body = Isometry::new(
+            translation_to_vector(&transform.translation),
+            transform.rotation,
+        );
+        body * Isometry::new(self.translation_vec(), self.rotation)
     }
 
     pub fn from_toml(value: &emerald::toml::Value) -> Result<Self, EmeraldError> {
-        let width = value
-            .get("width")
-            .unwrap_or(&emerald::toml::Value::Float(0.0))
-            .as_float()
-            .unwrap_or(0.0) as f32;
-        let height = value
-            .get("height")
-            .unwrap_or(&emerald::toml::Value::Float(0.0))
-            .as_float()
-            .unwrap_or(0.0) as f32;
+        let shape = ColliderShape::from_toml(value)?;
 
         let mut name = None;
 
@@ -167,39 +715,122 @@ impl RectCollider {
             translation = toml_value_to_translation(value);
         }
 
+        let rotation = value
+            .get("rotation")
+            .map(toml_value_to_f32)
+            .flatten()
+            .unwrap_or(0.0);
+
+        let solid = value
+            .get("solid")
+            .map(|v| v.as_bool())
+            .flatten()
+            .unwrap_or(false);
+
         Ok(Self {
-            width,
-            height,
+            shape,
             translation,
             name,
+            rotation,
+            solid,
         })
     }
 }
 
+/// Reads a number, accepting both TOML integers and floats.
+pub fn toml_value_to_f32(value: &emerald::toml::Value) -> Option<f32> {
+    value
+        .as_float()
+        .map(|f| f as f32)
+        .or_else(|| value.as_integer().map(|i| i as f32))
+}
+
+/// Reads the optional `groups` table of a set, ex. `groups = { hitbox = 3, hurtbox = 4 }`.
+/// Each group is a number from 1 to 32, missing groups fall back to the given ones.
+/// Returns the hurtbox group and the hitbox group.
+pub fn toml_value_to_groups(
+    value: &emerald::toml::Value,
+    hurtbox_group: Group,
+    hitbox_group: Group,
+) -> (Group, Group) {
+    let get_group = |key: &str| {
+        value
+            .get("groups")
+            .map(|groups| groups.get(key))
+            .flatten()
+            .map(|v| v.as_integer())
+            .flatten()
+            .filter(|n| (1..=32).contains(n))
+            .map(|n| Group::from_bits_truncate(1 << (n - 1)))
+    };
+
+    (
+        get_group("hurtbox").unwrap_or(hurtbox_group),
+        get_group("hitbox").unwrap_or(hitbox_group),
+    )
+}
+
+/// Reads the optional `team` key of a box or set.
+pub fn toml_value_to_team(value: &emerald::toml::Value) -> Option<u32> {
+    value
+        .get("team")
+        .map(|v| v.as_integer())
+        .flatten()
+        .map(|n| n.max(0) as u32)
+}
+
+/// Reads an array of strings, ignoring any non string values.
+pub fn toml_value_to_strings(value: Option<&emerald::toml::Value>) -> Vec<String> {
+    value
+        .map(|v| v.as_array())
+        .flatten()
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str().map(|s| s.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn translation_to_vector(translation: &Translation) -> Vector2<f32> {
+    Vector2::new(translation.x, translation.y)
+}
+
+pub fn vector_to_translation(vector: &Vector2<f32>) -> Translation {
+    Translation::new(vector.x, vector.y)
+}
+
 pub fn toml_value_to_translation(value: &emerald::toml::Value) -> Translation {
     let x = value
         .get("x")
-        .unwrap_or(&emerald::toml::Value::Float(0.0))
-        .as_float()
-        .unwrap_or(0.0) as f32;
+        .map(toml_value_to_f32)
+        .flatten()
+        .unwrap_or(0.0);
     let y = value
         .get("y")
-        .unwrap_or(&emerald::toml::Value::Float(0.0))
-        .as_float()
-        .unwrap_or(0.0) as f32;
+        .map(toml_value_to_f32)
+        .flatten()
+        .unwrap_or(0.0);
 
     Translation::new(x, y)
 }
 
+/// Returns the active hurtboxes of the world, see `ActiveBoxIndex`.
 pub fn get_all_active_hurtboxes(world: &World) -> Vec<Entity> {
-    world
-        .query::<&Hurtbox>()
-        .iter()
-        .filter_map(|(id, hurtbox)| hurtbox.active.then(|| id))
-        .collect()
+    get_indexed_active_hurtboxes(world)
+}
+
+/// Activates or deactivates the hurtbox, keeping the active box index up to date.
+pub fn set_hurtbox_active(world: &mut World, id: Entity, active: bool) {
+    if let Ok(mut hurtbox) = world.get::<&mut Hurtbox>(id) {
+        hurtbox.active = active;
+    }
+    if active {
+        index_hurtbox(world, id);
+    }
 }
 
-/// returns all entities that have hurtboxes from the given set
+/// returns all entities that have hurtboxes from the given set, skipping invulnerable hurtboxes
 fn get_active_hurtboxes_on_entities(world: &World, entities: Vec<Entity>) -> Vec<Entity> {
     entities
         .into_iter()
@@ -207,7 +838,7 @@ fn get_active_hurtboxes_on_entities(world: &World, entities: Vec<Entity>) -> Vec
             world
                 .get::<&Hurtbox>(id)
                 .ok()
-                .map(|h| h.active)
+                .map(|h| h.active && !h.is_invulnerable())
                 .unwrap_or(false)
                 .then(|| id)
         })
@@ -227,7 +858,17 @@ fn get_colliding_active_hurtbox_sets(world: &mut World, id: Entity) -> Vec<Entit
 }
 
 pub fn get_colliding_active_hurtboxes(world: &mut World, id: Entity) -> Vec<Entity> {
-    let colliding_entities = world.physics().get_colliding_entities(id);
+    let overlaps = PhysicsOverlapSource::new(world, &vec![id]);
+    get_overlapping_active_hurtboxes(world, &overlaps, id)
+}
+
+/// Returns the active hurtboxes overlapping the given entity, according to the overlap source.
+pub fn get_overlapping_active_hurtboxes(
+    world: &World,
+    overlaps: &dyn OverlapSource,
+    id: Entity,
+) -> Vec<Entity> {
+    let colliding_entities = overlaps.colliding(id);
     let colliding_active_hurtboxes = get_active_hurtboxes_on_entities(world, colliding_entities);
     colliding_active_hurtboxes
 }
@@ -268,3 +909,457 @@ pub fn get_hurtbox_sets_from_hurtboxes(world: &World, hurtboxes: Vec<Entity>) ->
         .into_iter()
         .collect()
 }
+
+/// Removes the hurtbox set from its owner, and despawns every hurtbox of the set.
+/// Returns how many hurtbox entities were despawned.
+pub fn despawn_hurtbox_set(world: &mut World, owner: Entity) -> usize {
+    let hurtbox_set = match world.remove_one::<HurtboxSet>(owner) {
+        Ok(hurtbox_set) => hurtbox_set,
+        Err(_) => return 0,
+    };
+
+    hurtbox_set
+        .hurtboxes
+        .into_iter()
+        .filter(|id| world.despawn(id.clone()).is_ok())
+        .count()
+}
+
+/// Returns the physics collider of the hurtbox that the hitbox's physics colliders touch.
+/// Pairs whose collision groups don't interact are skipped,
+/// ex. hitbox colliders switched off with `set_hitbox_collider_active`.
+pub fn get_struck_collider(
+    world: &mut World,
+    hitbox: Entity,
+    hurtbox: Entity,
+) -> Option<ColliderHandle> {
+    let hitbox_colliders = world.get::<&Hitbox>(hitbox).ok()?.physics_colliders();
+    let hurtbox_colliders = world.get::<&Hurtbox>(hurtbox).ok()?.physics_colliders();
+
+    let mut physics = world.physics();
+    let mut shapes = |colliders: Vec<(ColliderHandle, RectCollider)>| {
+        colliders
+            .into_iter()
+            .filter_map(|(handle, _)| {
+                let collider = physics.collider_mut(handle)?;
+                Some((
+                    handle,
+                    collider.collision_groups(),
+                    collider.shared_shape().clone(),
+                    *collider.position(),
+                ))
+            })
+            .collect::<Vec<_>>()
+    };
+    let hitbox_shapes = shapes(hitbox_colliders);
+    let hurtbox_shapes = shapes(hurtbox_colliders);
+
+    hurtbox_shapes
+        .into_iter()
+        .find(|(_, groups, shape, position)| {
+            hitbox_shapes
+                .iter()
+                .any(|(_, hitbox_groups, hitbox_shape, hitbox_position)| {
+                    hitbox_groups.test(*groups)
+                        && intersection_test(hitbox_position, &**hitbox_shape, position, &**shape)
+                            .unwrap_or(false)
+                })
+        })
+        .map(|(handle, ..)| handle)
+}
+
+/// Returns the name of the hurtbox collider struck by the hitbox, from the colliders' data
+/// rather than the physics engine, see `get_struck_collider`.
+/// Hurtboxes with a single collider report its name without checking for intersections.
+pub fn get_struck_collider_name(world: &World, hitbox: Entity, hurtbox: Entity) -> Option<String> {
+    let hurtbox_ref = world.get::<&Hurtbox>(hurtbox).ok()?;
+    if hurtbox_ref.colliders.len() == 1 {
+        return hurtbox_ref.colliders[0].name.clone();
+    }
+
+    let hitbox_ref = world.get::<&Hitbox>(hitbox).ok()?;
+    let hurtbox_transform = world.get::<&Transform>(hurtbox).ok()?;
+    let hitbox_transform = world.get::<&Transform>(hitbox).ok()?;
+
+    let hitbox_colliders = hitbox_ref.active_scaled_colliders();
+
+    hurtbox_ref
+        .colliders
+        .iter()
+        .filter(|collider| collider.name.is_some())
+        .find(|collider| {
+            hitbox_colliders.iter().any(|hitbox_collider| {
+                collider.intersects(&hurtbox_transform, hitbox_collider, &hitbox_transform)
+            })
+        })
+        .map(|collider| collider.name.clone())
+        .flatten()
+}
+
+/// Advances the timers of hurtboxes, using the delta of the entity that owns them.
+/// Hurtboxes without any timers are skipped.
+pub(crate) fn hurtbox_system(host: &mut impl HitmeHost, world: &mut World, config: &HitmeConfig) {
+    let timed_hurtboxes = world
+        .query::<&Hurtbox>()
+        .iter()
+        .filter_map(|(id, hurtbox)| hurtbox.has_timers().then(|| id))
+        .collect::<Vec<Entity>>();
+
+    for id in timed_hurtboxes {
+        let owner = get_hurtbox_owner(world, id).unwrap_or(id);
+        let delta = config.get_host_delta_for_entity(host, world, owner);
+        world.get::<&mut Hurtbox>(id).ok().map(|mut hurtbox| {
+            hurtbox.advance_timers(delta);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use emerald::{toml::Value, Group, Transform, Translation, World};
+
+    use crate::collision_tests::spawn_hurtbox_set;
+    use crate::hitboxes::Hitbox;
+    use crate::hurtboxes::{
+        despawn_hurtbox_set, get_hurtbox_facing, get_hurtboxes_for_owner, get_struck_collider_name,
+        CapsuleAxis, ColliderShape, Hurtbox, HurtboxSet, RectCollider,
+    };
+    use crate::tracker::track_targets;
+    use crate::Facing;
+
+    fn load_hurtbox(world: &mut World, toml: &str) -> Hurtbox {
+        let parent_set = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(toml).unwrap();
+        Hurtbox::from_toml(&value, parent_set).unwrap()
+    }
+
+    #[test]
+    fn rotation_tracking_hurtbox_colliders_turn_with_the_owner() {
+        let mut world = World::new();
+        let owner = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                track_rotation = true
+
+                [[hurtboxes]]
+                active = true
+                colliders = [{ width = 4.0, height = 8.0, translation = { x = 10.0, y = 0.0 } }]
+            "#,
+        )
+        .unwrap();
+        let set = HurtboxSet::from_toml(&mut world, &value, owner, Group::GROUP_1, Group::GROUP_2)
+            .unwrap();
+        let hurtbox = set.hurtboxes[0];
+        world.insert_one(owner, set).unwrap();
+
+        world.get::<&mut Transform>(owner).unwrap().rotation = std::f32::consts::FRAC_PI_2;
+        track_targets(&mut world, 0.016);
+
+        assert_eq!(
+            world.get::<&Transform>(hurtbox).unwrap().rotation,
+            std::f32::consts::FRAC_PI_2
+        );
+        let handle = world.get::<&Hurtbox>(hurtbox).unwrap().physics_colliders()[0].0;
+        let mut physics = world.physics();
+        let pose = *physics
+            .collider_mut(handle)
+            .unwrap()
+            .position_wrt_parent()
+            .unwrap();
+        assert!(pose.translation.x.abs() < 0.0001);
+        assert!((pose.translation.y - 10.0).abs() < 0.0001);
+        assert!((pose.rotation.angle() - std::f32::consts::FRAC_PI_2).abs() < 0.0001);
+    }
+
+    #[test]
+    fn hurtboxes_of_left_facing_owners_face_left() {
+        let mut world = World::new();
+        let (owner, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        assert_eq!(get_hurtbox_facing(&world, hurtbox).unwrap().x, 1.0);
+
+        world.insert_one(owner, Facing(-1.0)).unwrap();
+        assert_eq!(get_hurtbox_facing(&world, hurtbox).unwrap().x, -1.0);
+    }
+
+    #[test]
+    fn owner_hurtboxes_include_set_and_standalone_hurtboxes() {
+        let mut world = World::new();
+        let (owner, set_hurtbox) = spawn_hurtbox_set(&mut world, None);
+        let value = emerald::toml::from_str::<Value>("active = true").unwrap();
+        let standalone = Hurtbox::from_toml(&value, owner).unwrap();
+        let standalone = world.spawn((standalone,));
+        let (other_owner, other_hurtbox) = spawn_hurtbox_set(&mut world, None);
+
+        assert_eq!(
+            get_hurtboxes_for_owner(&world, owner),
+            vec![set_hurtbox, standalone]
+        );
+        assert_eq!(
+            get_hurtboxes_for_owner(&world, other_owner),
+            vec![other_hurtbox]
+        );
+        assert!(get_hurtboxes_for_owner(&world, standalone).is_empty());
+    }
+
+    #[test]
+    fn named_timers_count_down_and_expire() {
+        let mut world = World::new();
+        let mut hurtbox = load_hurtbox(&mut world, "active = true");
+        assert!(!hurtbox.has_timers());
+
+        hurtbox.start_timer("block_stun", 0.5);
+        hurtbox.make_invulnerable(1.0);
+        assert!(hurtbox.has_timers());
+
+        hurtbox.advance_timers(0.25);
+        assert_eq!(hurtbox.get_timer_remaining("block_stun"), Some(0.25));
+        assert!(hurtbox.is_invulnerable());
+
+        hurtbox.advance_timers(0.25);
+        assert!(!hurtbox.is_timer_running("block_stun"));
+        assert!(hurtbox.is_invulnerable());
+
+        hurtbox.advance_timers(0.5);
+        assert!(!hurtbox.has_timers());
+    }
+
+    #[test]
+    fn struck_collider_name_is_resolved() {
+        let mut world = World::new();
+        let hurtbox = load_hurtbox(
+            &mut world,
+            r#"
+                [[colliders]]
+                name = "head"
+                width = 10.0
+                height = 10.0
+                translation = { x = 0.0, y = 20.0 }
+
+                [[colliders]]
+                name = "torso"
+                width = 10.0
+                height = 20.0
+            "#,
+        );
+        let hurtbox = world.spawn((hurtbox, Transform::default()));
+
+        let parent_set = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>("colliders = [{ width = 4.0, height = 4.0 }]")
+            .unwrap();
+        let hitbox = Hitbox::from_toml(&mut world, &value, parent_set).unwrap();
+        let hitbox = world.spawn((
+            hitbox,
+            Transform::from_translation(Translation::new(0.0, 22.0)),
+        ));
+
+        assert_eq!(
+            get_struck_collider_name(&world, hitbox, hurtbox),
+            Some(String::from("head"))
+        );
+    }
+
+    #[test]
+    fn despawning_a_hurtbox_set_despawns_its_hurtboxes() {
+        let mut world = World::new();
+        let (owner, hurtbox) = spawn_hurtbox_set(&mut world, None);
+
+        assert_eq!(despawn_hurtbox_set(&mut world, owner), 1);
+        assert!(!world.contains(hurtbox));
+        assert!(world.contains(owner) && !world.has::<HurtboxSet>(owner));
+    }
+
+    #[test]
+    fn hurtbox_is_visible_by_default() {
+        let mut world = World::new();
+        assert!(load_hurtbox(&mut world, "active = true").is_visible());
+        assert!(!load_hurtbox(&mut world, "visible = false").is_visible());
+    }
+
+    #[test]
+    fn damage_multiplier_defaults_to_one() {
+        let mut world = World::new();
+        assert_eq!(
+            load_hurtbox(&mut world, "active = true").damage_multiplier,
+            1.0
+        );
+        assert_eq!(
+            load_hurtbox(&mut world, "damage_multiplier = 2").damage_multiplier,
+            2.0
+        );
+    }
+
+    #[test]
+    fn invulnerability_runs_out() {
+        let mut world = World::new();
+        let mut hurtbox = load_hurtbox(&mut world, "active = true");
+        hurtbox.make_invulnerable(0.5);
+
+        hurtbox.advance_invulnerability(0.3);
+        assert!(hurtbox.is_invulnerable());
+        hurtbox.advance_invulnerability(0.2);
+        assert!(!hurtbox.is_invulnerable());
+        assert_eq!(hurtbox.invulnerable_for, None);
+    }
+
+    #[test]
+    fn armor_breaks_on_last_point() {
+        let mut world = World::new();
+        let mut hurtbox = load_hurtbox(&mut world, "armor_hits = 2");
+
+        assert!(!hurtbox.consume_armor());
+        assert!(hurtbox.consume_armor());
+        assert!(!hurtbox.consume_armor());
+        assert_eq!(hurtbox.armor_hits, Some(0));
+    }
+
+    #[test]
+    fn collider_without_shape_is_rect() {
+        let value = emerald::toml::from_str::<Value>("width = 4.0\nheight = 2.0").unwrap();
+        let collider = RectCollider::from_toml(&value).unwrap();
+
+        assert!(matches!(
+            collider.shape,
+            ColliderShape::Rect {
+                width,
+                height
+            } if width == 4.0 && height == 2.0
+        ));
+    }
+
+    #[test]
+    fn circle_collider_reads_radius() {
+        let value = emerald::toml::from_str::<Value>("shape = \"circle\"\nradius = 8").unwrap();
+        let collider = RectCollider::from_toml(&value).unwrap();
+
+        assert!(matches!(collider.shape, ColliderShape::Circle { radius } if radius == 8.0));
+    }
+
+    #[test]
+    fn solid_colliders_are_not_sensors() {
+        for (toml, expected_sensor) in
+            [("", true), ("solid = false", true), ("solid = true", false)]
+        {
+            let toml = format!("width = 4.0\nheight = 4.0\n{}", toml);
+            let value = emerald::toml::from_str::<Value>(&toml).unwrap();
+            let collider = RectCollider::from_toml(&value).unwrap();
+
+            assert_eq!(
+                collider.to_collider_builder().build().is_sensor(),
+                expected_sensor
+            );
+        }
+    }
+
+    #[test]
+    fn circle_collider_requires_radius() {
+        let value = emerald::toml::from_str::<Value>("shape = \"circle\"").unwrap();
+
+        assert!(RectCollider::from_toml(&value).is_err());
+    }
+
+    #[test]
+    fn triangle_hitbox_collides_with_overlapping_hurtbox() {
+        let mut world = World::new();
+        let parent_set = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                active = true
+
+                [[colliders]]
+                shape = "polygon"
+                points = [{ x = 0.0, y = 0.0 }, { x = 20.0, y = 0.0 }, { x = 0.0, y = 20.0 }]
+            "#,
+        )
+        .unwrap();
+        let hitbox = Hitbox::from_toml(&mut world, &value, parent_set).unwrap();
+        let triangle = &hitbox.raw_collider_data[0];
+        assert!(
+            matches!(triangle.shape, ColliderShape::Polygon { ref points } if points.len() == 3)
+        );
+
+        let hurtbox = load_hurtbox(&mut world, "colliders = [{ width = 4.0, height = 4.0 }]");
+        let overlapping = Transform::from_translation(Translation::new(4.0, 4.0));
+        let outside = Transform::from_translation(Translation::new(18.0, 18.0));
+
+        assert!(triangle.intersects(&Transform::default(), &hurtbox.colliders[0], &overlapping));
+        assert!(!triangle.intersects(&Transform::default(), &hurtbox.colliders[0], &outside));
+    }
+
+    #[test]
+    fn degenerate_polygon_is_an_error() {
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                shape = "polygon"
+                points = [{ x = 0.0, y = 0.0 }, { x = 1.0, y = 1.0 }, { x = 2.0, y = 2.0 }]
+            "#,
+        )
+        .unwrap();
+
+        assert!(RectCollider::from_toml(&value).is_err());
+    }
+
+    #[test]
+    fn self_intersecting_polygon_is_an_error() {
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                shape = "polygon"
+                points = [
+                    { x = 0.0, y = 10.0 },
+                    { x = 6.0, y = -8.0 },
+                    { x = -9.5, y = 3.0 },
+                    { x = 9.5, y = 3.0 },
+                    { x = -6.0, y = -8.0 },
+                ]
+            "#,
+        )
+        .unwrap();
+
+        assert!(RectCollider::from_toml(&value).is_err());
+    }
+
+    #[test]
+    fn capsule_collider_reads_both_axes() {
+        for (axis_key, expected_axis) in [("y", CapsuleAxis::Y), ("x", CapsuleAxis::X)] {
+            let toml = format!(
+                "shape = \"capsule\"\nhalf_height = 6.0\nradius = 2.0\naxis = \"{}\"",
+                axis_key
+            );
+            let value = emerald::toml::from_str::<Value>(&toml).unwrap();
+            let collider = RectCollider::from_toml(&value).unwrap();
+
+            assert!(matches!(
+                collider.shape,
+                ColliderShape::Capsule {
+                    half_height,
+                    radius,
+                    axis,
+                } if half_height == 6.0 && radius == 2.0 && axis == expected_axis
+            ));
+        }
+    }
+
+    #[test]
+    fn capsule_axis_defaults_to_y() {
+        let value =
+            emerald::toml::from_str::<Value>("shape = \"capsule\"\nhalf_height = 6\nradius = 2")
+                .unwrap();
+        let collider = RectCollider::from_toml(&value).unwrap();
+
+        assert!(matches!(
+            collider.shape,
+            ColliderShape::Capsule {
+                axis: CapsuleAxis::Y,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn collider_rotation_defaults_to_zero() {
+        let value = emerald::toml::from_str::<Value>("width = 4.0\nheight = 2.0").unwrap();
+        assert_eq!(RectCollider::from_toml(&value).unwrap().rotation, 0.0);
+
+        let value = emerald::toml::from_str::<Value>("width = 4.0\nrotation = 0.5").unwrap();
+        assert_eq!(RectCollider::from_toml(&value).unwrap().rotation, 0.5);
+    }
+}
@@ -1,21 +1,147 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::atomic::{AtomicU8, Ordering as AtomicOrdering},
+};
 
-use emerald::{toml::Value, Emerald, EmeraldError, Entity, World, WorldMerge};
-use hitboxes::{get_all_active_hitboxes, get_hitbox_owner, hitbox_system, Hitbox, HitboxSet};
-use hurtboxes::{get_colliding_active_hurtboxes, get_hurtbox_owner, Hurtbox, HurtboxSet};
+use active_box_index::merge_active_box_indexes;
+use combat_pause::is_combat_paused;
+use combo::{
+    combo_system, get_combo_scaling, register_attacker_combo_hit, register_combo_hit,
+    AttackerComboTracker, ComboTracker,
+};
+use emerald::{
+    serde::de::DeserializeOwned, toml::Value, Emerald, EmeraldError, Entity, Transform, Vector2,
+    World, WorldMerge,
+};
+use event_buffer::EventBuffer;
+use hitboxes::{
+    cancel_all_sequences, deactivate_all_hitboxes, get_all_active_hitboxes, get_hit_group_hitboxes,
+    get_hitbox_on_hit, get_hitbox_owner, get_hitboxes_for_owner, record_hitbox_translations,
+    refresh_all_hitboxes, run_hitbox_system, update_hitbox_overlaps, Hitbox, HitboxSet,
+    StatusEffect,
+};
+use host::HitmeHost;
+use hurtboxes::{
+    get_all_active_hurtboxes, get_hurtbox_block_reduction, get_hurtbox_owner,
+    get_overlapping_active_hurtboxes, get_struck_collider_name, hurtbox_system, Hurtbox,
+    HurtboxSet,
+};
+use overlap::{OverlapSource, PhysicsOverlapSource};
+use pushboxes::{pushbox_system, record_pushbox_placements};
 use tracker::{tracker_system, SimpleTranslationTracker};
 
+pub mod active_box_index;
+pub mod combat_pause;
+pub mod combo;
 pub mod component_loader;
 pub mod draw;
+pub mod event_buffer;
+pub mod hit_stop;
 pub mod hitboxes;
+mod host;
 pub mod hurtboxes;
-pub(crate) mod tracker;
+pub mod overlap;
+pub mod pushboxes;
+pub mod tracker;
 
+/// Marker component on every hitbox and hurtbox entity spawned by this crate.
+/// Useful for excluding combat boxes from gameplay queries.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CombatBox;
+
+/// Which way an entity faces horizontally, 1.0 for right and -1.0 for left.
+/// Offsets and knockback are authored facing right, and mirrored for entities facing left.
+#[derive(Clone, Copy, Debug)]
+pub struct Facing(pub f32);
+
+/// Returns the horizontal facing of the entity, from its `Facing` component if it has one,
+/// otherwise from the sign of its transform's x scale. Defaults to facing right.
+pub fn get_facing(world: &World, id: Entity) -> f32 {
+    if let Ok(facing) = world.get::<&Facing>(id) {
+        return if facing.0 < 0.0 { -1.0 } else { 1.0 };
+    }
+
+    world
+        .get::<&Transform>(id)
+        .ok()
+        .map(|transform| if transform.scale.x < 0.0 { -1.0 } else { 1.0 })
+        .unwrap_or(1.0)
+}
+
+/// Which side of the victim a hit came from, relative to the way the victim faces.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HitDirection {
+    Front,
+    Back,
+}
+
+/// Returns which side of the victim the attacker is on, using their transforms and
+/// the victim's facing. An attacker directly above, below or on top of the victim
+/// is neither in front nor behind, and counts as a hit from the front.
+pub fn get_hit_direction(world: &World, attacker: Entity, victim: Entity) -> HitDirection {
+    let x = |id: Entity| {
+        world
+            .get::<&Transform>(id)
+            .ok()
+            .map(|transform| transform.translation.x)
+            .unwrap_or(0.0)
+    };
+
+    if (x(attacker) - x(victim)) * get_facing(world, victim) < 0.0 {
+        HitDirection::Back
+    } else {
+        HitDirection::Front
+    }
+}
+
+/// Returns every entity in the world that isn't a combat box.
+pub fn get_gameplay_entities(world: &World) -> Vec<Entity> {
+    world
+        .query::<()>()
+        .iter()
+        .filter_map(|(id, _)| (!world.has::<CombatBox>(id)).then(|| id))
+        .collect()
+}
+
+#[derive(Clone, Debug)]
 pub struct OnTagTriggerContext {
     pub tag: String,
     pub hitbox_set_owner: Entity,
     pub data: Value,
+
+    /// The sequence that triggered the tag
+    pub sequence_name: String,
+
+    /// The frame of the sequence the tag belongs to
+    pub frame: usize,
+
+    /// Time elapsed since the sequence started
+    pub elapsed: f32,
+}
+impl OnTagTriggerContext {
+    /// Deserializes the tag's data into a user type, ex. `{ amount = 5 }` into `Heal { amount }`.
+    pub fn data_as<T: DeserializeOwned>(&self) -> Result<T, EmeraldError> {
+        self.data
+            .clone()
+            .try_into::<T>()
+            .map_err(|e| EmeraldError::new(format!("Invalid data for tag {}: {}", self.tag, e)))
+    }
+}
+/// Two opposing hitboxes overlapping each other, ex. attacks trading.
+#[derive(Clone, Debug)]
+pub struct OnClashContext {
+    /// The owner of the first hitbox
+    pub hit_entity: Entity,
+    pub hitbox: Entity,
+
+    /// The owner of the second hitbox
+    pub other_hit_entity: Entity,
+    pub other_hitbox: Entity,
 }
+
+#[derive(Clone, Debug)]
 pub struct OnHitFilterContext {
     /// The entity that is hitting something.
     pub hit_entity: Entity,
@@ -28,8 +154,12 @@ pub struct OnHitFilterContext {
 
     /// The hitbox touching the hurtbox.
     pub hitbox: Entity,
+
+    /// Armor points the hurtbox has left before this hit, see `Hurtbox::armor_hits`.
+    pub armor_remaining: Option<u32>,
 }
 
+#[derive(Clone, Debug)]
 pub struct OnHitContext {
     /// The entity that is hitting something.
     pub hit_entity: Entity,
@@ -42,6 +172,93 @@ pub struct OnHitContext {
 
     /// The hitbox touching the hurtbox.
     pub hitbox: Entity,
+
+    /// How many consecutive hits the hurt entity has taken, including this one.
+    /// Always 0 when `HitmeConfig::combo_timeout` is not set.
+    pub combo_hits_on_target: u32,
+
+    /// Damage scaling of the hit entity's combo by this attacker, see `HitmeConfig::combo_scaling`.
+    /// Always 1.0 when `HitmeConfig::combo_timeout` is not set.
+    pub scaling: f32,
+
+    /// Custom properties of the hitbox, see `Hitbox::properties`.
+    pub hitbox_properties: HashMap<String, Value>,
+
+    /// Status effects the hitbox applies, see `Hitbox::applied_effects`.
+    pub applied_effects: Vec<StatusEffect>,
+
+    /// Whether this hit consumed the last armor point of the hurtbox, see `Hurtbox::armor_hits`.
+    pub broke_armor: bool,
+
+    /// Armor points the hurtbox has left after this hit, see `Hurtbox::armor_hits`.
+    pub armor_remaining: Option<u32>,
+
+    /// Which side of the hurt entity the hit came from, see `get_hit_direction`.
+    pub direction: HitDirection,
+
+    /// Whether the hurt entity blocked the hit, see `HurtboxSet::set_blocking`.
+    /// Blocked hits have their `damage_multiplier` reduced by the set's `block_reduction`.
+    pub blocked: bool,
+
+    /// When within the frame the hit occurred, as reported by `OverlapSource::contact_time`.
+    /// Hits are dispatched in ascending order of this time.
+    pub sub_step_time: f32,
+
+    /// The surface material of the hurtbox, see `Hurtbox::surface`.
+    pub hurtbox_surface: Option<String>,
+
+    /// For burst hitboxes, which hit of the burst this is, starting at 0.
+    pub burst_index: Option<u32>,
+
+    /// How much damage the hitbox deals, see `Hitbox::damage`.
+    pub damage: f32,
+
+    /// The knockback of the hitbox, see `Hitbox::knockback`.
+    pub knockback: Option<Vector2<f32>>,
+
+    /// Damage multiplier of the struck hurtbox, see `Hurtbox::damage_multiplier`.
+    pub damage_multiplier: f32,
+
+    /// Name of the hurtbox collider that was struck, if it has one. ex. "head"
+    pub collider_name: Option<String>,
+}
+impl OnHitContext {
+    pub fn new(hit_entity: Entity, hurt_entity: Entity, hurtbox: Entity, hitbox: Entity) -> Self {
+        Self {
+            hit_entity,
+            hurt_entity,
+            hurtbox,
+            hitbox,
+            combo_hits_on_target: 0,
+            scaling: 1.0,
+            hitbox_properties: HashMap::new(),
+            applied_effects: Vec::new(),
+            broke_armor: false,
+            armor_remaining: None,
+            direction: HitDirection::Front,
+            blocked: false,
+            sub_step_time: 0.0,
+            hurtbox_surface: None,
+            burst_index: None,
+            damage: 0.0,
+            knockback: None,
+            damage_multiplier: 1.0,
+            collider_name: None,
+        }
+    }
+}
+
+/// An active hitbox overlapping an active hurtbox it's allowed to damage,
+/// with the owners and the struck collider already resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HitCollision {
+    pub hitbox: Entity,
+    pub hitbox_owner: Entity,
+    pub hurtbox: Entity,
+    pub hurtbox_owner: Entity,
+
+    /// Name of the hurtbox collider that was struck, see `OnHitContext::collider_name`
+    pub collider_name: Option<String>,
 }
 
 pub type OnTagTriggerFn = fn(emd: &mut Emerald, world: &mut World, ctx: OnTagTriggerContext);
@@ -49,8 +266,53 @@ pub type GetDeltaFn = fn(emd: &mut Emerald, world: &World) -> f32;
 pub type GetDeltaForEntityFn = fn(emd: &mut Emerald, world: &World, id: Entity) -> f32;
 pub type OnHitFilterFn = fn(emd: &mut Emerald, world: &mut World, ctx: OnHitFilterContext) -> bool;
 pub type OnHitFn = fn(emd: &mut Emerald, world: &mut World, ctx: OnHitContext);
+pub type OnClashFn = fn(emd: &mut Emerald, world: &mut World, ctx: OnClashContext);
+
+/// Identifies a registered callback, so it can be removed later.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallbackToken(u64);
+
+/// What to do with merged hitboxes and hurtboxes whose tracked target wasn't part of the merge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrphanedTrackerMode {
+    /// Keep the boxes, marked with `OrphanedTracker`, and log a warning naming them.
+    /// They are cleaned up on the next frame, once `tracker_system` finds their target missing.
+    Warn,
+
+    /// Fail the merge, before anything is remapped.
+    Error,
+
+    /// Despawn the boxes during the merge.
+    Drop,
+}
+impl OrphanedTrackerMode {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => OrphanedTrackerMode::Error,
+            2 => OrphanedTrackerMode::Drop,
+            _ => OrphanedTrackerMode::Warn,
+        }
+    }
+}
+
+/// Marks merged boxes whose tracked target wasn't part of the merge,
+/// inserted by the merge handler in `OrphanedTrackerMode::Warn`.
+/// Useful for finding the boxes before they're cleaned up on the next frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OrphanedTracker;
 
+/// The mode the world merge handler runs with, copied from the config by `init`
+/// and by every `emd_hitme_system` tick.
+/// Merge handlers are plain functions, so the mode can't be read from the config.
+/// It is shared by every game in the process, the last config to tick wins.
+static ORPHANED_TRACKER_MODE: AtomicU8 = AtomicU8::new(OrphanedTrackerMode::Warn as u8);
+
+#[derive(Clone)]
 pub struct HitmeConfig {
+    /// When false, `emd_hitme_system` does nothing, leaving every timer and sequence as is.
+    /// Ex. pause menus and cutscenes. Defaults to true, see `set_combat_enabled`.
+    pub enabled: bool,
+
     /// An alternate method for getting delta aside from `emd.delta()`
     /// Used for calculations and hitbox sequence progression.
     pub alt_get_delta_fn: Option<GetDeltaFn>,
@@ -60,43 +322,349 @@ pub struct HitmeConfig {
     /// Ex. An entity is affected by a time slow effect, and progresses slower than usual.
     pub alt_get_delta_for_entity_fn: Option<GetDeltaForEntityFn>,
 
-    /// A list of functions that filter out hits, a hit must pass all filters to succeed.
+    /// Functions that filter out hits, a hit must pass all filters to succeed.
+    /// Called in order, before the filters added with `add_hit_filter_fn`.
     pub hit_filter_fns: Vec<OnHitFilterFn>,
 
-    /// A list of callbacks to call when a hitbox successfully hits a hurtbox.
+    /// Callbacks to call when a hitbox successfully hits a hurtbox.
+    /// Called in order, before the callbacks added with `add_on_hit_fn`.
     pub on_hit_fns: Vec<OnHitFn>,
 
-    tag_handlers_by_name: HashMap<String, OnTagTriggerFn>,
+    /// Filters added with `add_hit_filter_fn`, removable by token
+    registered_hit_filter_fns: Vec<(CallbackToken, OnHitFilterFn)>,
+
+    /// Callbacks added with `add_on_hit_fn`, removable by token
+    registered_on_hit_fns: Vec<(CallbackToken, OnHitFn)>,
+
+    /// Callbacks to call when opposing hitboxes overlap, once per pair of hitboxes.
+    /// Only hitboxes with `clashes = true` take part, see `Hitbox::clashes`.
+    /// Clashing hitboxes keep going unless a callback deactivates them.
+    on_clash_fns: Vec<(CallbackToken, OnClashFn)>,
+
+    next_callback_token: u64,
+
+    /// How long an entity can go without being hit before its combo resets.
+    /// Combos are not tracked when this is `None`.
+    pub combo_timeout: Option<f32>,
+
+    /// Damage scaling applied again for each hit of a combo after the first,
+    /// ex. 0.9 scales the second hit to 90% and the third to 81%. Defaults to 1.0
+    /// Skipped when `combo_timeout` is `None`, every hit is then scaled by 1.0
+    pub combo_scaling: f32,
+
+    /// The lowest scaling a combo can reach, see `OnHitContext::scaling`.
+    pub combo_min_scaling: f32,
+
+    /// Cooldown used by hitboxes that don't define `cooldown_per_entity`.
+    /// Defaults to `None`, hitting again on every tick.
+    pub default_cooldown_per_entity: Option<f32>,
+
+    /// When true, an entity can only be hit once per frame, no matter how many attackers reach it.
+    /// Simultaneous hits are resolved in a stable order, so the same hit wins every time.
+    pub single_hit_per_target_per_frame: bool,
+
+    /// Animation frames per second, used for sequence frames timed in frame counts. Defaults to 60.
+    pub frames_per_second: f32,
+
+    tag_handlers_by_name: HashMap<String, Vec<OnTagTriggerFn>>,
     tag_handlers: Vec<OnTagTriggerFn>,
+
+    /// How the world merge handler treats merged boxes tracking entities that weren't merged.
+    /// Picked up by `init` and every `emd_hitme_system` tick, so changes apply to later merges.
+    /// Shared by every game in the process, see `ORPHANED_TRACKER_MODE`.
+    pub orphaned_tracker_mode: OrphanedTrackerMode,
+
+    /// Per entity deltas calculated during the current frame
+    delta_cache: RefCell<HashMap<Entity, f32>>,
+
+    /// Delta used for everything during the current frame, see `emd_hitme_system_with_delta`
+    delta_override: Option<f32>,
 }
 impl HitmeConfig {
     pub fn get_delta(&self, emd: &mut Emerald, world: &World) -> f32 {
-        self.alt_get_delta_fn
-            .map(|f| f(emd, world))
-            .unwrap_or(emd.delta())
+        self.get_host_delta(emd, world)
+    }
+
+    pub(crate) fn get_host_delta(&self, host: &mut impl HitmeHost, world: &World) -> f32 {
+        if let Some(delta) = self.delta_override {
+            return delta;
+        }
+
+        match self.alt_get_delta_fn {
+            Some(f) => host.call_delta_fn(f, world),
+            None => host.frame_delta(),
+        }
     }
 
+    /// Returns the delta for the given entity, 0 while it's frozen by the `HitStop` resource
+    /// or its combat is paused, see `pause_entity_combat`.
+    /// The result is cached for the rest of the frame, so the delta function runs at most once
+    /// per entity during `emd_hitme_system`.
     pub fn get_delta_for_entity(&self, emd: &mut Emerald, world: &World, id: Entity) -> f32 {
-        self.alt_get_delta_for_entity_fn
-            .map(|f| f(emd, world, id))
-            .unwrap_or(emd.delta())
+        self.get_host_delta_for_entity(emd, world, id)
+    }
+
+    pub(crate) fn get_host_delta_for_entity(
+        &self,
+        host: &mut impl HitmeHost,
+        world: &World,
+        id: Entity,
+    ) -> f32 {
+        let frozen = host
+            .hit_stop()
+            .map(|hit_stop| hit_stop.is_frozen_with_owner(world, id))
+            .unwrap_or(false);
+
+        self.get_delta_for_entity_with(world, id, frozen, || {
+            match self.alt_get_delta_for_entity_fn {
+                Some(f) => host.call_delta_for_entity_fn(f, world, id),
+                None => host.frame_delta(),
+            }
+        })
+    }
+
+    /// `get_delta_for_entity`, given whether the entity is frozen,
+    /// and the delta function to call when nothing else decides the delta.
+    pub(crate) fn get_delta_for_entity_with(
+        &self,
+        world: &World,
+        id: Entity,
+        frozen: bool,
+        delta_fn: impl FnOnce() -> f32,
+    ) -> f32 {
+        if frozen || is_combat_paused(world, id) {
+            return 0.0;
+        }
+
+        if let Some(delta) = self.delta_override {
+            return delta;
+        }
+
+        if let Some(delta) = self.delta_cache.borrow().get(&id) {
+            return *delta;
+        }
+
+        let delta = delta_fn();
+        self.delta_cache.borrow_mut().insert(id, delta);
+
+        delta
+    }
+
+    pub fn add_on_hit_fn(&mut self, on_hit_fn: OnHitFn) -> CallbackToken {
+        let token = self.next_callback_token();
+        self.registered_on_hit_fns.push((token, on_hit_fn));
+
+        token
+    }
+
+    /// Removes the on hit callback added with the given token, returns true if it was found.
+    pub fn remove_on_hit_fn(&mut self, token: CallbackToken) -> bool {
+        let len = self.registered_on_hit_fns.len();
+        self.registered_on_hit_fns.retain(|(t, _)| *t != token);
+
+        self.registered_on_hit_fns.len() != len
+    }
+
+    /// Removes every on hit callback, including those pushed to `on_hit_fns` directly.
+    pub fn clear_on_hit_fns(&mut self) {
+        self.on_hit_fns.clear();
+        self.registered_on_hit_fns.clear();
+    }
+
+    pub fn add_hit_filter_fn(&mut self, filter_fn: OnHitFilterFn) -> CallbackToken {
+        let token = self.next_callback_token();
+        self.registered_hit_filter_fns.push((token, filter_fn));
+
+        token
+    }
+
+    /// Removes the hit filter added with the given token, returns true if it was found.
+    pub fn remove_hit_filter_fn(&mut self, token: CallbackToken) -> bool {
+        let len = self.registered_hit_filter_fns.len();
+        self.registered_hit_filter_fns.retain(|(t, _)| *t != token);
+
+        self.registered_hit_filter_fns.len() != len
+    }
+
+    /// Removes every hit filter, including those pushed to `hit_filter_fns` directly.
+    pub fn clear_hit_filter_fns(&mut self) {
+        self.hit_filter_fns.clear();
+        self.registered_hit_filter_fns.clear();
+    }
+
+    /// The on hit callbacks, in the order they are called.
+    pub(crate) fn on_hit_callbacks(&self) -> Vec<OnHitFn> {
+        self.on_hit_fns
+            .iter()
+            .cloned()
+            .chain(self.registered_on_hit_fns.iter().map(|(_, f)| *f))
+            .collect()
+    }
+
+    /// The hit filters, in the order they are called.
+    pub(crate) fn hit_filters(&self) -> Vec<OnHitFilterFn> {
+        self.hit_filter_fns
+            .iter()
+            .cloned()
+            .chain(self.registered_hit_filter_fns.iter().map(|(_, f)| *f))
+            .collect()
+    }
+
+    pub fn add_on_clash_fn(&mut self, on_clash_fn: OnClashFn) -> CallbackToken {
+        let token = self.next_callback_token();
+        self.on_clash_fns.push((token, on_clash_fn));
+
+        token
+    }
+
+    /// Removes the clash callback added with the given token, returns true if it was found.
+    pub fn remove_on_clash_fn(&mut self, token: CallbackToken) -> bool {
+        let len = self.on_clash_fns.len();
+        self.on_clash_fns.retain(|(t, _)| *t != token);
+
+        self.on_clash_fns.len() != len
+    }
+
+    pub fn clear_on_clash_fns(&mut self) {
+        self.on_clash_fns.clear();
+    }
+
+    /// The clash callbacks, in the order they are called.
+    pub(crate) fn on_clash_callbacks(&self) -> Vec<OnClashFn> {
+        self.on_clash_fns.iter().map(|(_, f)| *f).collect()
+    }
+
+    fn next_callback_token(&mut self) -> CallbackToken {
+        let token = CallbackToken(self.next_callback_token);
+        self.next_callback_token += 1;
+
+        token
+    }
+
+    /// Returns the handlers for all tags, followed by the handlers registered for the given tag.
+    pub(crate) fn get_tag_handlers(&self, tag: &str) -> Vec<OnTagTriggerFn> {
+        let mut handlers = self.tag_handlers.clone();
+        if let Some(named_handlers) = self.tag_handlers_by_name.get(tag) {
+            handlers.extend(named_handlers);
+        }
+
+        handlers
+    }
+
+    fn clear_delta_cache(&self) {
+        self.delta_cache.borrow_mut().clear();
+    }
+
+    /// Copies the settings the systems read during a frame, leaving the callbacks behind.
+    /// Callbacks are looked up in the resource as they're called, see `HitmeHost`.
+    fn frame_copy(&self) -> Self {
+        Self {
+            enabled: self.enabled,
+            alt_get_delta_fn: self.alt_get_delta_fn,
+            alt_get_delta_for_entity_fn: self.alt_get_delta_for_entity_fn,
+            combo_timeout: self.combo_timeout,
+            combo_scaling: self.combo_scaling,
+            combo_min_scaling: self.combo_min_scaling,
+            default_cooldown_per_entity: self.default_cooldown_per_entity,
+            single_hit_per_target_per_frame: self.single_hit_per_target_per_frame,
+            frames_per_second: self.frames_per_second,
+            orphaned_tracker_mode: self.orphaned_tracker_mode,
+            ..Self::default()
+        }
     }
 }
 impl Default for HitmeConfig {
     fn default() -> Self {
         Self {
+            enabled: true,
             alt_get_delta_fn: Default::default(),
             alt_get_delta_for_entity_fn: Default::default(),
             tag_handlers: Vec::new(),
             tag_handlers_by_name: HashMap::new(),
             hit_filter_fns: Vec::new(),
             on_hit_fns: Vec::new(),
+            registered_hit_filter_fns: Vec::new(),
+            registered_on_hit_fns: Vec::new(),
+            on_clash_fns: Vec::new(),
+            next_callback_token: 0,
+            combo_timeout: None,
+            combo_scaling: 1.0,
+            combo_min_scaling: 0.0,
+            default_cooldown_per_entity: None,
+            single_hit_per_target_per_frame: false,
+            frames_per_second: 60.0,
+            delta_cache: RefCell::new(HashMap::new()),
+            delta_override: None,
+            orphaned_tracker_mode: OrphanedTrackerMode::Warn,
+        }
+    }
+}
+
+/// Builds a `HitmeConfig`, including the handlers that can't be set through public fields.
+#[derive(Default)]
+pub struct HitmeConfigBuilder {
+    config: HitmeConfig,
+}
+impl HitmeConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            config: HitmeConfig::default(),
         }
     }
+
+    pub fn with_delta_fn(mut self, get_delta_fn: GetDeltaFn) -> Self {
+        self.config.alt_get_delta_fn = Some(get_delta_fn);
+        self
+    }
+
+    pub fn with_delta_for_entity_fn(mut self, get_delta_fn: GetDeltaForEntityFn) -> Self {
+        self.config.alt_get_delta_for_entity_fn = Some(get_delta_fn);
+        self
+    }
+
+    pub fn add_hit_filter(mut self, filter_fn: OnHitFilterFn) -> Self {
+        self.config.add_hit_filter_fn(filter_fn);
+        self
+    }
+
+    pub fn add_on_hit(mut self, on_hit_fn: OnHitFn) -> Self {
+        self.config.add_on_hit_fn(on_hit_fn);
+        self
+    }
+
+    pub fn add_on_clash(mut self, on_clash_fn: OnClashFn) -> Self {
+        self.config.add_on_clash_fn(on_clash_fn);
+        self
+    }
+
+    /// Adds a handler called for every tag.
+    pub fn add_tag_handler(mut self, handler: OnTagTriggerFn) -> Self {
+        self.config.tag_handlers.push(handler);
+        self
+    }
+
+    pub fn add_tag_handler_by_name<T: Into<String>>(
+        mut self,
+        tag: T,
+        handler: OnTagTriggerFn,
+    ) -> Self {
+        self.config
+            .tag_handlers_by_name
+            .entry(tag.into())
+            .or_default()
+            .push(handler);
+        self
+    }
+
+    pub fn build(self) -> HitmeConfig {
+        self.config
+    }
 }
 
 pub fn init(emd: &mut Emerald, config: HitmeConfig) {
+    set_merge_orphaned_tracker_mode(config.orphaned_tracker_mode);
     emd.resources().insert(config);
+    emd.resources().insert(EventBuffer::new());
     emd.loader().add_world_merge_handler(merge_handler);
 }
 
@@ -105,213 +673,1809 @@ pub fn add_on_tag_trigger_by_name<T: Into<String>>(
     tag: T,
     handler: OnTagTriggerFn,
 ) {
+    emd.resources().get_mut::<HitmeConfig>().map(|config| {
+        config
+            .tag_handlers_by_name
+            .entry(tag.into())
+            .or_default()
+            .push(handler)
+    });
+}
+
+/// Removes every handler registered for the given tag with `add_on_tag_trigger_by_name`.
+pub fn remove_on_tag_triggers_by_name(emd: &mut Emerald, tag: &str) {
+    emd.resources()
+        .get_mut::<HitmeConfig>()
+        .map(|config| config.tag_handlers_by_name.remove(tag));
+}
+/// Enables or disables all combat processing, see `HitmeConfig::enabled`.
+pub fn set_combat_enabled(emd: &mut Emerald, enabled: bool) {
     emd.resources()
         .get_mut::<HitmeConfig>()
-        .map(|config| config.tag_handlers_by_name.insert(tag.into(), handler));
+        .map(|config| config.enabled = enabled);
 }
+
 pub fn add_on_tag_trigger(emd: &mut Emerald, handler: OnTagTriggerFn) {
     emd.resources()
         .get_mut::<HitmeConfig>()
         .map(|config| config.tag_handlers.push(handler));
 }
-pub fn emd_hitme_system(emd: &mut Emerald, world: &mut World) {
-    let config = emd.resources().remove::<HitmeConfig>().unwrap();
-    cleanup_system(world, &config);
-    hitbox_system(emd, world, &config).unwrap();
-    let collisions = get_active_hitbox_to_active_hurtbox_collisions(world);
-    collisions.into_iter().for_each(|(hitbox_id, hurtboxes)| {
-        hurtboxes.into_iter().for_each(|hurtbox| {
-            config.on_hit_fns.iter().for_each(|f| {
-                get_hurtbox_owner(world, hurtbox).map(|hurtbox_owner| {
-                    get_hitbox_owner(world, hitbox_id).map(|hitbox_owner| {
-                        let can_damage_hurtbox_owner = world
-                            .get::<&Hitbox>(hitbox_id)
-                            .ok()
-                            .map(|h| h.can_damage_entity(&hurtbox_owner))
-                            .unwrap_or(false);
-
-                        let hit = !config.hit_filter_fns.iter().any(|filter_fn| {
-                            !filter_fn(
-                                emd,
-                                world,
-                                OnHitFilterContext {
-                                    hit_entity: hitbox_owner,
-                                    hurt_entity: hurtbox_owner,
-                                    hurtbox: hurtbox,
-                                    hitbox: hitbox_id,
-                                },
-                            )
-                        });
-
-                        if hit && can_damage_hurtbox_owner {
-                            f(
-                                emd,
-                                world,
-                                OnHitContext {
-                                    hit_entity: hitbox_owner,
-                                    hurt_entity: hurtbox_owner,
-                                    hurtbox,
-                                    hitbox: hitbox_id,
-                                },
-                            );
-                            add_to_damaged_list(world, hitbox_id, hurtbox_owner);
-                        }
-                    });
-                });
-            });
-        });
-    });
+/// Runs the combat systems for the frame.
+/// Errors if `init` was never called, or if any of the systems fail.
+pub fn emd_hitme_system(emd: &mut Emerald, world: &mut World) -> Result<(), EmeraldError> {
+    run_frame(emd, world, None)
+}
+
+/// Runs the combat systems using the given delta for all progression,
+/// instead of `emd.delta()` or the alternate delta functions.
+/// Useful for fixed timestep loops and deterministic tests.
+pub fn emd_hitme_system_with_delta(
+    emd: &mut Emerald,
+    world: &mut World,
+    delta: f32,
+) -> Result<(), EmeraldError> {
+    run_frame(emd, world, Some(delta))
+}
 
-    tracker_system(emd, world, &config);
+/// Runs the systems with a copy of the config's settings, so the callbacks can be handed
+/// `&mut Emerald`. The resource stays in place, so callbacks can read and change it.
+/// Changes to the settings take effect on the next frame, changes to the callbacks right away.
+fn run_frame(
+    host: &mut impl HitmeHost,
+    world: &mut World,
+    delta_override: Option<f32>,
+) -> Result<(), EmeraldError> {
+    let mut config = host
+        .config()
+        .map(|config| config.frame_copy())
+        .ok_or_else(|| {
+            EmeraldError::new(
+                "emd_hitme: HitmeConfig resource is missing, call emd_hitme::init first",
+            )
+        })?;
+    config.delta_override = delta_override;
 
-    emd.resources().insert(config);
+    begin_frame(&config, host.event_buffer());
+    set_merge_orphaned_tracker_mode(config.orphaned_tracker_mode);
+    run_if_enabled(&config, || run_systems(host, world, &config))
 }
 
-pub fn add_to_damaged_list(world: &mut World, hitbox_id: Entity, damaged_entity: Entity) {
-    world.get::<&mut Hitbox>(hitbox_id).ok().map(|mut h| {
-        h.add_damaged_entity(damaged_entity);
-    });
+/// Clears what the last tick left behind, the buffered events and the cached deltas.
+/// Runs every tick, even when combat is disabled.
+fn begin_frame(config: &HitmeConfig, buffer: Option<&mut EventBuffer>) {
+    buffer.map(|buffer| buffer.clear());
+    config.clear_delta_cache();
 }
 
-fn cleanup_system(world: &mut World, config: &HitmeConfig) {
-    let mut to_despawn = Vec::new();
-    for (id, h) in world.query::<&Hitbox>().iter() {
-        if !world.contains(h.parent_set) {
-            to_despawn.push(id);
-        }
-    }
-    for (id, h) in world.query::<&Hurtbox>().iter() {
-        if !world.contains(h.parent_set) {
-            to_despawn.push(id);
-        }
+/// Runs the systems unless combat is disabled, see `HitmeConfig::enabled`.
+/// Disabled frames leave every timer and sequence untouched.
+fn run_if_enabled(
+    config: &HitmeConfig,
+    systems: impl FnOnce() -> Result<(), EmeraldError>,
+) -> Result<(), EmeraldError> {
+    if config.enabled {
+        systems()
+    } else {
+        Ok(())
     }
-
-    to_despawn.into_iter().for_each(|id| {
-        world.despawn(id).ok();
-    });
 }
 
-fn merge_handler(
-    new_world: &mut World,
-    _old_world: &mut World,
-    entity_map: &mut HashMap<Entity, Entity>,
-    ctx: &WorldMerge,
+fn run_systems(
+    host: &mut impl HitmeHost,
+    world: &mut World,
+    config: &HitmeConfig,
 ) -> Result<(), EmeraldError> {
-    for (old_entity, new_entity) in entity_map.iter() {
-        new_world
-            .get::<&mut SimpleTranslationTracker>(new_entity.clone())
-            .ok()
-            .map(|mut s| {
-                entity_map.get(&s.target).map(|e| {
-                    s.target = e.clone();
-                });
-            });
-
-        new_world
-            .get::<&mut Hitbox>(new_entity.clone())
-            .ok()
-            .map(|mut h| {
-                entity_map
-                    .get(&h.parent_set)
-                    .map(|e| h.parent_set = e.clone())
-            });
-        new_world
-            .get::<&mut Hurtbox>(new_entity.clone())
-            .ok()
-            .map(|mut h| {
-                entity_map
-                    .get(&h.parent_set)
-                    .map(|e| h.parent_set = e.clone())
-            });
-        new_world
-            .get::<&mut HurtboxSet>(new_entity.clone())
-            .ok()
-            .map(|mut hurtbox_set| {
-                let old_hurtbox_ids = hurtbox_set.hurtboxes.clone();
-                hurtbox_set.hurtboxes = Vec::new();
-                old_hurtbox_ids.into_iter().for_each(|h| {
-                    entity_map.get(&h).map(|e| {
-                        hurtbox_set.hurtboxes.push(e.clone());
-                    });
-                });
-                entity_map
-                    .get(&hurtbox_set.owner)
-                    .map(|e| hurtbox_set.owner = e.clone());
-            });
-        new_world
-            .get::<&mut HitboxSet>(new_entity.clone())
-            .ok()
-            .map(|mut hitbox_set| {
-                let old_hitbox_ids = hitbox_set.hitboxes.clone();
-                hitbox_set.hitboxes = HashMap::new();
-                old_hitbox_ids.into_iter().for_each(|(name, h)| {
-                    entity_map.get(&h).map(|e| {
-                        hitbox_set.hitboxes.insert(name, e.clone());
-                    });
-                });
-                entity_map.get(&hitbox_set.owner).map(|e| {
-                    hitbox_set.owner = e.clone();
-                });
-            });
-    }
+    cleanup_system(world, config);
+    run_hitbox_system(host, world, config)?;
+    hurtbox_system(host, world, config);
+    pushbox_system(world);
+    combo_system(host, world, config);
+    clash_system(host, world);
+    hit_system(host, world, config);
+    tracker_system(host, world, config);
+    record_pushbox_placements(world);
+    hit_stop_system(host, world, config);
 
     Ok(())
 }
 
-/// Returns a map of active hitboxes and active hurtboxes they are colliding with.
-pub fn get_active_hitbox_to_active_hurtbox_collisions(
-    world: &mut World,
-) -> HashMap<Entity, Vec<Entity>> {
+/// Counts down the freezes of the `HitStop` resource by the real delta, if there is one.
+/// Runs after the other systems, so freezes made by this frame's hits start next frame.
+fn hit_stop_system(host: &mut impl HitmeHost, world: &mut World, config: &HitmeConfig) {
+    let delta = config.get_host_delta(host, world);
+    host.hit_stop().map(|hit_stop| hit_stop.advance(delta));
+}
+
+/// Resolves hits between active hitboxes and active hurtboxes, calling the on hit callbacks.
+fn hit_system(host: &mut impl HitmeHost, world: &mut World, config: &HitmeConfig) {
+    let mut handler = CallbackHitHandler { host };
+    run_hit_pass(world, config, &mut handler);
+}
+
+/// Resolves the hits of active hitboxes against what the physics engine reports overlapping,
+/// along with what continuous hitboxes swept through, see `Hitbox::continuous`.
+fn run_hit_pass(world: &mut World, config: &HitmeConfig, handler: &mut dyn HitHandler) {
     let active_hitboxes = get_all_active_hitboxes(world);
-    let mut hitbox_hurtbox_collisions: HashMap<Entity, HashSet<Entity>> = HashMap::new();
-    for hitbox_id in active_hitboxes {
-        let colliding_hurtboxes = get_colliding_active_hurtboxes(world, hitbox_id)
+    let mut overlaps = PhysicsOverlapSource::new(world, &active_hitboxes);
+    overlaps.include_swept_hurtboxes(world);
+    resolve_hits(world, config, &overlaps, &active_hitboxes, handler);
+    record_hitbox_translations(world);
+}
+
+/// Calls the clash callbacks for opposing hitboxes overlapping each other.
+fn clash_system(host: &mut impl HitmeHost, world: &mut World) {
+    let on_clash_fns = host
+        .config()
+        .map(|config| config.on_clash_callbacks())
+        .unwrap_or_default();
+    if on_clash_fns.is_empty() {
+        return;
+    }
+
+    let clashing_hitboxes = get_all_active_hitboxes(world)
+        .into_iter()
+        .filter(|id| {
+            world
+                .get::<&Hitbox>(*id)
+                .map(|hitbox| hitbox.clashes)
+                .unwrap_or(false)
+        })
+        .collect::<Vec<Entity>>();
+    if clashing_hitboxes.is_empty() {
+        return;
+    }
+
+    let overlaps = PhysicsOverlapSource::new(world, &clashing_hitboxes);
+    resolve_clashes(world, &overlaps, &clashing_hitboxes, &mut |world, ctx| {
+        on_clash_fns.iter().for_each(|f| {
+            host.call_on_clash(*f, world, ctx.clone());
+        });
+    });
+}
+
+/// Reports each pair of clashing hitboxes once, in a stable order.
+fn resolve_clashes(
+    world: &mut World,
+    overlaps: &dyn OverlapSource,
+    hitboxes: &Vec<Entity>,
+    on_clash: &mut dyn FnMut(&mut World, OnClashContext),
+) {
+    let mut clashes = get_active_hitbox_to_active_hitbox_collisions_with(world, overlaps, hitboxes)
+        .into_iter()
+        .flat_map(|(hitbox, others)| others.into_iter().map(move |other| (hitbox, other)))
+        .filter(|(hitbox, other)| hitbox.to_bits() < other.to_bits())
+        .collect::<Vec<(Entity, Entity)>>();
+    clashes.sort_by_key(|(hitbox, other)| (hitbox.to_bits(), other.to_bits()));
+
+    for (hitbox, other_hitbox) in clashes {
+        let (hit_entity, other_hit_entity) = match (
+            get_hitbox_owner(world, hitbox),
+            get_hitbox_owner(world, other_hitbox),
+        ) {
+            (Some(hit_entity), Some(other_hit_entity)) => (hit_entity, other_hit_entity),
+            _ => continue,
+        };
+
+        on_clash(
+            world,
+            OnClashContext {
+                hit_entity,
+                hitbox,
+                other_hit_entity,
+                other_hitbox,
+            },
+        );
+    }
+}
+
+/// Receives the filter and on hit calls made while resolving hits.
+/// Defaults to the callbacks of the config, but can be replaced to observe hits in tests.
+pub(crate) trait HitHandler {
+    /// Returns false if the hit should be filtered out.
+    fn filter(&mut self, world: &mut World, ctx: OnHitFilterContext) -> bool;
+    fn on_hit(&mut self, world: &mut World, ctx: OnHitContext);
+}
+
+/// Calls the callbacks of the config resource, looked up for every hit
+/// so callbacks added or removed by an earlier hit apply right away.
+struct CallbackHitHandler<'a, H: HitmeHost> {
+    host: &'a mut H,
+}
+impl<'a, H: HitmeHost> HitHandler for CallbackHitHandler<'a, H> {
+    fn filter(&mut self, world: &mut World, ctx: OnHitFilterContext) -> bool {
+        self.host
+            .config()
+            .map(|config| config.hit_filters())
+            .unwrap_or_default()
             .into_iter()
-            .filter(|hurtbox_id| {
-                let hurtbox_parent_set = world
-                    .get::<&Hurtbox>(hurtbox_id.clone())
-                    .unwrap()
-                    .parent_set
-                    .clone();
-                let hurtbox_set_owner = world
-                    .get::<&HurtboxSet>(hurtbox_parent_set)
-                    .unwrap()
-                    .owner
-                    .clone();
-
-                let hitbox_parent_set = world.get::<&Hitbox>(hitbox_id).unwrap().parent_set.clone();
-                let hitbox_set_owner = world
-                    .get::<&HitboxSet>(hitbox_parent_set)
-                    .unwrap()
-                    .owner
-                    .clone();
-
-                let can_damage_hurtbox_owner = world
-                    .get::<&Hitbox>(hitbox_id)
-                    .unwrap()
-                    .can_damage_entity(&hurtbox_set_owner);
-                let same_owner = hitbox_set_owner == hurtbox_set_owner;
-
-                !same_owner && can_damage_hurtbox_owner
-            })
-            .collect::<HashSet<Entity>>();
+            .all(|filter_fn| self.host.call_hit_filter(filter_fn, world, ctx.clone()))
+    }
+
+    fn on_hit(&mut self, world: &mut World, ctx: OnHitContext) {
+        self.host
+            .config()
+            .map(|config| hit_callbacks(world, config, ctx.hitbox))
+            .unwrap_or_default()
+            .into_iter()
+            .for_each(|f| {
+                self.host.call_on_hit(f, world, ctx.clone());
+            });
+    }
+}
+
+/// The callbacks called for a hit of the given hitbox, in order.
+/// The global on hit callbacks come first, then the hitbox's own, see `Hitbox::set_on_hit`.
+fn hit_callbacks(world: &World, config: &HitmeConfig, hitbox: Entity) -> Vec<OnHitFn> {
+    let mut callbacks = config.on_hit_callbacks();
+    callbacks.extend(get_hitbox_on_hit(world, hitbox));
+
+    callbacks
+}
+
+/// Resolves the hits between the given active hitboxes and the hurtboxes they overlap.
+fn resolve_hits(
+    world: &mut World,
+    config: &HitmeConfig,
+    overlaps: &dyn OverlapSource,
+    active_hitboxes: &Vec<Entity>,
+    handler: &mut dyn HitHandler,
+) {
+    let mut hits = get_hit_collisions_with(world, overlaps)
+        .into_iter()
+        .map(|collision| {
+            let sub_step_time = overlaps.contact_time(collision.hitbox, collision.hurtbox);
+            (collision, sub_step_time)
+        })
+        .collect::<Vec<(HitCollision, f32)>>();
 
-        if !hitbox_hurtbox_collisions.contains_key(&hitbox_id) {
-            hitbox_hurtbox_collisions.insert(hitbox_id, HashSet::new());
+    // Resolve in the order hits occurred, ties are broken in a stable order
+    // so the winner of simultaneous hits is deterministic.
+    hits.sort_by(|(a, a_time), (b, b_time)| {
+        a_time
+            .partial_cmp(b_time)
+            .unwrap_or(Ordering::Equal)
+            .then(a.hitbox.to_bits().cmp(&b.hitbox.to_bits()))
+            .then(a.hurtbox.to_bits().cmp(&b.hurtbox.to_bits()))
+    });
+    retain_highest_priority_hits(world, &mut hits);
+
+    let mut hit_this_frame = HashSet::new();
+    // Hitboxes and the entities they hit this frame, including the rest of their hit group.
+    // Hitboxes overlapping several hurtboxes of one entity must only hit it once,
+    // no matter their cooldown or hit mode.
+    let mut damaged_this_frame = HashSet::new();
+    let overlapping_owners = get_overlapping_hurtbox_owners(world, overlaps, active_hitboxes);
+
+    for (collision, sub_step_time) in hits {
+        let (hitbox_id, hurtbox) = (collision.hitbox, collision.hurtbox);
+        let (hitbox_owner, hurtbox_owner) = (collision.hitbox_owner, collision.hurtbox_owner);
+
+        if config.single_hit_per_target_per_frame && hit_this_frame.contains(&hurtbox_owner) {
+            continue;
+        }
+
+        let can_damage_hurtbox_owner = !damaged_this_frame.contains(&(hitbox_id, hurtbox_owner))
+            && world
+                .get::<&Hitbox>(hitbox_id)
+                .ok()
+                .map(|h| {
+                    h.can_damage_entity_with_default(
+                        &hurtbox_owner,
+                        config.default_cooldown_per_entity,
+                    )
+                })
+                .unwrap_or(false);
+        if !can_damage_hurtbox_owner {
+            continue;
+        }
+
+        let hit = handler.filter(
+            world,
+            OnHitFilterContext {
+                hit_entity: hitbox_owner,
+                hurt_entity: hurtbox_owner,
+                hurtbox: hurtbox,
+                hitbox: hitbox_id,
+                armor_remaining: world
+                    .get::<&Hurtbox>(hurtbox)
+                    .ok()
+                    .map(|hurtbox| hurtbox.armor_hits)
+                    .flatten(),
+            },
+        );
+
+        if !hit {
+            continue;
         }
 
-        if let Some(collisions) = hitbox_hurtbox_collisions.get_mut(&hitbox_id) {
-            for id in colliding_hurtboxes {
-                collisions.insert(id);
+        let ctx = register_hit(world, config, &collision, sub_step_time);
+
+        handler.on_hit(world, ctx);
+        record_damage(
+            world,
+            hitbox_id,
+            hurtbox_owner,
+            config.default_cooldown_per_entity,
+        );
+        hit_this_frame.insert(hurtbox_owner);
+        damaged_this_frame.extend(
+            get_hit_group_hitboxes(world, hitbox_id)
+                .into_iter()
+                .map(|id| (id, hurtbox_owner)),
+        );
+    }
+
+    update_hitbox_overlaps(world, &overlapping_owners);
+}
+
+/// Drops hits of lower priority hitboxes, when a higher priority hitbox of the same owner
+/// hits the same entity. Hitboxes tied for the highest priority all keep their hits.
+fn retain_highest_priority_hits(world: &World, hits: &mut Vec<(HitCollision, f32)>) {
+    let priority = |hitbox: Entity| {
+        world
+            .get::<&Hitbox>(hitbox)
+            .map(|hitbox| hitbox.priority)
+            .unwrap_or(0)
+    };
+    let pair = |collision: &HitCollision| (collision.hitbox_owner, collision.hurtbox_owner);
+
+    let mut highest_priorities = HashMap::new();
+    for (collision, _) in hits.iter() {
+        let highest = highest_priorities
+            .entry(pair(collision))
+            .or_insert(i32::MIN);
+        *highest = (*highest).max(priority(collision.hitbox));
+    }
+
+    hits.retain(|(collision, _)| {
+        highest_priorities.get(&pair(collision)) == Some(&priority(collision.hitbox))
+    });
+}
+
+/// Applies the bookkeeping of a successful hit on the hurtbox,
+/// and builds the context passed to the on hit callbacks.
+fn register_hit(
+    world: &mut World,
+    config: &HitmeConfig,
+    collision: &HitCollision,
+    sub_step_time: f32,
+) -> OnHitContext {
+    let (hitbox_id, hurtbox) = (collision.hitbox, collision.hurtbox);
+    let (hitbox_owner, hurtbox_owner) = (collision.hitbox_owner, collision.hurtbox_owner);
+    let mut ctx = OnHitContext::new(hitbox_owner, hurtbox_owner, hurtbox, hitbox_id);
+    ctx.sub_step_time = sub_step_time;
+    ctx.collider_name = collision.collider_name.clone();
+
+    if let Ok(hitbox) = world.get::<&Hitbox>(hitbox_id) {
+        ctx.hitbox_properties = hitbox.properties.clone();
+        ctx.applied_effects = hitbox.applied_effects.clone();
+        ctx.burst_index = hitbox.next_burst_index(&hurtbox_owner);
+        ctx.damage = hitbox.damage;
+        ctx.knockback = hitbox.knockback.map(|knockback| {
+            Vector2::new(knockback.x * get_facing(world, hitbox_owner), knockback.y)
+        });
+    }
+
+    if let Ok(mut hurtbox) = world.get::<&mut Hurtbox>(hurtbox) {
+        ctx.broke_armor = hurtbox.consume_armor();
+        ctx.armor_remaining = hurtbox.armor_hits;
+        ctx.hurtbox_surface = hurtbox.surface.clone();
+        ctx.damage_multiplier = hurtbox.damage_multiplier;
+    }
+
+    ctx.direction = get_hit_direction(world, hitbox_owner, hurtbox_owner);
+
+    if let Some(block_reduction) = get_hurtbox_block_reduction(world, hurtbox) {
+        ctx.blocked = true;
+        ctx.damage_multiplier *= 1.0 - block_reduction.clamp(0.0, 1.0);
+    }
+
+    if config.combo_timeout.is_some() {
+        ctx.combo_hits_on_target = register_combo_hit(world, hurtbox_owner);
+        register_attacker_combo_hit(world, hitbox_owner, hurtbox_owner);
+        ctx.scaling = get_combo_scaling(world, config, hitbox_owner, hurtbox_owner);
+    }
+
+    ctx
+}
+
+/// Returns the owners of the active hurtboxes overlapping each of the given hitboxes.
+fn get_overlapping_hurtbox_owners(
+    world: &World,
+    overlaps: &dyn OverlapSource,
+    hitboxes: &Vec<Entity>,
+) -> HashMap<Entity, HashSet<Entity>> {
+    hitboxes
+        .iter()
+        .map(|hitbox_id| {
+            let owners = get_overlapping_active_hurtboxes(world, overlaps, hitbox_id.clone())
+                .into_iter()
+                .filter_map(|hurtbox| get_hurtbox_owner(world, hurtbox))
+                .collect();
+
+            (hitbox_id.clone(), owners)
+        })
+        .collect()
+}
+
+/// Returns all combat to a clean slate, ex. at the start of a new round.
+/// Cancels all sequences, deactivates and refreshes all hitboxes, and resets all combos.
+pub fn reset_all_combat(world: &mut World) {
+    cancel_all_sequences(world);
+    deactivate_all_hitboxes(world);
+    refresh_all_hitboxes(world);
+
+    for (_, tracker) in world.query::<&mut ComboTracker>().iter() {
+        tracker.reset();
+    }
+    for (_, tracker) in world.query::<&mut AttackerComboTracker>().iter() {
+        tracker.combos.clear();
+    }
+}
+
+/// Records the damaged entity against the hitbox, and every hitbox sharing its hit group.
+pub fn add_to_damaged_list(world: &mut World, hitbox_id: Entity, damaged_entity: Entity) {
+    for id in get_hit_group_hitboxes(world, hitbox_id) {
+        world.get::<&mut Hitbox>(id).ok().map(|mut h| {
+            h.add_damaged_entity(damaged_entity);
+        });
+    }
+}
+
+/// `add_to_damaged_list`, also recording the config's default cooldown on the hitboxes,
+/// see `Hitbox::can_damage_entity`.
+fn record_damage(
+    world: &mut World,
+    hitbox_id: Entity,
+    damaged_entity: Entity,
+    default_cooldown_per_entity: Option<f32>,
+) {
+    for id in get_hit_group_hitboxes(world, hitbox_id) {
+        if let Ok(mut hitbox) = world.get::<&mut Hitbox>(id) {
+            hitbox.add_damaged_entity(damaged_entity);
+            hitbox.default_cooldown_per_entity = default_cooldown_per_entity;
+        }
+    }
+}
+
+fn cleanup_system(world: &mut World, config: &HitmeConfig) {
+    let mut to_despawn = Vec::new();
+    for (id, h) in world.query::<&Hitbox>().iter() {
+        if !world.contains(h.parent_set) {
+            to_despawn.push(id);
+        }
+    }
+    for (id, h) in world.query::<&Hurtbox>().iter() {
+        if !world.contains(h.parent_set) {
+            to_despawn.push(id);
+        }
+    }
+
+    to_despawn.into_iter().for_each(|id| {
+        world.despawn(id).ok();
+    });
+}
+
+fn merge_handler(
+    new_world: &mut World,
+    _old_world: &mut World,
+    entity_map: &mut HashMap<Entity, Entity>,
+    _ctx: &WorldMerge,
+) -> Result<(), EmeraldError> {
+    let mode = OrphanedTrackerMode::from_u8(ORPHANED_TRACKER_MODE.load(AtomicOrdering::Relaxed));
+    let orphans = remap_merged_trackers(new_world, entity_map, mode)?;
+    merge_active_box_indexes(new_world, entity_map);
+
+    if mode == OrphanedTrackerMode::Warn && !orphans.is_empty() {
+        log::warn!(
+            "emd_hitme: {} merged combat boxes track entities missing from the merge: {:?}",
+            orphans.len(),
+            orphans
+        );
+    }
+
+    Ok(())
+}
+
+/// Sets the mode the world merge handler runs with, see `ORPHANED_TRACKER_MODE`.
+fn set_merge_orphaned_tracker_mode(mode: OrphanedTrackerMode) {
+    ORPHANED_TRACKER_MODE.store(mode as u8, AtomicOrdering::Relaxed);
+}
+
+/// Points the trackers and combat boxes merged into the new world at the remapped entities.
+/// Returns the merged boxes whose tracked target wasn't part of the merge,
+/// handled according to the mode. Nothing is changed when the mode fails the merge.
+pub fn remap_merged_trackers(
+    new_world: &mut World,
+    entity_map: &HashMap<Entity, Entity>,
+    mode: OrphanedTrackerMode,
+) -> Result<Vec<Entity>, EmeraldError> {
+    let orphans = entity_map
+        .values()
+        .filter(|new_entity| {
+            new_world
+                .get::<&SimpleTranslationTracker>(**new_entity)
+                .map(|tracker| !entity_map.contains_key(&tracker.target))
+                .unwrap_or(false)
+        })
+        .cloned()
+        .collect::<Vec<Entity>>();
+
+    if !orphans.is_empty() && mode == OrphanedTrackerMode::Error {
+        return Err(EmeraldError::new(format!(
+            "{} merged combat boxes track entities missing from the merge",
+            orphans.len()
+        )));
+    }
+
+    for new_entity in entity_map.values() {
+        new_world
+            .get::<&mut SimpleTranslationTracker>(new_entity.clone())
+            .ok()
+            .map(|mut s| entity_map.get(&s.target).map(|e| s.target = e.clone()));
+    }
+
+    for id in &orphans {
+        match mode {
+            OrphanedTrackerMode::Drop => {
+                new_world.despawn(id.clone()).ok();
+            }
+            _ => {
+                new_world.insert_one(id.clone(), OrphanedTracker).ok();
             }
         }
     }
 
-    hitbox_hurtbox_collisions
+    remap_merged_combat_boxes(new_world, entity_map);
+
+    Ok(orphans)
+}
+
+/// Points the merged combat boxes, and their sets, at the remapped entities of the new world.
+fn remap_merged_combat_boxes(new_world: &mut World, entity_map: &HashMap<Entity, Entity>) {
+    for (_, new_entity) in entity_map.iter() {
+        if !new_world.contains(new_entity.clone()) {
+            continue;
+        }
+
+        new_world
+            .get::<&mut Hitbox>(new_entity.clone())
+            .ok()
+            .map(|mut h| {
+                entity_map
+                    .get(&h.parent_set)
+                    .map(|e| h.parent_set = e.clone())
+            });
+        new_world
+            .get::<&mut Hurtbox>(new_entity.clone())
+            .ok()
+            .map(|mut h| {
+                entity_map
+                    .get(&h.parent_set)
+                    .map(|e| h.parent_set = e.clone())
+            });
+        new_world
+            .get::<&mut HurtboxSet>(new_entity.clone())
+            .ok()
+            .map(|mut hurtbox_set| {
+                hurtbox_set.hurtboxes = hurtbox_set
+                    .hurtboxes
+                    .iter()
+                    .filter_map(|h| entity_map.get(h).cloned())
+                    .collect();
+                entity_map
+                    .get(&hurtbox_set.owner)
+                    .map(|e| hurtbox_set.owner = e.clone());
+            });
+        new_world
+            .get::<&mut HitboxSet>(new_entity.clone())
+            .ok()
+            .map(|mut hitbox_set| {
+                let old_hitbox_ids = hitbox_set.hitboxes.clone();
+                hitbox_set.hitboxes = HashMap::new();
+                old_hitbox_ids.into_iter().for_each(|(name, h)| {
+                    entity_map.get(&h).map(|e| {
+                        hitbox_set.hitboxes.insert(name, e.clone());
+                    });
+                });
+                entity_map.get(&hitbox_set.owner).map(|e| {
+                    hitbox_set.owner = e.clone();
+                });
+            });
+    }
+}
+
+/// Returns a map of active hitboxes and active hurtboxes they are colliding with.
+pub fn get_active_hitbox_to_active_hurtbox_collisions(
+    world: &mut World,
+) -> HashMap<Entity, Vec<Entity>> {
+    let active_hitboxes = get_all_active_hitboxes(world);
+    let overlaps = PhysicsOverlapSource::new(world, &active_hitboxes);
+    get_active_hitbox_to_active_hurtbox_collisions_with(world, &overlaps)
+}
+
+/// Returns every active hitbox and active hurtbox collision, with owners resolved.
+pub fn get_hit_collisions(world: &mut World) -> Vec<HitCollision> {
+    let active_hitboxes = get_all_active_hitboxes(world);
+    let overlaps = PhysicsOverlapSource::new(world, &active_hitboxes);
+    get_hit_collisions_with(world, &overlaps)
+}
+
+/// Returns every active hitbox and active hurtbox collision, with owners resolved,
+/// using the given overlap source instead of the physics engine.
+pub fn get_hit_collisions_with(world: &World, overlaps: &dyn OverlapSource) -> Vec<HitCollision> {
+    // Each component is read once up front, rather than once per overlapping pair
+    let hurtbox_set_owners = world
+        .query::<&HurtboxSet>()
+        .iter()
+        .map(|(id, set)| (id, set.owner))
+        .collect::<HashMap<Entity, Entity>>();
+    let hitbox_sets = world
+        .query::<&HitboxSet>()
+        .iter()
+        .map(|(id, set)| (id, (set.owner, set.neutral)))
+        .collect::<HashMap<Entity, (Entity, bool)>>();
+
+    let hurtbox_owners = get_all_active_hurtboxes(world)
+        .into_iter()
+        .filter_map(|id| {
+            let hurtbox = world.get::<&Hurtbox>(id).ok()?;
+            if hurtbox.is_invulnerable() {
+                return None;
+            }
+
+            hurtbox_set_owners
+                .get(&hurtbox.parent_set)
+                .map(|owner| (id, *owner))
+        })
+        .collect::<HashMap<Entity, Entity>>();
+
+    get_all_active_hitboxes(world)
         .into_iter()
-        .map(|(hitbox_id, hurtbox_set)| {
-            let hurtboxes = hurtbox_set.into_iter().collect::<Vec<Entity>>();
-            (hitbox_id, hurtboxes)
+        .flat_map(|hitbox_id| {
+            let hitbox = match world.get::<&Hitbox>(hitbox_id) {
+                Ok(hitbox) => hitbox,
+                Err(_) => return Vec::new(),
+            };
+            let (hitbox_owner, neutral) = match hitbox_sets.get(&hitbox.parent_set) {
+                Some(hitbox_set) => *hitbox_set,
+                None => return Vec::new(),
+            };
+
+            overlaps
+                .colliding(hitbox_id)
+                .into_iter()
+                .collect::<HashSet<Entity>>()
+                .into_iter()
+                .filter_map(|hurtbox_id| {
+                    let hurtbox_owner = *hurtbox_owners.get(&hurtbox_id)?;
+                    let hurtbox = world.get::<&Hurtbox>(hurtbox_id).ok()?;
+                    let same_owner = !neutral && hitbox_owner == hurtbox_owner;
+                    let same_team = !neutral && hitbox.is_ally(&hurtbox);
+
+                    let hittable = !same_owner
+                        && !same_team
+                        && hitbox.can_damage_entity(&hurtbox_owner)
+                        && hitbox.targets_hurtbox(&hurtbox);
+
+                    hittable.then(|| HitCollision {
+                        hitbox: hitbox_id,
+                        hitbox_owner,
+                        hurtbox: hurtbox_id,
+                        hurtbox_owner,
+                        collider_name: overlaps
+                            .struck_collider(hitbox_id, hurtbox_id)
+                            .map(|handle| hurtbox.get_collider_name(handle))
+                            .unwrap_or_else(|| {
+                                get_struck_collider_name(world, hitbox_id, hurtbox_id)
+                            }),
+                    })
+                })
+                .collect::<Vec<HitCollision>>()
         })
         .collect()
 }
+
+/// Returns a map of active hitboxes and active hurtboxes they are colliding with,
+/// using the given overlap source instead of the physics engine.
+/// Every active hitbox has an entry, see `get_hit_collisions_with`.
+pub fn get_active_hitbox_to_active_hurtbox_collisions_with(
+    world: &World,
+    overlaps: &dyn OverlapSource,
+) -> HashMap<Entity, Vec<Entity>> {
+    let mut collisions = get_all_active_hitboxes(world)
+        .into_iter()
+        .map(|hitbox| (hitbox, Vec::new()))
+        .collect::<HashMap<Entity, Vec<Entity>>>();
+
+    for collision in get_hit_collisions_with(world, overlaps) {
+        collisions
+            .entry(collision.hitbox)
+            .or_default()
+            .push(collision.hurtbox);
+    }
+
+    collisions
+}
+
+/// Whether any active hitbox of the attacker overlaps an active hurtbox of the victim,
+/// that it's allowed to damage.
+pub fn is_hitting(world: &mut World, attacker: Entity, victim: Entity) -> bool {
+    let hitboxes = get_hitboxes_for_owner(world, attacker);
+    let overlaps = PhysicsOverlapSource::new(world, &hitboxes);
+    is_hitting_with(world, &overlaps, attacker, victim)
+}
+
+/// Whether any active hitbox of the attacker overlaps an active hurtbox of the victim,
+/// according to the given overlap source.
+pub fn is_hitting_with(
+    world: &World,
+    overlaps: &dyn OverlapSource,
+    attacker: Entity,
+    victim: Entity,
+) -> bool {
+    get_active_hitbox_to_active_hurtbox_collisions_with(world, overlaps)
+        .into_iter()
+        .filter(|(hitbox, _)| get_hitbox_owner(world, *hitbox) == Some(attacker))
+        .flat_map(|(_, hurtboxes)| hurtboxes)
+        .any(|hurtbox| get_hurtbox_owner(world, hurtbox) == Some(victim))
+}
+
+/// Returns a map of active hitboxes and the opposing active hitboxes they are colliding with.
+pub fn get_active_hitbox_to_active_hitbox_collisions(
+    world: &mut World,
+) -> HashMap<Entity, Vec<Entity>> {
+    let active_hitboxes = get_all_active_hitboxes(world);
+    let overlaps = PhysicsOverlapSource::new(world, &active_hitboxes);
+    get_active_hitbox_to_active_hitbox_collisions_with(world, &overlaps, &active_hitboxes)
+}
+
+/// Returns a map of active hitboxes and the opposing active hitboxes they are colliding with,
+/// according to the given overlap source. Only the given hitboxes are checked.
+/// Both hitboxes must have `Hitbox::clashes` set, and hitboxes of the same owner never clash.
+pub fn get_active_hitbox_to_active_hitbox_collisions_with(
+    world: &World,
+    overlaps: &dyn OverlapSource,
+    hitboxes: &Vec<Entity>,
+) -> HashMap<Entity, Vec<Entity>> {
+    let is_clashing = |hitbox: Entity| {
+        world
+            .get::<&Hitbox>(hitbox)
+            .map(|h| h.is_active() && h.clashes)
+            .unwrap_or(false)
+    };
+
+    hitboxes
+        .iter()
+        .cloned()
+        .filter(|hitbox| is_clashing(*hitbox))
+        .map(|hitbox| {
+            let owner = get_hitbox_owner(world, hitbox);
+            let others = overlaps
+                .colliding(hitbox)
+                .into_iter()
+                .filter(|other| {
+                    *other != hitbox
+                        && is_clashing(other.clone())
+                        && get_hitbox_owner(world, other.clone()) != owner
+                })
+                .collect::<Vec<Entity>>();
+
+            (hitbox, others)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod collision_tests {
+    use std::collections::{HashMap, HashSet};
+
+    use emerald::{
+        toml::Value, ColliderHandle, Entity, Group, Transform, Translation, Vector2, World,
+    };
+
+    use crate::{
+        get_active_hitbox_to_active_hitbox_collisions_with,
+        get_active_hitbox_to_active_hurtbox_collisions_with, get_gameplay_entities,
+        get_hit_collisions_with, get_hit_direction,
+        hitboxes::{
+            apply_status_effects, get_all_active_hitboxes, get_hitbox_owner,
+            set_hitbox_collider_active, Hitbox, HitboxSet, StatusEffect, StatusEffectTarget,
+        },
+        hurtboxes::{
+            get_hurtbox_owner, get_overlapping_active_hurtboxes, get_struck_collider, Hurtbox,
+            HurtboxSet,
+        },
+        is_hitting_with,
+        overlap::OverlapSource,
+        register_hit, remap_merged_combat_boxes, remap_merged_trackers, resolve_clashes,
+        tracker::SimpleTranslationTracker,
+        Facing, HitCollision, HitDirection, HitmeConfig, OrphanedTracker, OrphanedTrackerMode,
+    };
+
+    const HITBOX_SET_TOML: &str = r#"
+        [hitboxes.punch]
+        active = true
+        colliders = [{ width = 10.0, height = 10.0 }]
+    "#;
+
+    const HURTBOX_SET_TOML: &str = r#"
+        [[hurtboxes]]
+        active = true
+        colliders = [{ width = 10.0, height = 10.0 }]
+    "#;
+
+    pub fn spawn_hitbox_set(world: &mut World, toml: &str) -> (Entity, Entity) {
+        let owner = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(toml).unwrap();
+        let set =
+            HitboxSet::from_toml(world, &value, owner, Group::GROUP_1, Group::GROUP_2).unwrap();
+        let hitbox = set.hitboxes.values().next().unwrap().clone();
+        world.insert_one(owner, set).unwrap();
+
+        (owner, hitbox)
+    }
+
+    fn collision(
+        hitbox: Entity,
+        hurtbox: Entity,
+        attacker: Entity,
+        defender: Entity,
+    ) -> HitCollision {
+        HitCollision {
+            hitbox,
+            hitbox_owner: attacker,
+            hurtbox,
+            hurtbox_owner: defender,
+            collider_name: None,
+        }
+    }
+
+    pub fn spawn_hurtbox_set(world: &mut World, owner: Option<Entity>) -> (Entity, Entity) {
+        let owner = owner.unwrap_or_else(|| world.spawn((Transform::default(),)));
+        let value = emerald::toml::from_str::<Value>(HURTBOX_SET_TOML).unwrap();
+        let set =
+            HurtboxSet::from_toml(world, &value, owner, Group::GROUP_1, Group::GROUP_2).unwrap();
+        let hurtbox = set.hurtboxes[0];
+        world.insert_one(owner, set).unwrap();
+
+        (owner, hurtbox)
+    }
+
+    #[test]
+    fn scripted_overlaps_produce_collisions() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+
+        let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+        let collisions = get_active_hitbox_to_active_hurtbox_collisions_with(&world, &overlaps);
+
+        assert_eq!(collisions.get(&hitbox), Some(&vec![hurtbox]));
+    }
+
+    #[test]
+    fn hit_collisions_resolve_owners() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+
+        let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+        let collisions = get_hit_collisions_with(&world, &overlaps);
+
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].hitbox, hitbox);
+        assert_eq!(collisions[0].hitbox_owner, attacker);
+        assert_eq!(collisions[0].hurtbox, hurtbox);
+        assert_eq!(collisions[0].hurtbox_owner, defender);
+        assert_eq!(collisions[0].collider_name, None);
+    }
+
+    #[test]
+    fn hit_collisions_name_the_struck_collider() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let defender = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                [[hurtboxes]]
+                active = true
+                colliders = [{ name = "head", width = 4.0, height = 4.0 }]
+            "#,
+        )
+        .unwrap();
+        let set =
+            HurtboxSet::from_toml(&mut world, &value, defender, Group::GROUP_1, Group::GROUP_2)
+                .unwrap();
+        let hurtbox = set.hurtboxes[0];
+        world.insert_one(defender, set).unwrap();
+
+        let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+        let collisions = get_hit_collisions_with(&world, &overlaps);
+
+        assert_eq!(collisions[0].collider_name, Some(String::from("head")));
+    }
+
+    const SWORD_TOML: &str = r#"
+        [hitboxes.sword]
+        active = true
+
+        [[hitboxes.sword.colliders]]
+        name = "tip"
+        width = 4.0
+        height = 4.0
+        translation = { x = 0.0, y = 20.0 }
+
+        [[hitboxes.sword.colliders]]
+        name = "hilt"
+        width = 4.0
+        height = 4.0
+    "#;
+
+    fn spawn_head_and_torso(world: &mut World) -> Entity {
+        let defender = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(
+            r#"
+                [[hurtboxes]]
+                active = true
+
+                [[hurtboxes.colliders]]
+                name = "head"
+                width = 10.0
+                height = 10.0
+                translation = { x = 0.0, y = 20.0 }
+
+                [[hurtboxes.colliders]]
+                name = "torso"
+                width = 10.0
+                height = 20.0
+            "#,
+        )
+        .unwrap();
+        let set =
+            HurtboxSet::from_toml(world, &value, defender, Group::GROUP_1, Group::GROUP_2).unwrap();
+        let hurtbox = set.hurtboxes[0];
+        world.insert_one(defender, set).unwrap();
+
+        hurtbox
+    }
+
+    fn struck_name(world: &mut World, hitbox: Entity, hurtbox: Entity) -> Option<String> {
+        let handle = get_struck_collider(world, hitbox, hurtbox)?;
+        world
+            .get::<&Hurtbox>(hurtbox)
+            .unwrap()
+            .get_collider_name(handle)
+    }
+
+    #[test]
+    fn struck_collider_skips_switched_off_hitbox_colliders() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, SWORD_TOML);
+        let hurtbox = spawn_head_and_torso(&mut world);
+
+        assert_eq!(
+            struck_name(&mut world, hitbox, hurtbox),
+            Some(String::from("head"))
+        );
+
+        set_hitbox_collider_active(&mut world, hitbox, "tip", false).unwrap();
+
+        assert_eq!(
+            struck_name(&mut world, hitbox, hurtbox),
+            Some(String::from("torso"))
+        );
+    }
+
+    /// Reports a fixed struck collider for every overlap, like the physics engine would.
+    struct StruckOverlaps {
+        overlaps: HashMap<Entity, Vec<Entity>>,
+        struck: ColliderHandle,
+    }
+    impl OverlapSource for StruckOverlaps {
+        fn colliding(&self, id: Entity) -> Vec<Entity> {
+            self.overlaps.colliding(id)
+        }
+
+        fn struck_collider(&self, _id: Entity, _other: Entity) -> Option<ColliderHandle> {
+            Some(self.struck)
+        }
+    }
+
+    #[test]
+    fn hit_collisions_name_the_collider_reported_by_the_overlap_source() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, SWORD_TOML);
+        let hurtbox = spawn_head_and_torso(&mut world);
+        let torso = world.get::<&Hurtbox>(hurtbox).unwrap().physics_colliders()[1].0;
+
+        let overlaps = StruckOverlaps {
+            overlaps: HashMap::from([(hitbox, vec![hurtbox])]),
+            struck: torso,
+        };
+        let collisions = get_hit_collisions_with(&world, &overlaps);
+
+        assert_eq!(collisions[0].collider_name, Some(String::from("torso")));
+    }
+
+    #[test]
+    fn is_hitting_only_reports_overlapping_pairs() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (victim, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        let (bystander, _) = spawn_hurtbox_set(&mut world, None);
+
+        let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+        assert!(is_hitting_with(&world, &overlaps, attacker, victim));
+        assert!(!is_hitting_with(&world, &overlaps, attacker, bystander));
+        assert!(!is_hitting_with(&world, &overlaps, victim, attacker));
+
+        world.get::<&mut Hurtbox>(hurtbox).unwrap().active = false;
+        assert!(!is_hitting_with(&world, &overlaps, attacker, victim));
+    }
+
+    /// Gathers collisions looking up every component per overlapping pair
+    fn naive_hitbox_to_hurtbox_collisions(
+        world: &World,
+        overlaps: &HashMap<Entity, Vec<Entity>>,
+    ) -> HashMap<Entity, HashSet<Entity>> {
+        get_all_active_hitboxes(world)
+            .into_iter()
+            .map(|hitbox_id| {
+                let hitbox = world.get::<&Hitbox>(hitbox_id).unwrap();
+                let neutral = world.get::<&HitboxSet>(hitbox.parent_set).unwrap().neutral;
+                let hurtboxes = get_overlapping_active_hurtboxes(world, overlaps, hitbox_id)
+                    .into_iter()
+                    .filter(|hurtbox_id| {
+                        let hurtbox = world.get::<&Hurtbox>(*hurtbox_id).unwrap();
+                        let hurtbox_owner = get_hurtbox_owner(world, *hurtbox_id).unwrap();
+                        let same_owner =
+                            !neutral && get_hitbox_owner(world, hitbox_id) == Some(hurtbox_owner);
+
+                        !same_owner
+                            && !(!neutral && hitbox.is_ally(&hurtbox))
+                            && hitbox.can_damage_entity(&hurtbox_owner)
+                            && hitbox.targets_hurtbox(&hurtbox)
+                    })
+                    .collect();
+
+                (hitbox_id, hurtboxes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_pass_gather_matches_naive_gather() {
+        let mut world = World::new();
+        let mut hitboxes = Vec::new();
+        let mut hurtboxes = Vec::new();
+        for i in 0..100 {
+            let (owner, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+            // Every tenth owner can only overlap its own hurtbox
+            let hurtbox_owner = (i % 10 == 0).then(|| owner);
+            let (_, hurtbox) = spawn_hurtbox_set(&mut world, hurtbox_owner);
+            hitboxes.push(hitbox);
+            hurtboxes.push(hurtbox);
+        }
+        for i in (0..100).step_by(3) {
+            world.get::<&mut Hurtbox>(hurtboxes[i]).unwrap().active = false;
+        }
+
+        let overlaps = hitboxes
+            .iter()
+            .enumerate()
+            .map(|(i, hitbox)| {
+                let overlapping = vec![
+                    hurtboxes[i],
+                    hurtboxes[(i * 7) % 100],
+                    hitboxes[(i + 1) % 100],
+                ];
+                (*hitbox, overlapping)
+            })
+            .collect::<HashMap<Entity, Vec<Entity>>>();
+
+        let collisions = get_active_hitbox_to_active_hurtbox_collisions_with(&world, &overlaps)
+            .into_iter()
+            .map(|(hitbox, hurtboxes)| (hitbox, hurtboxes.into_iter().collect()))
+            .collect::<HashMap<Entity, HashSet<Entity>>>();
+
+        assert_eq!(collisions.len(), 100);
+        assert_eq!(
+            collisions,
+            naive_hitbox_to_hurtbox_collisions(&world, &overlaps)
+        );
+    }
+
+    #[test]
+    fn custom_groups_only_collide_with_matching_sets() {
+        let mut world = World::new();
+        let (_, hazard) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                groups = { hitbox = 3, hurtbox = 4 }
+
+                [hitboxes.spikes]
+                active = true
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let mut spawn_hurtboxes = |toml: &str| {
+            let owner = world.spawn((Transform::default(),));
+            let value = emerald::toml::from_str::<Value>(toml).unwrap();
+            HurtboxSet::from_toml(&mut world, &value, owner, Group::GROUP_1, Group::GROUP_2)
+                .unwrap()
+                .get_collision_groups()
+        };
+        let matching = spawn_hurtboxes("groups = { hitbox = 3, hurtbox = 4 }");
+        let default = spawn_hurtboxes("");
+
+        let hazard_groups = world.get::<&Hitbox>(hazard).unwrap().get_collision_groups();
+        assert!(hazard_groups.test(matching));
+        assert!(!hazard_groups.test(default));
+    }
+
+    const CLASHING_HITBOX_SET_TOML: &str = r#"
+        [hitboxes.punch]
+        active = true
+        clashes = true
+        colliders = [{ width = 10.0, height = 10.0 }]
+    "#;
+
+    #[test]
+    fn opposing_hitboxes_clash_once() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, CLASHING_HITBOX_SET_TOML);
+        let (_, other_hitbox) = spawn_hitbox_set(&mut world, CLASHING_HITBOX_SET_TOML);
+        let overlaps = HashMap::from([(hitbox, vec![other_hitbox]), (other_hitbox, vec![hitbox])]);
+
+        let mut clashes = Vec::new();
+        let hitboxes = vec![hitbox, other_hitbox];
+        resolve_clashes(&mut world, &overlaps, &hitboxes, &mut |_, ctx| {
+            clashes.push(ctx)
+        });
+
+        assert_eq!(clashes.len(), 1);
+        let pair = HashSet::from([clashes[0].hitbox, clashes[0].other_hitbox]);
+        assert_eq!(pair, HashSet::from([hitbox, other_hitbox]));
+    }
+
+    #[test]
+    fn hitboxes_only_clash_when_both_opt_in() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, CLASHING_HITBOX_SET_TOML);
+        let (_, other_hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let overlaps = HashMap::from([(hitbox, vec![other_hitbox]), (other_hitbox, vec![hitbox])]);
+
+        let mut clashes = Vec::new();
+        let hitboxes = vec![hitbox, other_hitbox];
+        resolve_clashes(&mut world, &overlaps, &hitboxes, &mut |_, ctx| {
+            clashes.push(ctx)
+        });
+        assert!(clashes.is_empty());
+
+        let groups = |id: Entity| world.get::<&Hitbox>(id).unwrap().get_collision_groups();
+        assert!(groups(hitbox).test(groups(hitbox)));
+        assert!(!groups(other_hitbox).test(groups(other_hitbox)));
+    }
+
+    #[test]
+    fn hitboxes_of_the_same_owner_do_not_clash() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.left]
+                active = true
+                clashes = true
+                colliders = [{ width = 10.0, height = 10.0 }]
+
+                [hitboxes.right]
+                active = true
+                clashes = true
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let hitboxes = world
+            .get::<&HitboxSet>(owner)
+            .unwrap()
+            .hitboxes
+            .values()
+            .cloned()
+            .collect::<Vec<Entity>>();
+        let overlaps = HashMap::from([(hitboxes[0], vec![hitboxes[1]])]);
+
+        let collisions =
+            get_active_hitbox_to_active_hitbox_collisions_with(&world, &overlaps, &hitboxes);
+        assert_eq!(collisions.get(&hitboxes[0]), Some(&vec![]));
+    }
+
+    #[test]
+    fn same_team_hitboxes_do_not_hit_allies() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                team = 1
+
+                [hitboxes.punch]
+                active = true
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world.get::<&mut Hurtbox>(hurtbox).unwrap().team = Some(1);
+
+        let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+        let collisions = get_active_hitbox_to_active_hurtbox_collisions_with(&world, &overlaps);
+
+        assert_eq!(collisions.get(&hitbox), Some(&vec![]));
+    }
+
+    #[test]
+    fn friendly_fire_hitboxes_hit_allies() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.explosion]
+                active = true
+                team = 1
+                friendly_fire = true
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world.get::<&mut Hurtbox>(hurtbox).unwrap().team = Some(1);
+
+        let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+        let collisions = get_active_hitbox_to_active_hurtbox_collisions_with(&world, &overlaps);
+
+        assert_eq!(collisions.get(&hitbox), Some(&vec![hurtbox]));
+    }
+
+    #[test]
+    fn invulnerable_hurtboxes_are_excluded_until_time_elapses() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+
+        world
+            .get::<&mut Hurtbox>(hurtbox)
+            .unwrap()
+            .make_invulnerable(0.5);
+        let collisions = get_active_hitbox_to_active_hurtbox_collisions_with(&world, &overlaps);
+        assert_eq!(collisions.get(&hitbox), Some(&vec![]));
+
+        world
+            .get::<&mut Hurtbox>(hurtbox)
+            .unwrap()
+            .advance_invulnerability(0.5);
+        let collisions = get_active_hitbox_to_active_hurtbox_collisions_with(&world, &overlaps);
+        assert_eq!(collisions.get(&hitbox), Some(&vec![hurtbox]));
+    }
+
+    #[test]
+    fn hitboxes_do_not_collide_with_their_owners_hurtboxes() {
+        let mut world = World::new();
+        let (owner, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, Some(owner));
+
+        let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+        let collisions = get_active_hitbox_to_active_hurtbox_collisions_with(&world, &overlaps);
+
+        assert!(collisions.get(&hitbox).unwrap().is_empty());
+    }
+
+    #[test]
+    fn hitboxes_ignore_hurtboxes_without_a_targeted_tag() {
+        let mut world = World::new();
+        let (_, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.disarm]
+                active = true
+                target_hurtbox_tags = ["weapon"]
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let (_, hurtbox) = spawn_hurtbox_set(&mut world, None);
+
+        let overlaps = HashMap::from([(hitbox, vec![hurtbox])]);
+        let collisions = get_active_hitbox_to_active_hurtbox_collisions_with(&world, &overlaps);
+        assert!(collisions.get(&hitbox).unwrap().is_empty());
+
+        world.get::<&mut Hurtbox>(hurtbox).unwrap().tags = vec![String::from("weapon")];
+        let collisions = get_active_hitbox_to_active_hurtbox_collisions_with(&world, &overlaps);
+        assert_eq!(collisions.get(&hitbox), Some(&vec![hurtbox]));
+    }
+
+    #[test]
+    fn combat_boxes_are_excluded_from_gameplay_entities() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+
+        let entities = get_gameplay_entities(&world);
+        assert!(entities.contains(&attacker) && entities.contains(&defender));
+        assert!(!entities.contains(&hitbox) && !entities.contains(&hurtbox));
+    }
+
+    #[test]
+    fn knockback_force_scales_the_normalized_direction() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.launcher]
+                active = true
+                knockback = { x = 3.0, y = 4.0, force = 10.0 }
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+
+        let config = HitmeConfig::default();
+        let ctx = register_hit(
+            &mut world,
+            &config,
+            &collision(hitbox, hurtbox, attacker, defender),
+            0.0,
+        );
+
+        assert_eq!(ctx.knockback, Some(Vector2::new(6.0, 8.0)));
+        assert_eq!(ctx.hit_entity, attacker);
+        assert_eq!(ctx.hurt_entity, defender);
+    }
+
+    #[derive(Default)]
+    struct Status {
+        effects: Vec<StatusEffect>,
+    }
+    impl StatusEffectTarget for Status {
+        fn apply_status_effect(&mut self, effect: StatusEffect) {
+            self.effects.push(effect);
+        }
+    }
+
+    #[test]
+    fn applied_effects_reach_on_hit_context() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.dart]
+                active = true
+                applied_effects = ["stun", "poison"]
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world.insert_one(defender, Status::default()).unwrap();
+
+        let config = HitmeConfig::default();
+        let ctx = register_hit(
+            &mut world,
+            &config,
+            &collision(hitbox, hurtbox, attacker, defender),
+            0.0,
+        );
+        assert_eq!(
+            ctx.applied_effects,
+            vec![StatusEffect::Stun, StatusEffect::Poison]
+        );
+
+        apply_status_effects::<Status>(&mut world, ctx.hurt_entity, &ctx.applied_effects);
+        assert_eq!(
+            world.get::<&Status>(defender).unwrap().effects,
+            ctx.applied_effects
+        );
+    }
+
+    #[test]
+    fn knockback_is_mirrored_for_left_facing_attackers() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.launcher]
+                active = true
+                knockback = { x = 3.0, y = 4.0 }
+                colliders = [{ width = 10.0, height = 10.0 }]
+            "#,
+        );
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world.insert_one(attacker, Facing(-1.0)).unwrap();
+
+        let config = HitmeConfig::default();
+        let ctx = register_hit(
+            &mut world,
+            &config,
+            &collision(hitbox, hurtbox, attacker, defender),
+            0.0,
+        );
+
+        assert_eq!(ctx.knockback, Some(Vector2::new(-3.0, 4.0)));
+    }
+
+    #[test]
+    fn attacker_behind_the_victim_hits_the_back() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world.get::<&mut Transform>(attacker).unwrap().translation.x = -20.0;
+
+        let config = HitmeConfig::default();
+        let ctx = register_hit(
+            &mut world,
+            &config,
+            &collision(hitbox, hurtbox, attacker, defender),
+            0.0,
+        );
+        assert_eq!(ctx.direction, HitDirection::Back);
+
+        world.insert_one(defender, Facing(-1.0)).unwrap();
+        assert_eq!(
+            get_hit_direction(&world, attacker, defender),
+            HitDirection::Front
+        );
+
+        world.get::<&mut Transform>(attacker).unwrap().translation.x = 0.0;
+        assert_eq!(
+            get_hit_direction(&world, attacker, defender),
+            HitDirection::Front
+        );
+    }
+
+    #[test]
+    fn armor_remaining_counts_down_across_hits() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world.get::<&mut Hurtbox>(hurtbox).unwrap().armor_hits = Some(3);
+
+        let config = HitmeConfig::default();
+        let remaining = (0..3)
+            .map(|_| {
+                register_hit(
+                    &mut world,
+                    &config,
+                    &collision(hitbox, hurtbox, attacker, defender),
+                    0.0,
+                )
+                .armor_remaining
+            })
+            .collect::<Vec<Option<u32>>>();
+
+        assert_eq!(remaining, vec![Some(2), Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn second_hit_of_a_combo_is_scaled_down() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+
+        let config = HitmeConfig {
+            combo_timeout: Some(1.0),
+            combo_scaling: 0.75,
+            ..HitmeConfig::default()
+        };
+        let scaling = (0..2)
+            .map(|_| {
+                register_hit(
+                    &mut world,
+                    &config,
+                    &collision(hitbox, hurtbox, attacker, defender),
+                    0.0,
+                )
+                .scaling
+            })
+            .collect::<Vec<f32>>();
+
+        assert_eq!(scaling, vec![1.0, 0.75]);
+    }
+
+    #[test]
+    fn blocking_hurtbox_reports_blocked_hit() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        let config = HitmeConfig::default();
+
+        let ctx = register_hit(
+            &mut world,
+            &config,
+            &collision(hitbox, hurtbox, attacker, defender),
+            0.0,
+        );
+        assert!(!ctx.blocked);
+
+        {
+            let mut hurtbox_set = world.get::<&mut HurtboxSet>(defender).unwrap();
+            hurtbox_set.set_blocking(true);
+            hurtbox_set.block_reduction = 0.75;
+        }
+        let ctx = register_hit(
+            &mut world,
+            &config,
+            &collision(hitbox, hurtbox, attacker, defender),
+            0.0,
+        );
+        assert!(ctx.blocked);
+        assert_eq!(ctx.damage_multiplier, 0.25);
+    }
+
+    #[test]
+    fn struck_hurtbox_damage_multiplier_reaches_on_hit_context() {
+        let mut world = World::new();
+        let (attacker, hitbox) = spawn_hitbox_set(&mut world, HITBOX_SET_TOML);
+        let (defender, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        world
+            .get::<&mut Hurtbox>(hurtbox)
+            .unwrap()
+            .damage_multiplier = 2.0;
+
+        let config = HitmeConfig::default();
+        let ctx = register_hit(
+            &mut world,
+            &config,
+            &collision(hitbox, hurtbox, attacker, defender),
+            0.0,
+        );
+
+        assert_eq!(ctx.damage_multiplier, 2.0);
+    }
+
+    #[test]
+    fn merge_replaces_hurtbox_ids_with_remapped_entities() {
+        let mut world = World::new();
+        let (owner, hurtbox) = spawn_hurtbox_set(&mut world, None);
+        let remapped_hurtbox = world.spawn((Transform::default(),));
+
+        let mut entity_map = HashMap::new();
+        entity_map.insert(owner, owner);
+        entity_map.insert(hurtbox, remapped_hurtbox);
+        remap_merged_combat_boxes(&mut world, &entity_map);
+
+        let hurtbox_set = world.get::<&HurtboxSet>(owner).unwrap();
+        assert_eq!(hurtbox_set.hurtboxes, vec![remapped_hurtbox]);
+    }
+
+    /// Returns the entity map of a merge bringing in a tracked box along with its target,
+    /// and a box whose target stayed behind, followed by the target and both boxes.
+    fn merge_with_orphan(world: &mut World) -> (HashMap<Entity, Entity>, [Entity; 4]) {
+        let mut old_world = World::new();
+        let old_target = old_world.spawn(());
+        let missing_target = old_world.spawn(());
+        let old_tracked = old_world.spawn(());
+        let old_orphan = old_world.spawn(());
+
+        let target = world.spawn((Transform::default(),));
+        let tracked = world.spawn((SimpleTranslationTracker::new(
+            old_target,
+            Translation::default(),
+        ),));
+        let orphan = world.spawn((SimpleTranslationTracker::new(
+            missing_target,
+            Translation::default(),
+        ),));
+        let entity_map = HashMap::from([
+            (old_target, target),
+            (old_tracked, tracked),
+            (old_orphan, orphan),
+        ]);
+
+        (entity_map, [old_target, target, tracked, orphan])
+    }
+
+    fn tracked_target(world: &World, id: Entity) -> Entity {
+        world.get::<&SimpleTranslationTracker>(id).unwrap().target
+    }
+
+    #[test]
+    fn warn_mode_marks_orphaned_boxes() {
+        let mut world = World::new();
+        let (entity_map, [_, target, tracked, orphan]) = merge_with_orphan(&mut world);
+
+        let orphans = remap_merged_trackers(&mut world, &entity_map, OrphanedTrackerMode::Warn);
+
+        assert_eq!(orphans.unwrap(), vec![orphan]);
+        assert!(world.has::<OrphanedTracker>(orphan));
+        assert!(!world.has::<OrphanedTracker>(tracked));
+        assert_eq!(tracked_target(&world, tracked), target);
+    }
+
+    #[test]
+    fn error_mode_fails_before_remapping() {
+        let mut world = World::new();
+        let (entity_map, [old_target, _, tracked, orphan]) = merge_with_orphan(&mut world);
+
+        let result = remap_merged_trackers(&mut world, &entity_map, OrphanedTrackerMode::Error);
+
+        assert!(result.is_err());
+        assert_eq!(tracked_target(&world, tracked), old_target);
+        assert!(!world.has::<OrphanedTracker>(orphan));
+    }
+
+    #[test]
+    fn drop_mode_despawns_orphaned_boxes() {
+        let mut world = World::new();
+        let (entity_map, [_, target, tracked, orphan]) = merge_with_orphan(&mut world);
+
+        let orphans = remap_merged_trackers(&mut world, &entity_map, OrphanedTrackerMode::Drop);
+
+        assert_eq!(orphans.unwrap(), vec![orphan]);
+        assert!(!world.contains(orphan));
+        assert_eq!(tracked_target(&world, tracked), target);
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use emerald::{serde::Deserialize, toml::Value, Emerald, World};
+
+    use crate::{
+        collision_tests::spawn_hitbox_set, hitboxes::HitboxSet, host::tests::HeadlessHost,
+        run_frame, HitmeConfig, HitmeConfigBuilder, OnHitContext, OnHitFn, OnTagTriggerContext,
+        OnTagTriggerFn,
+    };
+
+    fn sound_handler(_: &mut Emerald, _: &mut World, _: OnTagTriggerContext) {}
+    fn vfx_handler(_: &mut Emerald, _: &mut World, _: OnTagTriggerContext) {}
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    #[serde(crate = "emerald::serde")]
+    struct Heal {
+        amount: u32,
+    }
+
+    #[test]
+    fn tag_data_deserializes_into_user_types() {
+        let mut world = World::new();
+        let ctx = OnTagTriggerContext {
+            tag: String::from("heal"),
+            hitbox_set_owner: world.spawn(()),
+            data: emerald::toml::from_str::<Value>("amount = 5").unwrap(),
+            sequence_name: String::from("attack"),
+            frame: 0,
+            elapsed: 0.0,
+        };
+
+        assert_eq!(ctx.data_as::<Heal>().unwrap(), Heal { amount: 5 });
+        assert!(ctx.data_as::<Vec<u32>>().is_err());
+    }
+
+    #[test]
+    fn missing_config_is_an_error() {
+        let mut world = World::new();
+        let mut host = HeadlessHost::default();
+        assert!(run_frame(&mut host, &mut world, None).is_err());
+
+        host.config = Some(HitmeConfig::default());
+        assert!(run_frame(&mut host, &mut world, None).is_ok());
+    }
+
+    #[test]
+    fn frame_copy_leaves_the_callbacks_in_the_resource() {
+        let mut config = HitmeConfigBuilder::new()
+            .add_on_hit(play_hit_sound)
+            .add_tag_handler(sound_handler)
+            .build();
+        config.combo_timeout = Some(1.0);
+
+        let frame_config = config.frame_copy();
+        assert_eq!(frame_config.combo_timeout, Some(1.0));
+        assert!(frame_config.on_hit_callbacks().is_empty());
+        assert!(frame_config.get_tag_handlers("swing").is_empty());
+
+        let mut world = World::new();
+        let mut host = HeadlessHost::new(config, 0.1);
+        run_frame(&mut host, &mut world, Some(0.5)).unwrap();
+        let resource = host.config.unwrap();
+        assert_eq!(resource.on_hit_callbacks().len(), 1);
+        assert!(resource.delta_override.is_none());
+    }
+
+    #[test]
+    fn combat_is_enabled_by_default() {
+        assert!(HitmeConfig::default().enabled);
+        assert!(HitmeConfigBuilder::new().build().enabled);
+    }
+
+    #[test]
+    fn disabled_combat_keeps_sequences_frozen() {
+        let mut world = World::new();
+        let (owner, _) = spawn_hitbox_set(
+            &mut world,
+            r#"
+                [hitboxes.hitbox]
+
+                [sequences]
+                attack = [{ duration = 0.25 }, { duration = 0.25 }]
+            "#,
+        );
+        world
+            .get::<&mut HitboxSet>(owner)
+            .unwrap()
+            .start_sequence("attack")
+            .unwrap();
+        let progress = |world: &World| {
+            let hitbox_set = world.get::<&HitboxSet>(owner).unwrap();
+            let sequence = hitbox_set.active_sequence.as_ref().unwrap();
+            (sequence.frame, sequence.elapsed_time)
+        };
+
+        let mut host = HeadlessHost::new(HitmeConfig::default(), 0.1);
+        host.config.as_mut().unwrap().enabled = false;
+        for _ in 0..5 {
+            run_frame(&mut host, &mut world, Some(0.1)).unwrap();
+        }
+        assert_eq!(progress(&world), (0, 0.0));
+
+        host.config.as_mut().unwrap().enabled = true;
+        for _ in 0..3 {
+            run_frame(&mut host, &mut world, Some(0.1)).unwrap();
+        }
+        assert_eq!(progress(&world).0, 1);
+    }
+
+    fn play_hit_sound(_: &mut Emerald, _: &mut World, _: OnHitContext) {}
+    fn shake_camera(_: &mut Emerald, _: &mut World, _: OnHitContext) {}
+    fn flash_screen(_: &mut Emerald, _: &mut World, _: OnHitContext) {}
+
+    #[test]
+    fn removed_on_hit_fn_no_longer_fires() {
+        let mut config = HitmeConfig::default();
+        config.on_hit_fns.push(flash_screen);
+        let sound = config.add_on_hit_fn(play_hit_sound);
+        let shake = config.add_on_hit_fn(shake_camera);
+        assert_ne!(sound, shake);
+
+        assert!(config.remove_on_hit_fn(sound));
+        assert!(!config.remove_on_hit_fn(sound));
+
+        let fired = config
+            .on_hit_callbacks()
+            .into_iter()
+            .map(|f| f as usize)
+            .collect::<Vec<usize>>();
+        assert_eq!(
+            fired,
+            vec![
+                flash_screen as OnHitFn as usize,
+                shake_camera as OnHitFn as usize
+            ]
+        );
+
+        config.clear_on_hit_fns();
+        assert!(config.on_hit_callbacks().is_empty());
+    }
+
+    #[test]
+    fn builder_registers_handlers() {
+        let config = HitmeConfigBuilder::new()
+            .add_on_hit(play_hit_sound)
+            .add_on_hit(shake_camera)
+            .add_tag_handler(sound_handler)
+            .add_tag_handler_by_name("swing", vfx_handler)
+            .build();
+
+        assert_eq!(config.on_hit_callbacks().len(), 2);
+        assert_eq!(config.get_tag_handlers("swing").len(), 2);
+        assert_eq!(config.get_tag_handlers("land").len(), 1);
+    }
+
+    #[test]
+    fn named_tag_handlers_accumulate() {
+        let mut config = HitmeConfig::default();
+        config
+            .tag_handlers_by_name
+            .entry("swing".to_string())
+            .or_default()
+            .extend([sound_handler as OnTagTriggerFn, vfx_handler]);
+
+        assert_eq!(config.get_tag_handlers("swing").len(), 2);
+        assert!(config.get_tag_handlers("land").is_empty());
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use emerald::{ColliderHandle, Entity, World};
+
+use crate::hitboxes::get_swept_hurtbox_contacts;
+use crate::hurtboxes::get_struck_collider;
+
+/// A source of overlapping entities, used when gathering hitbox to hurtbox collisions.
+/// Defaults to the physics engine, but can be replaced to script overlaps in tests.
+pub trait OverlapSource {
+    /// Returns the entities colliding with the given entity.
+    fn colliding(&self, id: Entity) -> Vec<Entity>;
+
+    /// Returns when within the frame the two entities first made contact, from 0.0 to 1.0.
+    /// Sources without sub-stepping report every contact at the start of the frame.
+    fn contact_time(&self, _id: Entity, _other: Entity) -> f32 {
+        0.0
+    }
+
+    /// Returns the physics collider of the other entity that the given entity touched.
+    /// Sources that only know about entities report None.
+    fn struck_collider(&self, _id: Entity, _other: Entity) -> Option<ColliderHandle> {
+        None
+    }
+}
+
+/// Overlaps reported by the physics engine, gathered for a given set of entities.
+/// Contacts found by sweeping continuous hitboxes report when along the sweep they happened,
+/// the rest happen at the start of the frame.
+pub struct PhysicsOverlapSource {
+    overlaps: HashMap<Entity, Vec<Entity>>,
+    contact_times: HashMap<(Entity, Entity), f32>,
+    struck_colliders: HashMap<(Entity, Entity), ColliderHandle>,
+}
+impl PhysicsOverlapSource {
+    pub fn new(world: &mut World, ids: &Vec<Entity>) -> Self {
+        let overlaps = ids
+            .iter()
+            .map(|id| {
+                (
+                    id.clone(),
+                    world.physics().get_colliding_entities(id.clone()),
+                )
+            })
+            .collect::<HashMap<Entity, Vec<Entity>>>();
+
+        let mut struck_colliders = HashMap::new();
+        for (id, colliding) in overlaps.iter() {
+            for other in colliding {
+                if let Some(handle) = get_struck_collider(world, *id, *other) {
+                    struck_colliders.insert((*id, *other), handle);
+                }
+            }
+        }
+
+        Self {
+            overlaps,
+            contact_times: HashMap::new(),
+            struck_colliders,
+        }
+    }
+
+    /// Adds the hurtboxes continuous hitboxes passed through since the last tick,
+    /// see `Hitbox::continuous`.
+    pub fn include_swept_hurtboxes(&mut self, world: &World) {
+        for (id, colliding) in self.overlaps.iter_mut() {
+            for (hurtbox, time) in get_swept_hurtbox_contacts(world, *id) {
+                if !colliding.contains(&hurtbox) {
+                    colliding.push(hurtbox);
+                }
+                self.contact_times.insert((*id, hurtbox), time);
+            }
+        }
+    }
+}
+impl OverlapSource for PhysicsOverlapSource {
+    fn colliding(&self, id: Entity) -> Vec<Entity> {
+        self.overlaps.get(&id).cloned().unwrap_or_default()
+    }
+
+    fn contact_time(&self, id: Entity, other: Entity) -> f32 {
+        self.contact_times.get(&(id, other)).cloned().unwrap_or(0.0)
+    }
+
+    fn struck_collider(&self, id: Entity, other: Entity) -> Option<ColliderHandle> {
+        self.struck_colliders.get(&(id, other)).cloned()
+    }
+}
+
+/// A fixed map of overlaps, useful for driving collisions without a physics step.
+impl OverlapSource for HashMap<Entity, Vec<Entity>> {
+    fn colliding(&self, id: Entity) -> Vec<Entity> {
+        self.get(&id).cloned().unwrap_or_default()
+    }
+}
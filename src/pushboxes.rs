@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+
+use emerald::{
+    EmeraldError, Entity, Group, InteractionGroups, RigidBodyBuilder, Transform, Translation,
+    Vector2, World,
+};
+
+use crate::hurtboxes::{translation_to_vector, vector_to_translation, RectCollider};
+use crate::tracker::SimpleTranslationTracker;
+use crate::CombatBox;
+
+/// Keeps pushbox bodies from drifting on after a contact, they're placed by tracking each frame.
+const PUSHBOX_LINEAR_DAMPING: f32 = 1000.0;
+
+/// Bodies that physically push each other apart, without dealing or taking damage.
+/// ex. keeping two characters from walking through each other.
+pub struct PushboxSet {
+    pub pushboxes: Vec<Entity>,
+
+    /// The entity the pushboxes follow
+    pub owner: Entity,
+
+    /// Collision groups of the set's colliders, pushboxes only collide with other pushboxes
+    collision_groups: InteractionGroups,
+}
+impl PushboxSet {
+    pub fn from_toml(
+        world: &mut World,
+        value: &emerald::toml::Value,
+        owner: Entity,
+        pushbox_group: Group,
+    ) -> Result<Self, EmeraldError> {
+        let owner_transform = world.get::<&mut Transform>(owner)?.clone();
+        let collision_groups = InteractionGroups::new(pushbox_group, pushbox_group);
+        let track_rotation = value
+            .get("track_rotation")
+            .map(|v| v.as_bool())
+            .flatten()
+            .unwrap_or(false);
+        let pushboxes = value
+            .get("pushboxes")
+            .unwrap_or(&emerald::toml::Value::Array(Vec::new()))
+            .as_array()
+            .unwrap_or(&Vec::new())
+            .into_iter()
+            .map(|value| Pushbox::from_toml(value, owner))
+            .collect::<Result<Vec<Pushbox>, EmeraldError>>()?
+            .into_iter()
+            .map(|pushbox| {
+                let colliders = pushbox.colliders.clone();
+                let (id, rbh) = world.spawn_with_body(
+                    (
+                        pushbox,
+                        CombatBox,
+                        owner_transform.clone(),
+                        SimpleTranslationTracker {
+                            track_rotation,
+                            ..SimpleTranslationTracker::new(owner, Translation::new(0.0, 0.0))
+                        },
+                    ),
+                    RigidBodyBuilder::dynamic()
+                        .lock_rotations()
+                        .gravity_scale(0.0)
+                        .linear_damping(PUSHBOX_LINEAR_DAMPING),
+                )?;
+
+                for collider in colliders {
+                    let builder = collider
+                        .to_collider_builder()
+                        .collision_groups(collision_groups);
+                    world.physics().build_collider(rbh, builder);
+                }
+
+                Ok(id)
+            })
+            .collect::<Result<Vec<Entity>, EmeraldError>>()?;
+
+        Ok(Self {
+            pushboxes,
+            owner,
+            collision_groups,
+        })
+    }
+
+    pub fn get_collision_groups(&self) -> InteractionGroups {
+        self.collision_groups
+    }
+}
+
+pub struct Pushbox {
+    pub parent_set: Entity,
+
+    /// Always solid, see `RectCollider::solid`
+    pub colliders: Vec<RectCollider>,
+
+    /// Whether or not the pushbox is visible when debug drawing, defaults to true
+    pub visible: bool,
+
+    /// Where tracking last placed the pushbox, physics moves it away from there when pushed
+    pub(crate) placed_at: Option<Translation>,
+}
+impl Pushbox {
+    pub fn from_toml(
+        value: &emerald::toml::Value,
+        parent_set: Entity,
+    ) -> Result<Self, EmeraldError> {
+        let colliders = value
+            .get("colliders")
+            .unwrap_or(&emerald::toml::Value::Array(Vec::new()))
+            .as_array()
+            .unwrap_or(&Vec::new())
+            .into_iter()
+            .map(|value| RectCollider::from_toml(value))
+            .map(|collider| {
+                collider.map(|collider| RectCollider {
+                    solid: true,
+                    ..collider
+                })
+            })
+            .collect::<Result<Vec<RectCollider>, EmeraldError>>()?;
+
+        let visible = value
+            .get("visible")
+            .map(|v| v.as_bool())
+            .flatten()
+            .unwrap_or(true);
+
+        Ok(Self {
+            parent_set,
+            colliders,
+            visible,
+            placed_at: None,
+        })
+    }
+}
+
+/// Removes the pushbox set from its owner, and despawns every pushbox of the set.
+/// Returns how many pushbox entities were despawned.
+pub fn despawn_pushbox_set(world: &mut World, owner: Entity) -> usize {
+    let pushbox_set = match world.remove_one::<PushboxSet>(owner) {
+        Ok(pushbox_set) => pushbox_set,
+        Err(_) => return 0,
+    };
+
+    pushbox_set
+        .pushboxes
+        .into_iter()
+        .filter(|id| world.despawn(id.clone()).is_ok())
+        .count()
+}
+
+/// Despawns pushboxes whose set no longer exists, and moves owners by however far physics
+/// pushed their pushboxes since they were placed. Runs before `tracker_system`,
+/// which places the pushboxes back onto their owners, see `record_pushbox_placements`.
+pub(crate) fn pushbox_system(world: &mut World) {
+    let orphaned = world
+        .query::<&Pushbox>()
+        .iter()
+        .filter(|(_, pushbox)| !world.has::<PushboxSet>(pushbox.parent_set))
+        .map(|(id, _)| id)
+        .collect::<Vec<Entity>>();
+
+    orphaned.into_iter().for_each(|id| {
+        world.despawn(id).ok();
+    });
+
+    push_owners(world);
+}
+
+/// Moves every owner by the largest push any of its pushboxes received.
+fn push_owners(world: &mut World) {
+    let mut pushes: HashMap<Entity, Vector2<f32>> = HashMap::new();
+    for (_, (pushbox, transform)) in world.query::<(&Pushbox, &Transform)>().iter() {
+        let push = match &pushbox.placed_at {
+            Some(placed_at) => {
+                translation_to_vector(&transform.translation) - translation_to_vector(placed_at)
+            }
+            None => continue,
+        };
+        let largest = pushes.entry(pushbox.parent_set).or_insert(Vector2::zeros());
+        if push.norm() > largest.norm() {
+            *largest = push;
+        }
+    }
+
+    for (owner, push) in pushes {
+        world
+            .get::<&mut Transform>(owner)
+            .ok()
+            .map(|mut transform| {
+                let translation = translation_to_vector(&transform.translation) + push;
+                transform.translation = vector_to_translation(&translation);
+            });
+    }
+}
+
+/// Records where tracking placed each pushbox, runs after `tracker_system`.
+pub(crate) fn record_pushbox_placements(world: &mut World) {
+    for (_, (pushbox, transform)) in world.query::<(&mut Pushbox, &Transform)>().iter() {
+        pushbox.placed_at = Some(transform.translation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use emerald::{toml::Value, Entity, Group, Transform, Translation, World};
+
+    use crate::{
+        pushboxes::{
+            despawn_pushbox_set, pushbox_system, record_pushbox_placements, Pushbox, PushboxSet,
+        },
+        tracker::{track_targets, SimpleTranslationTracker},
+    };
+
+    const PUSHBOX_SET_TOML: &str = r#"
+        [[pushboxes]]
+        colliders = [{ width = 16.0, height = 32.0 }]
+    "#;
+
+    fn spawn_pushbox_set(world: &mut World) -> (Entity, Entity) {
+        let owner = world.spawn((Transform::default(),));
+        let value = emerald::toml::from_str::<Value>(PUSHBOX_SET_TOML).unwrap();
+        let set = PushboxSet::from_toml(world, &value, owner, Group::GROUP_3).unwrap();
+        let pushbox = set.pushboxes[0];
+        world.insert_one(owner, set).unwrap();
+
+        (owner, pushbox)
+    }
+
+    #[test]
+    fn pushbox_set_loads_solid_colliders_that_track_the_owner() {
+        let mut world = World::new();
+        let (owner, pushbox) = spawn_pushbox_set(&mut world);
+
+        let pushbox_ref = world.get::<&Pushbox>(pushbox).unwrap();
+        assert_eq!(pushbox_ref.parent_set, owner);
+        assert!(pushbox_ref.colliders.iter().all(|collider| collider.solid));
+        assert_eq!(
+            world
+                .get::<&SimpleTranslationTracker>(pushbox)
+                .unwrap()
+                .target,
+            owner
+        );
+    }
+
+    #[test]
+    fn pushboxes_of_removed_sets_are_despawned() {
+        let mut world = World::new();
+        let (owner, pushbox) = spawn_pushbox_set(&mut world);
+
+        pushbox_system(&mut world);
+        assert!(world.contains(pushbox));
+
+        world.remove_one::<PushboxSet>(owner).unwrap();
+        pushbox_system(&mut world);
+        assert!(!world.contains(pushbox));
+        assert_eq!(despawn_pushbox_set(&mut world, owner), 0);
+    }
+
+    /// Runs the pushbox part of a frame, between two physics steps.
+    fn run_frame(world: &mut World) {
+        pushbox_system(world);
+        track_targets(world, 0.016);
+        record_pushbox_placements(world);
+    }
+
+    fn x(world: &World, id: Entity) -> f32 {
+        world.get::<&Transform>(id).unwrap().translation.x
+    }
+
+    #[test]
+    fn pushed_pushboxes_move_their_owner() {
+        let mut world = World::new();
+        let (owner, pushbox) = spawn_pushbox_set(&mut world);
+        run_frame(&mut world);
+
+        // The physics step pushes the pushbox out of another body
+        world.get::<&mut Transform>(pushbox).unwrap().translation = Translation::new(3.0, 0.0);
+        run_frame(&mut world);
+
+        assert_eq!(x(&world, owner), 3.0);
+        assert_eq!(x(&world, pushbox), 3.0);
+
+        // Nothing pushed it this time, the owner stays put
+        run_frame(&mut world);
+        assert_eq!(x(&world, owner), 3.0);
+    }
+
+    #[test]
+    fn owners_moving_themselves_are_not_pushed() {
+        let mut world = World::new();
+        let (owner, pushbox) = spawn_pushbox_set(&mut world);
+        run_frame(&mut world);
+
+        world.get::<&mut Transform>(owner).unwrap().translation = Translation::new(5.0, 0.0);
+        run_frame(&mut world);
+
+        assert_eq!(x(&world, owner), 5.0);
+        assert_eq!(x(&world, pushbox), 5.0);
+    }
+}
@@ -1,38 +1,363 @@
 use std::ops::Deref;
 
-use emerald::{Emerald, Entity, Transform, Translation, World};
+use emerald::{Entity, Transform, Translation, World};
 
-use crate::HitmeConfig;
+use crate::{
+    get_facing,
+    hitboxes::{Hitbox, HitboxSet},
+    host::HitmeHost,
+    hurtboxes::{translation_to_vector, vector_to_translation, Hurtbox, RectCollider},
+    HitmeConfig,
+};
 
+/// Keeps a hitbox or hurtbox positioned relative to the entity it tracks, usually its owner.
 #[derive(Clone, Debug)]
-pub(crate) struct SimpleTranslationTracker {
+pub struct SimpleTranslationTracker {
     pub target: Entity,
     pub offset: Translation,
+
+    /// Keyframes of (frame progress, offset), sampled using the progress of the
+    /// target's active sequence frame. Overrides `offset` while the target has an active sequence.
+    pub offset_curve: Option<Vec<(f32, Translation)>>,
+
+    /// Rotates the offset and the colliders around the target by the target's rotation,
+    /// so the tracked entity keeps its position relative to the target's facing.
+    /// Tracked entities that don't track rotation stay upright.
+    pub track_rotation: bool,
+
+    /// Moves toward the tracked position at this rate per second, instead of snapping to it.
+    /// A rate of 10.0 covers roughly a tenth of the remaining distance each 1/100th of a second.
+    pub lerp_speed: Option<f32>,
+
+    /// The facing and rotation the physics colliders were last posed with, see `posed_collider`
+    pub(crate) posed: Option<(f32, f32)>,
 }
-pub(crate) fn tracker_system(emd: &mut Emerald, world: &mut World, config: &HitmeConfig) {
+impl SimpleTranslationTracker {
+    pub fn new(target: Entity, offset: Translation) -> Self {
+        Self {
+            target,
+            offset,
+            offset_curve: None,
+            track_rotation: false,
+            lerp_speed: None,
+            posed: None,
+        }
+    }
+
+    pub fn get_offset(&self) -> Translation {
+        self.offset
+    }
+
+    /// Sets the offset from the target, takes effect on the next `tracker_system` run.
+    pub fn set_offset(&mut self, offset: Translation) {
+        self.offset = offset;
+    }
+
+    /// Sets the keyframes of the offset curve, keyframe times are in the range 0.0 - 1.0.
+    pub fn set_offset_curve(&mut self, mut curve: Vec<(f32, Translation)>) {
+        curve.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        self.offset_curve = Some(curve);
+    }
+
+    /// Returns the offset at the given frame progress.
+    pub fn sample_offset(&self, progress: Option<f32>) -> Translation {
+        match (&self.offset_curve, progress) {
+            (Some(curve), Some(progress)) => sample_curve(curve, progress).unwrap_or(self.offset),
+            _ => self.offset,
+        }
+    }
+
+    /// Returns the transform of the tracking entity, given the target's transform and facing.
+    /// The offset is mirrored horizontally when the target faces left, see `Facing`.
+    pub fn tracked_transform(
+        &self,
+        target_transform: &Transform,
+        facing: f32,
+        progress: Option<f32>,
+    ) -> Transform {
+        let mut offset = self.sample_offset(progress);
+        if facing < 0.0 {
+            offset.x = -offset.x;
+        }
+        if self.track_rotation {
+            offset = rotate_translation(offset, target_transform.rotation);
+        }
+
+        let mut transform = target_transform.clone() + Transform::from_translation(offset);
+        transform.rotation = self.tracked_rotation(target_transform);
+        transform
+    }
+
+    /// The rotation the tracking entity and its colliders take on from the target.
+    pub fn tracked_rotation(&self, target_transform: &Transform) -> f32 {
+        if self.track_rotation {
+            target_transform.rotation
+        } else {
+            0.0
+        }
+    }
+
+    /// Moves the current transform toward the tracked transform, see `lerp_speed`.
+    /// Only the translation is smoothed, everything else snaps to the tracked transform.
+    pub fn follow(&self, current: &Transform, tracked: Transform, delta: f32) -> Transform {
+        let lerp_speed = match self.lerp_speed {
+            Some(lerp_speed) => lerp_speed,
+            None => return tracked,
+        };
+
+        let ratio = (lerp_speed * delta).clamp(0.0, 1.0);
+        let start = translation_to_vector(&current.translation);
+        let end = translation_to_vector(&tracked.translation);
+
+        let mut transform = tracked;
+        transform.translation = vector_to_translation(&(start + (end - start) * ratio));
+        transform
+    }
+}
+
+/// Returns the offset of a tracked box from the entity it tracks.
+pub fn get_box_offset(world: &World, id: Entity) -> Option<Translation> {
+    world
+        .get::<&SimpleTranslationTracker>(id)
+        .ok()
+        .map(|tracker| tracker.get_offset())
+}
+
+/// Repositions a tracked box relative to the entity it tracks.
+pub fn set_box_offset(world: &mut World, id: Entity, offset: Translation) {
+    world
+        .get::<&mut SimpleTranslationTracker>(id)
+        .ok()
+        .map(|mut tracker| tracker.set_offset(offset));
+}
+
+/// Returns the collider as it sits on its body, mirrored horizontally when facing left and
+/// rotated about the body's origin. Bodies follow their target's translation only,
+/// so colliders are posed to turn and flip with it.
+pub(crate) fn posed_collider(collider: &RectCollider, facing: f32, rotation: f32) -> RectCollider {
+    let mut collider = if facing < 0.0 {
+        collider.mirrored()
+    } else {
+        collider.clone()
+    };
+    collider.translation = rotate_translation(collider.translation, rotation);
+    collider.rotation += rotation;
+    collider
+}
+
+/// Poses the physics colliders of a tracked hitbox or hurtbox, see `posed_collider`.
+pub(crate) fn pose_colliders(world: &mut World, id: Entity, facing: f32, rotation: f32) {
+    let colliders = world
+        .get::<&Hitbox>(id)
+        .map(|hitbox| hitbox.physics_colliders())
+        .or_else(|_| {
+            world
+                .get::<&Hurtbox>(id)
+                .map(|hurtbox| hurtbox.physics_colliders())
+        })
+        .unwrap_or_default();
+
+    for (handle, collider) in colliders {
+        let builder = posed_collider(&collider, facing, rotation).to_collider_builder();
+        world.physics().collider_mut(handle).map(|collider| {
+            collider.set_shape(builder.shape.clone());
+            collider.set_position_wrt_parent(builder.position);
+        });
+    }
+}
+
+/// Rotates a translation around the origin by the given rotation, in radians.
+pub(crate) fn rotate_translation(translation: Translation, rotation: f32) -> Translation {
+    let (sin, cos) = rotation.sin_cos();
+    Translation::new(
+        translation.x * cos - translation.y * sin,
+        translation.x * sin + translation.y * cos,
+    )
+}
+
+fn sample_curve(curve: &Vec<(f32, Translation)>, t: f32) -> Option<Translation> {
+    let (first_t, first) = curve.first()?;
+    if t <= *first_t {
+        return Some(*first);
+    }
+
+    for window in curve.windows(2) {
+        let (start_t, start) = window[0];
+        let (end_t, end) = window[1];
+
+        if t <= end_t {
+            let span = end_t - start_t;
+            let ratio = if span > 0.0 {
+                (t - start_t) / span
+            } else {
+                1.0
+            };
+
+            let start = translation_to_vector(&start);
+            let end = translation_to_vector(&end);
+
+            return Some(vector_to_translation(&(start + (end - start) * ratio)));
+        }
+    }
+
+    curve.last().map(|(_, offset)| *offset)
+}
+
+pub(crate) fn tracker_system(host: &mut impl HitmeHost, world: &mut World, config: &HitmeConfig) {
+    let delta = config.get_host_delta(host, world);
+    track_targets(world, delta);
+}
+
+/// Moves tracking entities to their targets, and poses their colliders when the facing
+/// or rotation they take on changes.
+pub(crate) fn track_targets(world: &mut World, delta: f32) {
     let mut to_destroy = Vec::new();
+    let mut to_pose = Vec::new();
     world
-        .query::<(&SimpleTranslationTracker, &mut Transform)>()
+        .query::<(&mut SimpleTranslationTracker, &mut Transform)>()
         .iter()
         .filter_map(|(id, (tracker, transform))| {
             if world.contains(tracker.target) && world.has::<Transform>(tracker.target) {
-                Some((tracker, transform))
+                Some((id, tracker, transform))
             } else {
                 to_destroy.push(id);
                 None
             }
         })
-        .for_each(|(tracker, transform)| {
+        .for_each(|(id, tracker, transform)| {
             let target_transform = world
                 .get::<&Transform>(tracker.target)
                 .unwrap()
                 .deref()
                 .clone();
+            let progress = tracker
+                .offset_curve
+                .as_ref()
+                .map(|_| {
+                    world
+                        .get::<&HitboxSet>(tracker.target)
+                        .ok()
+                        .map(|set| set.current_frame_progress())
+                        .flatten()
+                })
+                .flatten();
 
-            *transform = target_transform + Transform::from_translation(tracker.offset);
+            let facing = get_facing(world, tracker.target);
+            let tracked = tracker.tracked_transform(&target_transform, facing, progress);
+            *transform = tracker.follow(transform, tracked, delta);
+
+            let pose = (facing, tracker.tracked_rotation(&target_transform));
+            if tracker.posed != Some(pose) {
+                tracker.posed = Some(pose);
+                to_pose.push((id, pose));
+            }
         });
 
+    to_pose.into_iter().for_each(|(id, (facing, rotation))| {
+        pose_colliders(world, id, facing, rotation);
+    });
+
     to_destroy.into_iter().for_each(|id| {
         world.despawn(id).ok();
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use emerald::{Transform, Translation, World};
+
+    use crate::tracker::{
+        get_box_offset, rotate_translation, set_box_offset, SimpleTranslationTracker,
+    };
+
+    #[test]
+    fn offset_curve_is_interpolated() {
+        let mut world = World::new();
+        let target = world.spawn(());
+        let mut tracker = SimpleTranslationTracker::new(target, Translation::new(0.0, 0.0));
+        tracker.set_offset_curve(vec![
+            (1.0, Translation::new(10.0, 20.0)),
+            (0.0, Translation::new(0.0, 0.0)),
+        ]);
+
+        let offset = tracker.sample_offset(Some(0.5));
+        assert_eq!(offset.x, 5.0);
+        assert_eq!(offset.y, 10.0);
+
+        let offset = tracker.sample_offset(Some(2.0));
+        assert_eq!(offset.x, 10.0);
+
+        let offset = tracker.sample_offset(None);
+        assert_eq!(offset.x, 0.0);
+    }
+
+    #[test]
+    fn rotating_an_offset_keeps_its_distance() {
+        let offset = rotate_translation(Translation::new(10.0, 0.0), std::f32::consts::FRAC_PI_2);
+
+        assert!(offset.x.abs() < 0.0001);
+        assert!((offset.y - 10.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn rotation_tracking_rotates_the_offset_around_the_target() {
+        let mut world = World::new();
+        let target = world.spawn(());
+        let mut tracker = SimpleTranslationTracker::new(target, Translation::new(10.0, 0.0));
+        let mut target_transform = Transform::from_translation(Translation::new(5.0, 5.0));
+        target_transform.rotation = std::f32::consts::PI;
+
+        let transform = tracker.tracked_transform(&target_transform, 1.0, None);
+        assert_eq!(transform.translation.x, 15.0);
+
+        tracker.track_rotation = true;
+        let transform = tracker.tracked_transform(&target_transform, 1.0, None);
+        assert!((transform.translation.x + 5.0).abs() < 0.0001);
+        assert!((transform.translation.y - 5.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn left_facing_target_mirrors_the_offset() {
+        let mut world = World::new();
+        let target = world.spawn(());
+        let tracker = SimpleTranslationTracker::new(target, Translation::new(10.0, 2.0));
+        let target_transform = Transform::from_translation(Translation::new(5.0, 5.0));
+
+        let transform = tracker.tracked_transform(&target_transform, -1.0, None);
+        assert_eq!(transform.translation.x, -5.0);
+        assert_eq!(transform.translation.y, 7.0);
+    }
+
+    #[test]
+    fn lerping_tracker_converges_on_the_target() {
+        let mut world = World::new();
+        let target = world.spawn(());
+        let mut tracker = SimpleTranslationTracker::new(target, Translation::new(0.0, 0.0));
+        tracker.lerp_speed = Some(10.0);
+        let tracked = Transform::from_translation(Translation::new(100.0, 0.0));
+
+        let mut transform = Transform::default();
+        let mut last_distance = 100.0;
+        for _ in 0..10 {
+            transform = tracker.follow(&transform, tracked.clone(), 0.016);
+            let distance = 100.0 - transform.translation.x;
+
+            assert!(distance < last_distance && distance >= 0.0);
+            last_distance = distance;
+        }
+    }
+
+    #[test]
+    fn box_offset_can_be_changed_at_runtime() {
+        let mut world = World::new();
+        let target = world.spawn(());
+        let tracker = SimpleTranslationTracker::new(target, Translation::new(1.0, 2.0));
+        let id = world.spawn((tracker,));
+
+        set_box_offset(&mut world, id, Translation::new(3.0, 4.0));
+
+        let offset = get_box_offset(&world, id).unwrap();
+        assert_eq!((offset.x, offset.y), (3.0, 4.0));
+        assert!(get_box_offset(&world, target).is_none());
+    }
+}